@@ -12,7 +12,7 @@ use serde::Deserialize;
 
 use crate::{
     base::{Amount, Transaction},
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -180,7 +180,7 @@ impl TryFrom<AlbyRecord> for Transaction {
     }
 }
 
-fn load_alby_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_alby_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut reader = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -203,9 +203,8 @@ static ALBY_CSV: TransactionSource = TransactionSource {
     id: "AlbyCsv",
     label: "Alby (CSV)",
     csv: &[CsvSpec::new(&ALBY_HEADERS)],
-    detect: None,
     load_sync: Some(load_alby_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 fn sats_to_btc_amount(sats: i64) -> Amount {
@@ -7,7 +7,7 @@ use serde::Deserialize;
 
 use crate::{
     base::{Amount, Transaction},
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -105,7 +105,7 @@ impl AlbyHubRecord {
     }
 }
 
-fn load_alby_hub_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_alby_hub_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -140,9 +140,8 @@ static ALBY_HUB_CSV: TransactionSource = TransactionSource {
         "metadata",
         "failureReason",
     ])],
-    detect: None,
     load_sync: Some(load_alby_hub_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 const MSATS_SCALE: u32 = 11;
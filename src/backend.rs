@@ -0,0 +1,158 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::{bitcoin_core_rpc, electrum_server, esplora, etherscan, horizon, solana, xrpl};
+
+/// Connection settings for one of the blockchain APIs used to sync wallet
+/// sources. Unset fields fall back to the defaults of the public service.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct BackendSettings {
+    /// The base URL of the API, for using a self-hosted instance.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) url: Option<String>,
+    /// Sent as `apikey` parameter to Etherscan and as bearer token to others.
+    /// For Bitcoin Core it is `user:password`, the cookie file being used
+    /// when it is not set.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) api_key: Option<String>,
+    /// A proxy for all requests, like `socks5h://127.0.0.1:9050` for Tor.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) proxy: Option<String>,
+    /// The minimum time between two requests, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) min_interval_ms: Option<u64>,
+}
+
+impl BackendSettings {
+    pub(crate) fn url_or<'a>(&'a self, default: &'a str) -> &'a str {
+        self.url.as_deref().unwrap_or(default)
+    }
+
+    pub(crate) fn rate_limiter(&self, default_interval_ms: u64) -> RateLimiter {
+        RateLimiter::new(Duration::from_millis(self.min_interval_ms.unwrap_or(default_interval_ms)))
+    }
+
+    /// Returns an HTTP client using the configured proxy.
+    pub(crate) fn http_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+/// The backend settings of a portfolio, per service.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Backends {
+    /// Esplora API used for Bitcoin, like a self-hosted mempool or electrs.
+    #[serde(default)]
+    pub(crate) esplora: BackendSettings,
+    /// Electrum server used for Bitcoin sources configured to use it.
+    #[serde(default)]
+    pub(crate) electrum: BackendSettings,
+    /// Bitcoin Core node used for Bitcoin Core wallets.
+    #[serde(default)]
+    pub(crate) bitcoin_core: BackendSettings,
+    /// Horizon API used for Stellar.
+    #[serde(default)]
+    pub(crate) horizon: BackendSettings,
+    /// Etherscan-compatible API used for Ethereum.
+    #[serde(default)]
+    pub(crate) etherscan: BackendSettings,
+    /// rippled JSON-RPC API used for the XRP Ledger.
+    #[serde(default)]
+    pub(crate) xrpl: BackendSettings,
+    /// Solana JSON-RPC API used for Solana.
+    #[serde(default)]
+    pub(crate) solana: BackendSettings,
+}
+
+impl Backends {
+    /// Lists the backends with their id, name and default URL, in the order
+    /// used by the UI.
+    pub(crate) fn list(&self) -> [(&'static str, &'static str, &'static str, &BackendSettings); 7] {
+        [
+            ("esplora", "Esplora (Bitcoin)", esplora::DEFAULT_ESPLORA_URL, &self.esplora),
+            ("electrum", "Electrum (Bitcoin)", electrum_server::DEFAULT_ELECTRUM_URL, &self.electrum),
+            ("bitcoin_core", "Bitcoin Core", bitcoin_core_rpc::DEFAULT_BITCOIN_CORE_URL, &self.bitcoin_core),
+            ("horizon", "Horizon (Stellar)", horizon::DEFAULT_HORIZON_URL, &self.horizon),
+            ("etherscan", "Etherscan (Ethereum)", etherscan::DEFAULT_ETHERSCAN_URL, &self.etherscan),
+            ("xrpl", "rippled (XRP)", xrpl::DEFAULT_RIPPLED_URL, &self.xrpl),
+            ("solana", "Solana RPC", solana::DEFAULT_SOLANA_URL, &self.solana),
+        ]
+    }
+
+    /// Returns the settings of the backend with the given id, as returned by
+    /// [`Backends::list`].
+    pub(crate) fn get_mut(&mut self, id: &str) -> Option<&mut BackendSettings> {
+        match id {
+            "esplora" => Some(&mut self.esplora),
+            "electrum" => Some(&mut self.electrum),
+            "bitcoin_core" => Some(&mut self.bitcoin_core),
+            "horizon" => Some(&mut self.horizon),
+            "etherscan" => Some(&mut self.etherscan),
+            "xrpl" => Some(&mut self.xrpl),
+            "solana" => Some(&mut self.solana),
+            _ => None,
+        }
+    }
+}
+
+/// Enforces a minimum interval between requests. Can be shared between
+/// concurrent requests, which are then spread out over time.
+pub(crate) struct RateLimiter {
+    min_interval: Duration,
+    next_call: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(min_interval: Duration) -> Self {
+        Self { min_interval, next_call: Mutex::new(None) }
+    }
+
+    pub(crate) async fn wait(&self) {
+        let delay = {
+            let mut next_call = self.next_call.lock().unwrap();
+            let now = Instant::now();
+            let call = next_call.map_or(now, |next| next.max(now));
+            *next_call = Some(call + self.min_interval);
+            call - now
+        };
+
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_client_accepts_socks_proxy() {
+        let settings = BackendSettings {
+            proxy: Some("socks5h://127.0.0.1:9050".to_owned()),
+            ..Default::default()
+        };
+        assert!(settings.http_client().is_ok());
+    }
+
+    #[test]
+    fn backends_by_id() {
+        let mut backends = Backends::default();
+        let ids: Vec<&str> = backends.list().iter().map(|(id, ..)| *id).collect();
+        for (index, id) in ids.iter().enumerate() {
+            backends.get_mut(id).unwrap().min_interval_ms = Some(index as u64);
+        }
+        for (index, (_, _, _, settings)) in backends.list().iter().enumerate() {
+            assert_eq!(settings.min_interval_ms, Some(index as u64));
+        }
+        assert!(backends.get_mut("unknown").is_none());
+    }
+}
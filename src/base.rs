@@ -4,8 +4,9 @@ use anyhow::Result;
 use chrono::{NaiveDateTime, Duration};
 use serde::{Serialize, Deserialize, Deserializer};
 use rust_decimal::prelude::*;
+use strum::{Display, EnumDiscriminants, EnumString, VariantArray};
 
-use crate::TransactionSource;
+use crate::{LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 /// Maps currencies to their CMC ID
@@ -36,13 +37,17 @@ pub(crate) fn cmc_id(currency: &str) -> i32 {
         ("LUNC", 4172),
         ("MANA", 1966),
         ("MANA (Decentraland)", 1966),
+        ("MATIC", 3890),
         ("NXT", 66),
+        ("POL", 28321),
         ("PPC", 5),
         ("RDD", 118),
         ("SBTC", 2282),
         ("SGB", 12186),
         ("SNT", 1759),
+        ("SOL", 5426),
         ("TON", 11419),
+        ("USD", 2781),
         ("USDC", 3408),
         ("USDT", 825),
         ("XEM", 873),
@@ -60,6 +65,76 @@ pub(crate) fn cmc_id(currency: &str) -> i32 {
     }
 }
 
+/// Maps the network name used by exchanges for deposits and withdrawals, like
+/// "ERC20" or "Arbitrum One", to the blockchain of the transaction. Falls back
+/// to the currency when no network is given.
+pub(crate) fn blockchain_from_network(network: &str, currency: &str) -> String {
+    let network = network.trim();
+    if network.is_empty() {
+        return currency.to_owned();
+    }
+    match network.to_uppercase().as_str() {
+        "BITCOIN" | "BTC" => "BTC",
+        "ETHEREUM" | "ERC20" | "ETH" => "ETH",
+        "ARBITRUM" | "ARBITRUM ONE" | "ARBI" | "ARB" => "ARB",
+        "BASE" => "BASE",
+        "BNB SMART CHAIN" | "BSC" | "BEP20" => "BSC",
+        "LITECOIN" | "LTC" => "LTC",
+        "OPTIMISM" | "OP" => "OP",
+        "POLYGON" | "MATIC" | "POL" => "POL",
+        "SOLANA" | "SOL" | "SPL" => "SOL",
+        "STELLAR" | "XLM" => "XLM",
+        "TRON" | "TRC20" | "TRX" => "TRX",
+        "RIPPLE" | "XRP" => "XRP",
+        other => return other.to_owned(),
+    }.to_owned()
+}
+
+/// Identifies a token by the chain it lives on and its contract address (or
+/// issuer), since unrelated tokens may use the same symbol.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub(crate) struct AssetId {
+    pub chain: String,
+    pub contract: String,
+}
+
+impl AssetId {
+    pub(crate) fn new(chain: &str, contract: &str) -> Self {
+        Self {
+            chain: chain.to_owned(),
+            contract: contract.to_owned(),
+        }
+    }
+
+    /// Returns the currency used for well-known tokens, which are treated the
+    /// same as this currency in other sources.
+    pub(crate) fn known_currency(&self) -> Option<&'static str> {
+        const KNOWN_ASSETS: &[(&str, &str, &str)] = &[
+            ("ethereum", "0x0f5d2fb29fb7d3cfee444a200298f468908cc942", "MANA (Decentraland)"),
+            ("ethereum", "0x111111111117dc0aa78b770fa6a738034120c302", "1INCH (1INCH Token)"),
+            ("ethereum", "0x4fabb145d64652a948d72533023f6e7a623c7c53", "BUSD"),
+            ("ethereum", "0x58b6a8a3302369daec383334672404ee733ab239", "LPT (Livepeer Token)"),
+            ("ethereum", "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "USDC"),
+            ("ethereum", "0xdac17f958d2ee523a2206206994597c13d831ec7", "USDT"),
+            // wrapped ether is treated the same as ether
+            ("arbitrum", "0x82af49447d8a07e3bd95bd0d56f35241523fbab1", "ETH"),
+            ("base", "0x4200000000000000000000000000000000000006", "ETH"),
+            ("ethereum", "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2", "ETH"),
+            ("optimism", "0x4200000000000000000000000000000000000006", "ETH"),
+            ("polygon", "0x7ceb23fd6bc0add59e62ac25578270cff1b9f619", "ETH"),
+            // wrapped SOL is treated the same as SOL
+            ("solana", "So11111111111111111111111111111111111111112", "SOL"),
+            ("solana", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USDC"),
+            ("solana", "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", "USDT"),
+            ("stellar", "GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN", "USDC"),
+            ("stellar", "GBNZILSTVQZ4R7IKQDGHYGY2QXL5QOFJYQMXPKWRRM5PAV7Y4M67AQUA", "AQUA"),
+        ];
+        KNOWN_ASSETS.iter()
+            .find(|(chain, contract, _)| *chain == self.chain && contract.eq_ignore_ascii_case(&self.contract))
+            .map(|(_, _, currency)| *currency)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum GainError {
     InvalidTransactionOrder,    // should only happen in case of a bug
@@ -89,6 +164,9 @@ pub(crate) struct Amount {
     pub currency: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub token_id: Option<String>,
+    /// Identifies the token on its chain, when known.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub asset: Option<AssetId>,
 }
 
 impl Amount {
@@ -97,6 +175,18 @@ impl Amount {
             quantity,
             currency,
             token_id: None,
+            asset: None,
+        }
+    }
+
+    /// Creates an amount of a token, identified by its chain and contract
+    /// address or issuer.
+    pub(crate) fn new_asset(quantity: Decimal, currency: String, asset: AssetId) -> Self {
+        Self {
+            quantity,
+            currency,
+            token_id: None,
+            asset: Some(asset),
         }
     }
 
@@ -105,6 +195,7 @@ impl Amount {
             quantity: Decimal::ONE,
             currency: contract,
             token_id: Some(token_id),
+            asset: None,
         }
     }
 
@@ -117,6 +208,7 @@ impl Amount {
             quantity: Decimal::new(quantity as i64, 8),
             currency: "BTC".to_owned(),
             token_id: None,
+            asset: None,
         }
     }
 
@@ -128,21 +220,30 @@ impl Amount {
         self.quantity.is_zero()
     }
 
+    /// Returns the currency used to track holdings and look up prices.
+    ///
+    /// For tokens identified by their chain and contract, this is the currency
+    /// qualified by chain and contract, unless the token is well-known.
     pub(crate) fn effective_currency(&self) -> Cow<'_, str> {
-        match &self.token_id {
-            Some(token_id) => Cow::Owned(format!("{}:{}", token_id, self.currency)),
-            None => Cow::Borrowed(&self.currency),
+        match (&self.token_id, &self.asset) {
+            (Some(token_id), _) => Cow::Owned(format!("{}:{}", token_id, self.currency)),
+            (None, Some(asset)) => match asset.known_currency() {
+                Some(currency) => Cow::Borrowed(currency),
+                None => Cow::Owned(format!("{} [{}:{}]", self.currency, asset.chain, asset.contract)),
+            },
+            (None, None) => Cow::Borrowed(&self.currency),
         }
     }
 
     pub(crate) fn try_add(&self, amount: &Amount) -> Option<Amount> {
         if self.token_id.is_some() || amount.token_id.is_some() {
             None
-        } else if self.currency == amount.currency {
+        } else if self.currency == amount.currency && self.asset == amount.asset {
             Some(Amount {
                 quantity: self.quantity + amount.quantity,
                 currency: self.currency.clone(),
                 token_id: None,
+                asset: self.asset.clone(),
             })
         } else {
             None
@@ -155,7 +256,7 @@ impl Amount {
     }
 
     pub(crate) fn cmc_id(&self) -> i32 {
-        cmc_id(&self.currency)
+        cmc_id(&self.effective_currency())
     }
 }
 
@@ -211,8 +312,9 @@ pub (crate) fn deserialize_amount<'de, D: Deserializer<'de>>(d: D) -> std::resul
 }
 
 /// Unified transaction type for all exchanges and wallets
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, EnumDiscriminants)]
 #[serde(tag = "type")]
+#[strum_discriminants(name(OperationType), derive(Serialize, Deserialize, Display, EnumString, VariantArray))]
 pub(crate) enum Operation {
     Buy(Amount),
     Sell(Amount),
@@ -291,6 +393,68 @@ impl Operation {
     pub(crate) fn is_trade(&self) -> bool {
         matches!(self, Self::Trade { .. })
     }
+
+    /// Converts the operation to the given type, keeping its amounts.
+    ///
+    /// Returns `None` when the amounts don't fit the requested type, like
+    /// when converting a trade to a send.
+    pub(crate) fn reclassified(&self, operation_type: OperationType) -> Option<Operation> {
+        match self {
+            Operation::Trade { incoming, outgoing } |
+            Operation::Swap { incoming, outgoing } => {
+                let (incoming, outgoing) = (incoming.clone(), outgoing.clone());
+                match operation_type {
+                    OperationType::Trade => Some(Operation::Trade { incoming, outgoing }),
+                    OperationType::Swap => Some(Operation::Swap { incoming, outgoing }),
+                    _ => None,
+                }
+            }
+            Operation::Buy(amount) |
+            Operation::Sell(amount) |
+            Operation::FiatDeposit(amount) |
+            Operation::FiatWithdrawal(amount) |
+            Operation::Fee(amount) |
+            Operation::Receive(amount) |
+            Operation::Send(amount) |
+            Operation::ChainSplit(amount) |
+            Operation::Expense(amount) |
+            Operation::Stolen(amount) |
+            Operation::Lost(amount) |
+            Operation::Burn(amount) |
+            Operation::Income(amount) |
+            Operation::Airdrop(amount) |
+            Operation::Staking(amount) |
+            Operation::Cashback(amount) |
+            Operation::IncomingGift(amount) |
+            Operation::OutgoingGift(amount) |
+            Operation::Spam(amount) => {
+                let amount = amount.clone();
+                match operation_type {
+                    OperationType::Buy => Some(Operation::Buy(amount)),
+                    OperationType::Sell => Some(Operation::Sell(amount)),
+                    OperationType::Trade |
+                    OperationType::Swap => None,
+                    OperationType::FiatDeposit => Some(Operation::FiatDeposit(amount)),
+                    OperationType::FiatWithdrawal => Some(Operation::FiatWithdrawal(amount)),
+                    OperationType::Fee => Some(Operation::Fee(amount)),
+                    OperationType::Receive => Some(Operation::Receive(amount)),
+                    OperationType::Send => Some(Operation::Send(amount)),
+                    OperationType::ChainSplit => Some(Operation::ChainSplit(amount)),
+                    OperationType::Expense => Some(Operation::Expense(amount)),
+                    OperationType::Stolen => Some(Operation::Stolen(amount)),
+                    OperationType::Lost => Some(Operation::Lost(amount)),
+                    OperationType::Burn => Some(Operation::Burn(amount)),
+                    OperationType::Income => Some(Operation::Income(amount)),
+                    OperationType::Airdrop => Some(Operation::Airdrop(amount)),
+                    OperationType::Staking => Some(Operation::Staking(amount)),
+                    OperationType::Cashback => Some(Operation::Cashback(amount)),
+                    OperationType::IncomingGift => Some(Operation::IncomingGift(amount)),
+                    OperationType::OutgoingGift => Some(Operation::OutgoingGift(amount)),
+                    OperationType::Spam => Some(Operation::Spam(amount)),
+                }
+            }
+        }
+    }
 }
 
 /// Unified transaction type for all exchanges and wallets
@@ -304,6 +468,9 @@ pub(crate) struct Transaction {
     pub tx_hash: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub blockchain: Option<String>,
+    /// The address on the other side of a send or receive, when known.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub counterparty: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fee: Option<Amount>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -334,6 +501,7 @@ impl Transaction {
             description: None,
             tx_hash: None,
             blockchain: None,
+            counterparty: None,
             fee: None,
             fee_value: None,
             gain: None,
@@ -403,6 +571,38 @@ impl Transaction {
         }
     }
 
+    pub(crate) fn incoming_outgoing_mut(&mut self) -> (Option<&mut Amount>, Option<&mut Amount>) {
+        match &mut self.operation {
+            Operation::Buy(amount) |
+            Operation::FiatDeposit(amount) |
+            Operation::Receive(amount) |
+            Operation::ChainSplit(amount) |
+            Operation::Income(amount) |
+            Operation::Airdrop(amount) |
+            Operation::Staking(amount) |
+            Operation::Cashback(amount) |
+            Operation::IncomingGift(amount) |
+            Operation::Spam(amount) => {
+                (Some(amount), None)
+            }
+            Operation::Sell(amount) |
+            Operation::FiatWithdrawal(amount) |
+            Operation::Fee(amount) |
+            Operation::Send(amount) |
+            Operation::Expense(amount) |
+            Operation::Stolen(amount) |
+            Operation::Lost(amount) |
+            Operation::Burn(amount) |
+            Operation::OutgoingGift(amount) => {
+                (None, Some(amount))
+            }
+            Operation::Trade { incoming, outgoing } |
+            Operation::Swap { incoming, outgoing } => {
+                (Some(incoming), Some(outgoing))
+            }
+        }
+    }
+
     pub(crate) fn has_incoming(&self) -> bool {
         self.incoming_outgoing().0.is_some()
     }
@@ -502,6 +702,31 @@ impl Transaction {
     }
 }
 
+/// Identifies a transaction in a way that remains valid when the transactions
+/// are loaded again, unlike [`Transaction::index`].
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct TransactionRef {
+    /// The name of the wallet this transaction was loaded from.
+    pub wallet: String,
+    pub timestamp: NaiveDateTime,
+    /// The operation as loaded from the source, before any send/receive
+    /// matching took place.
+    pub operation: Operation,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tx_hash: Option<String>,
+}
+
+impl TransactionRef {
+    pub(crate) fn new(tx: &Transaction, wallet: &str) -> Self {
+        Self {
+            wallet: wallet.to_owned(),
+            timestamp: tx.timestamp,
+            operation: tx.operation.clone(),
+            tx_hash: tx.tx_hash.clone(),
+        }
+    }
+}
+
 pub(crate) fn save_transactions_to_json(transactions: &Vec<Transaction>, output_path: impl AsRef<Path>) -> Result<()> {
     println!("Saving {}", output_path.as_ref().display());
 
@@ -511,7 +736,7 @@ pub(crate) fn save_transactions_to_json(transactions: &Vec<Transaction>, output_
     Ok(())
 }
 
-pub(crate) fn load_transactions_from_json(input_path: &Path) -> Result<Vec<Transaction>> {
+pub(crate) fn load_transactions_from_json(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let json = std::fs::read_to_string(input_path)?;
     let transactions: Vec<Transaction> = serde_json::from_str(&json)?;
     Ok(transactions)
@@ -521,9 +746,7 @@ pub(crate) fn load_transactions_from_json(input_path: &Path) -> Result<Vec<Trans
 static JSON: TransactionSource = TransactionSource {
     id: "Json",
     label: "JSON",
-    csv: &[],
-    detect: None,
     load_sync: Some(load_transactions_from_json),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
@@ -5,7 +5,7 @@ use chrono::{NaiveDateTime, NaiveDate, NaiveTime};
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
-use crate::{time::deserialize_date_time, base::{Amount, Transaction, self, deserialize_amount}, CsvSpec, TransactionSource};
+use crate::{time::deserialize_date_time, base::{Amount, Transaction, self, deserialize_amount}, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 // #[derive(Debug, Deserialize)]
@@ -128,6 +128,7 @@ fn normalize_currency_for_amount(timestamp: NaiveDateTime, amount: Amount) -> Am
         quantity: amount.quantity,
         currency: normalize_currency(timestamp, amount.currency),
         token_id: amount.token_id,
+        asset: amount.asset,
     }
 }
 
@@ -262,7 +263,7 @@ impl From<BinanceConvert> for Transaction {
     }
 }
 
-fn load_binance_transaction_records_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_binance_transaction_records_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -309,7 +310,7 @@ fn load_binance_transaction_records_csv(input_path: &Path) -> Result<Vec<Transac
     Ok(transactions)
 }
 
-fn load_binance_spot_trades_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_binance_spot_trades_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -324,7 +325,7 @@ fn load_binance_spot_trades_csv(input_path: &Path) -> Result<Vec<Transaction>> {
 }
 
 // todo: document custom format
-fn load_binance_convert_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_binance_convert_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -349,9 +350,8 @@ static BINANCE_CONVERT_CSV: TransactionSource = TransactionSource {
         "Fee",
         "Converted To",
     ])],
-    detect: None,
     load_sync: Some(load_binance_convert_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -367,9 +367,8 @@ static BINANCE_SPOT_TRADE_HISTORY_CSV: TransactionSource = TransactionSource {
         "Amount",
         "Fee",
     ])],
-    detect: None,
     load_sync: Some(load_binance_spot_trades_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -385,7 +384,6 @@ static BINANCE_TRANSACTION_HISTORY_CSV: TransactionSource = TransactionSource {
         "Change",
         "Remark",
     ])],
-    detect: None,
     load_sync: Some(load_binance_transaction_records_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
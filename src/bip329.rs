@@ -0,0 +1,128 @@
+use std::{collections::BTreeMap, fs::File, io::{BufRead, BufReader, BufWriter, Write}, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::base::Transaction;
+
+/// A label as defined by BIP-329, stored one per line in a JSON Lines file.
+///
+/// Only transaction labels are used, but labels of other types are accepted
+/// so that any BIP-329 export can be imported.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Bip329Label {
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(rename = "ref")]
+    ref_: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    origin: Option<String>,
+}
+
+/// Reads the transaction labels from a BIP-329 file, by transaction ID.
+pub(crate) fn load_bip329_labels(input_path: &Path) -> Result<BTreeMap<String, String>> {
+    let reader = BufReader::new(File::open(input_path)?);
+
+    let mut labels = BTreeMap::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let label: Bip329Label = serde_json::from_str(&line)
+            .with_context(|| format!("invalid label on line {}", index + 1))?;
+        if label.type_ != "tx" {
+            continue;
+        }
+        match label.label {
+            Some(text) if !text.is_empty() => { labels.insert(label.ref_, text); }
+            _ => {}
+        }
+    }
+
+    Ok(labels)
+}
+
+/// Writes the descriptions of the given Bitcoin transactions as BIP-329
+/// transaction labels. Transactions on other blockchains or without hash or
+/// description are skipped, as are repeated transaction IDs, like the two
+/// sides of a transfer.
+pub(crate) fn save_bip329_labels(transactions: &[Transaction], output_path: &Path) -> Result<usize> {
+    let mut labels: BTreeMap<&str, &str> = BTreeMap::new();
+    for tx in transactions.iter().filter(|tx| tx.blockchain.as_deref() == Some("BTC")) {
+        if let (Some(tx_hash), Some(description)) = (&tx.tx_hash, &tx.description) {
+            labels.entry(tx_hash.as_str()).or_insert(description.as_str());
+        }
+    }
+
+    let mut writer = BufWriter::new(File::create(output_path)?);
+    for (&tx_hash, &description) in &labels {
+        let label = Bip329Label {
+            type_: "tx".to_owned(),
+            ref_: tx_hash.to_owned(),
+            label: Some(description.to_owned()),
+            origin: None,
+        };
+        serde_json::to_writer(&mut writer, &label)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+
+    Ok(labels.len())
+}
+
+/// Sets the description of transactions for which a label was imported.
+pub(crate) fn apply_labels(labels: &BTreeMap<String, String>, transactions: &mut [Transaction]) {
+    if labels.is_empty() {
+        return;
+    }
+
+    for tx in transactions {
+        if let Some(label) = tx.tx_hash.as_ref().and_then(|tx_hash| labels.get(tx_hash)) {
+            tx.description = Some(label.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Amount;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn round_trip_labels() {
+        let labels = load_bip329_labels(Path::new("tests/data/bip329_labels.jsonl")).unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels["f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16"], "Coffee machine");
+
+        let mut transactions = vec![
+            Transaction::receive(parse_date_time("2023-11-02 14:05:00").unwrap(), Amount::new(dec!(0.015), "BTC".to_owned())),
+            Transaction::send(parse_date_time("2023-11-20 09:31:00").unwrap(), Amount::new(dec!(0.005), "BTC".to_owned())),
+            Transaction::send(parse_date_time("2023-11-21 10:00:00").unwrap(), Amount::new(dec!(0.1), "ETH".to_owned())),
+        ];
+        transactions[0].tx_hash = Some("0e3e2357e806b6cdb1f70b54c3a3a17b6714ee1f0e68bebb44a74b1efd512098".to_owned());
+        transactions[1].tx_hash = Some("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16".to_owned());
+        transactions[1].description = Some("Payment".to_owned());
+        transactions[2].tx_hash = Some("0x88df016429689c079f3b2f6ad39fa052532c56795b733da78a91ebe6a713944b".to_owned());
+        transactions[2].description = Some("Gas refill".to_owned());
+        for tx in &mut transactions[..2] {
+            tx.blockchain = Some("BTC".to_owned());
+        }
+        transactions[2].blockchain = Some("ETH".to_owned());
+
+        apply_labels(&labels, &mut transactions);
+        assert_eq!(transactions[0].description, None);
+        assert_eq!(transactions[1].description.as_deref(), Some("Coffee machine"));
+
+        let output_path = std::env::temp_dir().join(format!("raccoin_bip329_labels_{}.jsonl", std::process::id()));
+        // only the Bitcoin transaction is labeled
+        assert_eq!(save_bip329_labels(&transactions, &output_path).unwrap(), 1);
+        assert_eq!(load_bip329_labels(&output_path).unwrap().len(), 1);
+        std::fs::remove_file(output_path).unwrap();
+    }
+}
@@ -8,7 +8,7 @@ use serde::{Deserialize, Deserializer};
 use crate::{
     base::{Amount, Transaction},
     time::deserialize_date_time,
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -179,7 +179,7 @@ impl BisonRecord {
     }
 }
 
-fn load_bison_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_bison_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new()
         .delimiter(b';')
         .trim(csv::Trim::All)
@@ -222,9 +222,8 @@ static BISON_CSV: TransactionSource = TransactionSource {
         skip_lines: 0,
         trim: csv::Trim::All,
     }],
-    detect: None,
     load_sync: Some(load_bison_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[cfg(test)]
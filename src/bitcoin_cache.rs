@@ -0,0 +1,223 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::Result;
+use bitcoin::{BlockHash, ScriptBuf, Txid};
+use esplora_client::{PrevOut, Tx, TxStatus, Vin, Vout};
+use serde::{Deserialize, Serialize};
+
+const CACHE_FILE: &str = "bitcoin_history.json";
+
+/// Serializes saving the cache, since sources may be synced concurrently.
+static SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+/// A confirmed transaction, reduced to what is needed to convert it.
+#[derive(Serialize, Deserialize)]
+struct CachedTx {
+    txid: Txid,
+    block_height: Option<u32>,
+    block_hash: Option<BlockHash>,
+    block_time: Option<u64>,
+    fee: u64,
+    inputs: Vec<CachedInput>,
+    outputs: Vec<CachedOutput>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedInput {
+    txid: Txid,
+    vout: u32,
+    /// The spent output, missing for coinbase inputs.
+    prevout: Option<CachedOutput>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedOutput {
+    value: u64,
+    script: ScriptBuf,
+}
+
+impl From<&Tx> for CachedTx {
+    fn from(tx: &Tx) -> Self {
+        Self {
+            txid: tx.txid,
+            block_height: tx.status.block_height,
+            block_hash: tx.status.block_hash,
+            block_time: tx.status.block_time,
+            fee: tx.fee,
+            inputs: tx.vin.iter().map(|vin| CachedInput {
+                txid: vin.txid,
+                vout: vin.vout,
+                prevout: vin.prevout.as_ref().map(|prevout| CachedOutput {
+                    value: prevout.value,
+                    script: prevout.scriptpubkey.clone(),
+                }),
+            }).collect(),
+            outputs: tx.vout.iter().map(|vout| CachedOutput {
+                value: vout.value,
+                script: vout.scriptpubkey.clone(),
+            }).collect(),
+        }
+    }
+}
+
+impl From<CachedTx> for Tx {
+    fn from(tx: CachedTx) -> Self {
+        Self {
+            txid: tx.txid,
+            version: 0,
+            locktime: 0,
+            vin: tx.inputs.into_iter().map(|input| Vin {
+                txid: input.txid,
+                vout: input.vout,
+                is_coinbase: input.prevout.is_none(),
+                prevout: input.prevout.map(|prevout| PrevOut {
+                    value: prevout.value,
+                    scriptpubkey: prevout.script,
+                }),
+                scriptsig: ScriptBuf::new(),
+                witness: Vec::new(),
+                sequence: 0,
+            }).collect(),
+            vout: tx.outputs.into_iter().map(|output| Vout {
+                value: output.value,
+                scriptpubkey: output.script,
+            }).collect(),
+            size: 0,
+            weight: 0,
+            status: TxStatus {
+                confirmed: true,
+                block_height: tx.block_height,
+                block_hash: tx.block_hash,
+                block_time: tx.block_time,
+            },
+            fee: tx.fee,
+        }
+    }
+}
+
+/// The confirmed history of Bitcoin scripts, kept between syncs so that
+/// only the history of scripts with new activity needs to be fetched.
+///
+/// The history does not depend on the wallet or the backend, so a single
+/// cache is shared by all Bitcoin sources.
+#[derive(Default)]
+pub(crate) struct HistoryCache {
+    path: Option<PathBuf>,
+    scripts: HashMap<ScriptBuf, Vec<Tx>>,
+    /// The scripts of which the history was fetched since loading.
+    updated: HashSet<ScriptBuf>,
+}
+
+fn read_cache_file(path: &Path) -> HashMap<ScriptBuf, Vec<CachedTx>> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            println!("warning: ignoring invalid Bitcoin history cache {}: {}", path.display(), e);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
+}
+
+impl HistoryCache {
+    /// Loads the cache from the given directory. Without a directory, the
+    /// cache is only kept in memory.
+    pub(crate) fn load(dir: Option<&Path>) -> Self {
+        let Some(path) = dir.map(|dir| dir.join(CACHE_FILE)) else {
+            return Self::default();
+        };
+
+        let scripts = read_cache_file(&path).into_iter()
+            .map(|(script, txs)| (script, txs.into_iter().map(Tx::from).collect()))
+            .collect();
+
+        Self { path: Some(path), scripts, updated: HashSet::new() }
+    }
+
+    /// Returns the cached confirmed transactions of the given script, newest
+    /// first.
+    pub(crate) fn get(&self, script: &ScriptBuf) -> &[Tx] {
+        self.scripts.get(script).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Remembers the history of the scripts that were fetched successfully.
+    pub(crate) fn update(&mut self, script_transactions: &HashMap<ScriptBuf, Result<Vec<Tx>>>) {
+        for (script, txs) in script_transactions {
+            match txs {
+                Ok(txs) if !txs.is_empty() => {
+                    self.scripts.insert(script.clone(), txs.clone());
+                    self.updated.insert(script.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Saves the fetched history to the cache file.
+    ///
+    /// Other syncs may have saved the cache since it was loaded, so the
+    /// history fetched since then is merged into the current file.
+    pub(crate) fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let _guard = SAVE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let mut scripts = read_cache_file(path);
+        for script in &self.updated {
+            if let Some(txs) = self.scripts.get(script) {
+                scripts.insert(script.clone(), txs.iter().map(CachedTx::from).collect());
+            }
+        }
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // replace the file at once, so that it is never read half-written
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string(&scripts)?)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn history(script: &ScriptBuf, value: u64) -> HashMap<ScriptBuf, Result<Vec<Tx>>> {
+        let tx = CachedTx {
+            txid: Txid::from_str(&format!("{:064x}", value)).unwrap(),
+            block_height: Some(800_000),
+            block_hash: None,
+            block_time: Some(1_690_000_000),
+            fee: 0,
+            inputs: Vec::new(),
+            outputs: vec![CachedOutput { value, script: script.clone() }],
+        };
+        HashMap::from([(script.clone(), Ok(vec![Tx::from(tx)]))])
+    }
+
+    #[test]
+    fn concurrent_saves_are_merged() {
+        let cache_dir = std::env::temp_dir().join(format!("raccoin_merged_cache_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&cache_dir);
+        let first_script = ScriptBuf::from_hex("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let second_script = ScriptBuf::from_hex("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap();
+
+        // two syncs load the cache before either of them saves it
+        let mut first = HistoryCache::load(Some(&cache_dir));
+        let mut second = HistoryCache::load(Some(&cache_dir));
+        first.update(&history(&first_script, 1000));
+        second.update(&history(&second_script, 2000));
+        first.save().unwrap();
+        second.save().unwrap();
+
+        let cache = HistoryCache::load(Some(&cache_dir));
+        assert_eq!(cache.get(&first_script)[0].vout[0].value, 1000);
+        assert_eq!(cache.get(&second_script)[0].vout[0].value, 2000);
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+    }
+}
@@ -1,14 +1,15 @@
 use std::path::Path;
 
 use anyhow::Result;
-use chrono::{NaiveDateTime, TimeZone};
-use chrono_tz::Europe::Berlin;
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
 use crate::{
     base::{Amount, Transaction, Operation},
-    CsvSpec, TransactionSource,
+    time::local_to_utc,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -41,8 +42,8 @@ struct BitcoinCoreAction<'a> {
 
 impl<'a> BitcoinCoreAction<'a> {
     // todo: translate address?
-    fn to_tx(self, currency: &str) -> Transaction {
-        let utc_time = Berlin.from_local_datetime(&self.date).unwrap().naive_utc();
+    fn to_tx(self, currency: &str, time_zone: Tz) -> Transaction {
+        let utc_time = local_to_utc(self.date, time_zone);
         let mut tx = match self.type_ {
             TransferType::SentTo => {
                 Transaction::send(utc_time, Amount::new(-self.amount, currency.to_owned()))
@@ -62,7 +63,7 @@ impl<'a> BitcoinCoreAction<'a> {
 }
 
 // loads a Bitcoin Core CSV file into a list of unified transactions
-fn load_transactions(input_path: &Path, currency: &str) -> Result<Vec<Transaction>> {
+fn load_transactions(input_path: &Path, currency: &str, time_zone: Tz) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -72,25 +73,25 @@ fn load_transactions(input_path: &Path, currency: &str) -> Result<Vec<Transactio
 
     while rdr.read_record(&mut raw_record)? {
         let record: BitcoinCoreAction = raw_record.deserialize(Some(&headers))?;
-        transactions.push(record.to_tx(currency));
+        transactions.push(record.to_tx(currency, time_zone));
     }
 
     Ok(transactions)
 }
 
 // loads a Bitcoin Core CSV file into a list of unified transactions
-fn load_bitcoin_core_csv(input_path: &Path) -> Result<Vec<Transaction>> {
-    load_transactions(input_path, "BTC")
+fn load_bitcoin_core_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
+    load_transactions(input_path, "BTC", options.time_zone)
 }
 
 // loads a Peercoin CSV file into a list of unified transactions
-fn load_peercoin_csv(input_path: &Path) -> Result<Vec<Transaction>> {
-    load_transactions(input_path, "PPC")
+fn load_peercoin_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
+    load_transactions(input_path, "PPC", options.time_zone)
 }
 
 // loads a Reddcoin Core CSV file into a list of unified transactions
-fn load_reddcoin_core_csv(input_path: &Path) -> Result<Vec<Transaction>> {
-    load_transactions(input_path, "RDD")
+fn load_reddcoin_core_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
+    load_transactions(input_path, "RDD", options.time_zone)
 }
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -106,9 +107,9 @@ static BITCOIN_CORE_CSV: TransactionSource = TransactionSource {
         "Amount (BTC)",
         "ID",
     ])],
-    detect: None,
     load_sync: Some(load_bitcoin_core_csv),
-    load_async: None,
+    local_time: true,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -124,9 +125,9 @@ static PEERCOIN_CSV: TransactionSource = TransactionSource {
         "Amount (PPC)",
         "ID",
     ])],
-    detect: None,
     load_sync: Some(load_peercoin_csv),
-    load_async: None,
+    local_time: true,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -142,7 +143,7 @@ static REDDCOIN_CORE_CSV: TransactionSource = TransactionSource {
         "Amount (RDD)",
         "ID",
     ])],
-    detect: None,
     load_sync: Some(load_reddcoin_core_csv),
-    load_async: None,
+    local_time: true,
+    ..TransactionSource::DEFAULT
 };
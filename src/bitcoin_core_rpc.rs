@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use bitcoincore_rpc::json::{GetTransactionResult, GetTransactionResultDetailCategory};
+use bitcoincore_rpc::{Auth, Client, RpcApi};
+use chrono::DateTime;
+use directories::BaseDirs;
+
+use crate::{
+    backend::BackendSettings,
+    base::{Amount, Operation, Transaction},
+    LoadFuture, SyncOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+pub(crate) const DEFAULT_BITCOIN_CORE_URL: &str = "http://127.0.0.1:8332";
+
+const PAGE_SIZE: usize = 100;
+
+// The cookie file is written to a sub-directory of the data directory for
+// networks other than mainnet, which we recognize by their default RPC port.
+fn default_cookie_file(url: &str) -> Result<PathBuf> {
+    let network_dir = match url.trim_end_matches('/').rsplit(':').next() {
+        Some("18332") => "testnet3",
+        Some("48332") => "testnet4",
+        Some("38332") => "signet",
+        Some("18443") => "regtest",
+        _ => "",
+    };
+
+    let dirs = BaseDirs::new().context("Missing home directory")?;
+    let data_dirs = if cfg!(target_os = "linux") {
+        vec![dirs.home_dir().join(".bitcoin")]
+    } else {
+        // recent versions use the local data directory on Windows
+        vec![dirs.data_local_dir().join("Bitcoin"), dirs.data_dir().join("Bitcoin")]
+    };
+
+    data_dirs.iter()
+        .map(|data_dir| data_dir.join(network_dir).join(".cookie"))
+        .find(|cookie_file| cookie_file.exists())
+        .with_context(|| format!("No cookie file found in {}, configure a user and password instead", data_dirs[0].display()))
+}
+
+fn auth(settings: &BackendSettings, url: &str) -> Result<Auth> {
+    match &settings.api_key {
+        Some(user_pass) => {
+            let (user, pass) = user_pass.split_once(':').context("Expected the API key as user:password")?;
+            Ok(Auth::UserPass(user.to_owned(), pass.to_owned()))
+        }
+        None => Ok(Auth::CookieFile(default_cookie_file(url)?)),
+    }
+}
+
+fn to_transaction(tx: &GetTransactionResult) -> Result<Transaction> {
+    let block_time = tx.info.blocktime.context("transaction is not confirmed")?;
+    let timestamp = DateTime::from_timestamp(block_time as i64, 0).context("invalid block time")?.naive_utc();
+
+    // the amount is the change in balance, excluding the fee paid by this wallet
+    let amount = Amount::from_satoshis(tx.amount.unsigned_abs().to_sat());
+    let fee = tx.fee
+        .map(|fee| Amount::from_satoshis(fee.unsigned_abs().to_sat()))
+        .filter(|fee| !fee.quantity.is_zero());
+
+    // coinbase outputs are reported as immature until they can be spent, and
+    // as orphan when their block is no longer part of the chain
+    if tx.details.iter().any(|detail| detail.category == GetTransactionResultDetailCategory::Orphan) {
+        return Err(anyhow!("orphaned coinbase transaction"));
+    }
+    let coinbase_sats: u64 = tx.details.iter()
+        .filter(|detail| matches!(detail.category, GetTransactionResultDetailCategory::Generate | GetTransactionResultDetailCategory::Immature))
+        .map(|detail| detail.amount.unsigned_abs().to_sat())
+        .sum();
+
+    let mut transaction = if coinbase_sats > 0 {
+        // the amount of an immature coinbase is not yet included in the balance
+        Transaction::new(timestamp, Operation::Income(Amount::from_satoshis(coinbase_sats)))
+    } else if tx.amount.is_positive() {
+        Transaction::receive(timestamp, amount)
+    } else if tx.amount.is_negative() {
+        let mut transaction = Transaction::send(timestamp, amount);
+        transaction.fee = fee;
+        transaction
+    } else {
+        // sent to self, so only the fee is spent
+        Transaction::fee(timestamp, fee.with_context(|| format!("transaction {} does not affect the balance", tx.info.txid))?)
+    };
+
+    let mut labels: Vec<&str> = Vec::new();
+    for label in tx.details.iter().filter_map(|detail| detail.label.as_deref()) {
+        if !label.is_empty() && !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+
+    transaction.description = (!labels.is_empty()).then(|| labels.join(", "));
+    transaction.tx_hash = Some(tx.info.txid.to_string());
+    transaction.blockchain = Some("BTC".to_owned());
+
+    Ok(transaction)
+}
+
+fn wallet_transactions(client: &Client) -> Result<Vec<Transaction>> {
+    // listtransactions returns an entry per output, so collect the unique
+    // confirmed transactions and get their totals from gettransaction
+    let mut txids = Vec::new();
+    let mut seen = HashSet::new();
+    let mut skip = 0;
+
+    loop {
+        let page = client.list_transactions(None, Some(PAGE_SIZE), Some(skip), Some(true))?;
+        for item in &page {
+            if item.info.confirmations > 0 && seen.insert(item.info.txid) {
+                txids.push(item.info.txid);
+            }
+        }
+        if page.len() < PAGE_SIZE {
+            break;
+        }
+        skip += PAGE_SIZE;
+    }
+
+    println!("Bitcoin Core: loading {} transactions", txids.len());
+
+    let mut transactions = Vec::new();
+    for txid in txids {
+        let tx = client.get_transaction(&txid, Some(true))?;
+        match to_transaction(&tx) {
+            Ok(transaction) => transactions.push(transaction),
+            Err(e) => println!("Bitcoin Core: skipping transaction {}: {}", txid, e),
+        }
+    }
+
+    Ok(transactions)
+}
+
+/// Returns the URL for making wallet calls to the given wallet.
+fn wallet_url(url: &str, wallet: &str) -> Result<String> {
+    let mut wallet_url = reqwest::Url::parse(url)?;
+    wallet_url.path_segments_mut()
+        .map_err(|_| anyhow!("Invalid Bitcoin Core URL {}", url))?
+        .pop_if_empty()
+        .extend(["wallet", wallet]);
+    Ok(wallet_url.into())
+}
+
+/// Loads the transactions of the given wallet from the Bitcoin Core node
+/// configured in the backend settings.
+pub(crate) async fn bitcoin_core_wallet_transactions(settings: &BackendSettings, wallet: &str) -> Result<Vec<Transaction>> {
+    if settings.proxy.is_some() {
+        return Err(anyhow!("A proxy is not supported for Bitcoin Core"));
+    }
+
+    let url = settings.url_or(DEFAULT_BITCOIN_CORE_URL);
+    let wallet_url = wallet_url(url, wallet)?;
+    let auth = auth(settings, url)?;
+
+    tokio::task::spawn_blocking(move || {
+        let client = Client::new(&wallet_url, auth)?;
+        wallet_transactions(&client)
+    }).await?
+}
+
+pub(crate) fn load_bitcoin_core_wallet_async(source_path: String, options: SyncOptions) -> LoadFuture {
+    Box::pin(async move {
+        bitcoin_core_wallet_transactions(&options.backends.bitcoin_core, source_path.trim()).await
+    })
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static BITCOIN_CORE_WALLET: TransactionSource = TransactionSource {
+    id: "BitcoinCoreWallet",
+    label: "Bitcoin Core Wallet (RPC)",
+    load_async: Some(load_bitcoin_core_wallet_async),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn sync_wallet_from_node() {
+        let fixture: Value = serde_json::from_str(&std::fs::read_to_string("tests/data/bitcoin_core_rpc.json").unwrap()).unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/wallet/test%20wallet")
+            .match_header("authorization", "Basic dXNlcjpwYXNz") // user:pass
+            .with_body_from_request(move |request| {
+                let request: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+                let result = match request["method"].as_str().unwrap() {
+                    "listtransactions" => fixture["listtransactions"].clone(),
+                    "gettransaction" => fixture["gettransaction"][request["params"][0].as_str().unwrap()].clone(),
+                    method => panic!("unexpected method {}", method),
+                };
+                json!({ "result": result, "error": null, "id": request["id"] }).to_string().into_bytes()
+            })
+            .expect(5)
+            .create_async()
+            .await;
+
+        let settings = BackendSettings {
+            url: Some(server.url()),
+            api_key: Some("user:pass".to_owned()),
+            ..Default::default()
+        };
+        let transactions = bitcoin_core_wallet_transactions(&settings, "test wallet").await.unwrap();
+        mock.assert_async().await;
+
+        // the unconfirmed transaction is skipped
+        assert_eq!(transactions.len(), 4);
+
+        // an immature coinbase is income, though not yet in the balance
+        assert_eq!(transactions[0].operation, Operation::Income(Amount::from_satoshis(5_000_000_000)));
+
+        let receive = &transactions[1];
+        assert_eq!(receive.timestamp, parse_date_time("2023-11-14 22:13:20").unwrap());
+        assert_eq!(receive.operation, Operation::Receive(Amount::from_satoshis(100_000_000)));
+        assert_eq!(receive.description.as_deref(), Some("Savings"));
+
+        let send = &transactions[2];
+        assert_eq!(send.operation, Operation::Send(Amount::from_satoshis(25_000_000)));
+        assert_eq!(send.fee, Some(Amount::from_satoshis(1_410)));
+        assert_eq!(send.tx_hash.as_deref(), Some("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb2"));
+
+        // a payment to an address of the same wallet only costs the fee
+        assert_eq!(transactions[3].operation, Operation::Fee(Amount::from_satoshis(705)));
+    }
+
+    /// Syncs a new wallet with mined and sent coins from a Bitcoin Core node
+    /// running in regtest mode. The node is reached at `RACCOIN_REGTEST_URL`
+    /// (default `http://127.0.0.1:18443`), using the `user:password` in
+    /// `RACCOIN_REGTEST_AUTH` or else the cookie file.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore = "requires a Bitcoin Core node in regtest mode"]
+    async fn sync_wallet_from_regtest_node() {
+        let settings = BackendSettings {
+            url: Some(std::env::var("RACCOIN_REGTEST_URL").unwrap_or_else(|_| "http://127.0.0.1:18443".to_owned())),
+            api_key: std::env::var("RACCOIN_REGTEST_AUTH").ok(),
+            ..Default::default()
+        };
+        let url = settings.url_or(DEFAULT_BITCOIN_CORE_URL).to_owned();
+        let auth = auth(&settings, &url).unwrap();
+
+        // the space in the name needs to be encoded in the URL
+        let wallet = format!("raccoin test {}", std::process::id());
+        let setup_wallet = wallet.clone();
+        tokio::task::spawn_blocking(move || {
+            Client::new(&url, auth.clone()).unwrap().create_wallet(&setup_wallet, None, None, None, None).unwrap();
+            let client = Client::new(&wallet_url(&url, &setup_wallet).unwrap(), auth).unwrap();
+
+            // after 101 blocks only the first coinbase is mature and can be spent
+            let address = client.get_new_address(None, None).unwrap().assume_checked();
+            client.generate_to_address(101, &address).unwrap();
+            let own_address = client.get_new_address(None, None).unwrap().assume_checked();
+            let one_btc = bitcoincore_rpc::bitcoin::Amount::from_sat(100_000_000);
+            client.send_to_address(&own_address, one_btc, None, None, None, None, None, None).unwrap();
+            client.generate_to_address(1, &address).unwrap();
+        }).await.unwrap();
+
+        let transactions = bitcoin_core_wallet_transactions(&settings, &wallet).await.unwrap();
+
+        // each coinbase is income once, whether mature or not
+        let income = transactions.iter().filter(|tx| matches!(tx.operation, Operation::Income(_))).count();
+        assert_eq!(income, 102);
+        assert!(!transactions.iter().any(|tx| tx.operation.is_receive()));
+
+        // sending to an own address only costs the fee
+        assert_eq!(transactions.len(), 103);
+        assert!(transactions.iter().any(|tx| matches!(tx.operation, Operation::Fee(_))));
+    }
+}
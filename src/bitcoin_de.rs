@@ -1,12 +1,12 @@
 use std::{path::Path, fs::File, io::BufReader};
 
 use anyhow::Result;
-use chrono::{NaiveDateTime, TimeZone};
-use chrono_tz::Europe::Berlin;
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
-use crate::{time::deserialize_date_time, base::{Transaction, Amount, Operation}, TransactionSource};
+use crate::{time::{deserialize_date_time, local_to_utc}, base::{Transaction, Amount, Operation}, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 #[derive(Debug, Deserialize)]
@@ -61,61 +61,59 @@ struct BitcoinDeAction {
     pub incoming_outgoing: Decimal,
 }
 
-impl TryFrom<BitcoinDeAction> for Transaction {
-    type Error = &'static str;
-
+impl BitcoinDeAction {
     // todo: take trading fee into account?
     // todo: translate btc_address?
-    fn try_from(item: BitcoinDeAction) -> Result<Self, Self::Error> {
-        let utc_time = Berlin.from_local_datetime(&item.date).unwrap().naive_utc();
-        let currency = item.currency.clone();
-        let mut tx = match item.type_ {
+    fn into_transaction(self, time_zone: Tz) -> Result<Transaction, &'static str> {
+        let utc_time = local_to_utc(self.date, time_zone);
+        let currency = self.currency.clone();
+        let mut tx = match self.type_ {
             BitcoinDeActionType::Registration => {
                 Err("Registration is not a transaction")
             }
             BitcoinDeActionType::Purchase => {
                 Ok(Transaction::trade(
                     utc_time,
-                    Amount::new(item.incoming_outgoing, currency),
-                    Amount::new(item.amount_after_bitcoin_de_fee.expect("Purchase should have an amount"), item.unit_amount_after_bitcoin_de_fee),
+                    Amount::new(self.incoming_outgoing, currency),
+                    Amount::new(self.amount_after_bitcoin_de_fee.expect("Purchase should have an amount"), self.unit_amount_after_bitcoin_de_fee),
                 ))
             }
             BitcoinDeActionType::Disbursement => {
-                Ok(Transaction::send(utc_time, Amount::new(-item.incoming_outgoing, currency)))
+                Ok(Transaction::send(utc_time, Amount::new(-self.incoming_outgoing, currency)))
             }
             BitcoinDeActionType::Deposit => {
-                Ok(Transaction::receive(utc_time, Amount::new(item.incoming_outgoing, currency)))
+                Ok(Transaction::receive(utc_time, Amount::new(self.incoming_outgoing, currency)))
             }
             BitcoinDeActionType::Sale => {
                 Ok(Transaction::trade(
                     utc_time,
-                    Amount::new(item.amount_after_bitcoin_de_fee.expect("Sale should have an amount"), item.unit_amount_after_bitcoin_de_fee),
-                    Amount::new(-item.incoming_outgoing, currency,),
+                    Amount::new(self.amount_after_bitcoin_de_fee.expect("Sale should have an amount"), self.unit_amount_after_bitcoin_de_fee),
+                    Amount::new(-self.incoming_outgoing, currency,),
                 ))
             }
             BitcoinDeActionType::NetworkFee => {
-                Ok(Transaction::fee(utc_time, Amount::new(-item.incoming_outgoing, currency)))
+                Ok(Transaction::fee(utc_time, Amount::new(-self.incoming_outgoing, currency)))
             }
             BitcoinDeActionType::PartnerProgramme => {
                 // Partner programme transactions are treated as income (free coins received)
                 // This is typically a referral bonus or similar promotional reward
-                Ok(Transaction::new(utc_time, Operation::Income(Amount::new(item.incoming_outgoing, currency))))
+                Ok(Transaction::new(utc_time, Operation::Income(Amount::new(self.incoming_outgoing, currency))))
             }
         }?;
-        match item.type_ {
+        match self.type_ {
             BitcoinDeActionType::Registration => unreachable!(),
             BitcoinDeActionType::Purchase |
             BitcoinDeActionType::Sale => {
-                tx.description = Some(item.reference);
+                tx.description = Some(self.reference);
             }
             BitcoinDeActionType::Disbursement |
             BitcoinDeActionType::Deposit |
             BitcoinDeActionType::NetworkFee => {
-                tx.tx_hash = Some(item.reference);
-                tx.blockchain = Some(item.currency);
+                tx.tx_hash = Some(self.reference);
+                tx.blockchain = Some(self.currency);
             }
             BitcoinDeActionType::PartnerProgramme => {
-                tx.description = Some(format!("Partner programme: {}", item.reference));
+                tx.description = Some(format!("Partner programme: {}", self.reference));
             }
         };
         Ok(tx)
@@ -146,7 +144,7 @@ pub(crate) fn is_bitcoin_de_csv(path: &Path) -> Result<bool> {
 }
 
 // loads a bitcoin.de CSV file into a list of unified transactions
-fn load_bitcoin_de_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_bitcoin_de_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -155,7 +153,7 @@ fn load_bitcoin_de_csv(input_path: &Path) -> Result<Vec<Transaction>> {
 
     for result in rdr.deserialize() {
         let record: BitcoinDeAction = result?;
-        match Transaction::try_from(record) {
+        match record.into_transaction(options.time_zone) {
             Ok(tx) => transactions.push(tx),
             Err(_) => continue,  // Skip non-transaction records like Registration
         };
@@ -197,10 +195,10 @@ fn load_bitcoin_de_csv(input_path: &Path) -> Result<Vec<Transaction>> {
 static BITCOIN_DE_CSV: TransactionSource = TransactionSource {
     id: "BitcoinDeCsv",
     label: "bitcoin.de (CSV)",
-    csv: &[],
     detect: Some(is_bitcoin_de_csv),
     load_sync: Some(load_bitcoin_de_csv),
-    load_async: None,
+    local_time: true,
+    ..TransactionSource::DEFAULT
 };
 
 #[cfg(test)]
@@ -223,7 +221,7 @@ mod tests {
             // Verify header detection works
             assert!(is_bitcoin_de_csv(path).unwrap(), "Failed to detect {} format", format_name);
 
-            let transactions = load_bitcoin_de_csv(path).unwrap();
+            let transactions = load_bitcoin_de_csv(path, &LoadOptions { time_zone: chrono_tz::Europe::Berlin, account: None, custom_csv: None }).unwrap();
             assert_eq!(transactions.len(), 5, "Wrong transaction count for {} format", format_name); // 6 records - 1 merged fee = 5 (Registration filtered out)
 
             // Check the purchase trade (transaction 0)
@@ -1,12 +1,12 @@
 use std::path::Path;
 
 use anyhow::Result;
-use chrono::{NaiveDateTime, TimeZone};
-use chrono_tz::Europe::Berlin;
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
-use crate::{base::{Transaction, Operation, Amount}, time::deserialize_date_time, CsvSpec, TransactionSource};
+use crate::{base::{Transaction, Operation, Amount}, time::{deserialize_date_time, local_to_utc}, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -27,22 +27,22 @@ struct BitonicAction {
     pub price: Decimal,
 }
 
-impl From<BitonicAction> for Transaction {
-    fn from(item: BitonicAction) -> Self {
-        let utc_time = Berlin.from_local_datetime(&item.date).unwrap().naive_utc();
-        match item.action {
+impl BitonicAction {
+    fn into_transaction(self, time_zone: Tz) -> Transaction {
+        let utc_time = local_to_utc(self.date, time_zone);
+        match self.action {
             BitonicActionType::Buy => {
                 Transaction::trade(
                     utc_time,
-                    Amount::new(item.amount, "BTC".to_owned()),
-                    Amount::new(-item.price, "EUR".to_owned()),
+                    Amount::new(self.amount, "BTC".to_owned()),
+                    Amount::new(-self.price, "EUR".to_owned()),
                 )
             }
             BitonicActionType::Sell => {
                 Transaction::trade(
                     utc_time,
-                    Amount::new(item.price, "EUR".to_owned()),
-                    Amount::new(-item.amount, "BTC".to_owned()),
+                    Amount::new(self.price, "EUR".to_owned()),
+                    Amount::new(-self.amount, "BTC".to_owned()),
                 )
             }
         }
@@ -51,7 +51,7 @@ impl From<BitonicAction> for Transaction {
 
 // loads a bitonic CSV file into a list of unified transactions
 // todo: document custom format
-fn load_bitonic_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_bitonic_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -59,7 +59,7 @@ fn load_bitonic_csv(input_path: &Path) -> Result<Vec<Transaction>> {
 
     for result in rdr.deserialize() {
         let record: BitonicAction = result?;
-        let transaction: Transaction = record.into();
+        let transaction = record.into_transaction(options.time_zone);
 
         // Since Bitonic does not hold any fiat or crypto, we add dummy deposit and send transactions
         // for each buy/sell transaction.
@@ -95,7 +95,7 @@ static BITONIC_CSV: TransactionSource = TransactionSource {
     id: "BitonicCsv",
     label: "Bitonic (CSV)",
     csv: &[CsvSpec::new(&["Date", "Action", "Amount", "Price"])],
-    detect: None,
     load_sync: Some(load_bitonic_csv),
-    load_async: None,
+    local_time: true,
+    ..TransactionSource::DEFAULT
 };
@@ -0,0 +1,199 @@
+use std::{fs::File, io::{BufRead, BufReader}, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, FixedOffset};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    base::{Amount, Operation, Transaction},
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+// The header is preceded by a disclaimer, the license agreement, the user ID,
+// the account creation and export dates and an empty line.
+const BITPANDA_CSV_SPEC: CsvSpec = CsvSpec {
+    headers: &[
+        "Transaction ID",
+        "Timestamp",
+        "Transaction Type",
+        "In/Out",
+        "Amount Fiat",
+        "Fiat",
+        "Amount Asset",
+        "Asset",
+        "Asset market price",
+        "Asset market price currency",
+        "Asset class",
+        "Product ID",
+        "Fee",
+        "Fee asset",
+        "Spread",
+        "Spread Currency",
+        "Tax Fiat",
+    ],
+    delimiters: b",",
+    skip_lines: 6,
+    trim: csv::Trim::None,
+};
+
+#[derive(Debug, Deserialize)]
+enum BitpandaDirection {
+    #[serde(rename = "incoming")]
+    Incoming,
+    #[serde(rename = "outgoing")]
+    Outgoing,
+}
+
+// Missing values are exported as "-".
+#[derive(Debug, Deserialize)]
+struct BitpandaTransaction {
+    #[serde(rename = "Transaction ID")]
+    transaction_id: String,
+    #[serde(rename = "Timestamp")]
+    timestamp: DateTime<FixedOffset>,
+    #[serde(rename = "Transaction Type")]
+    type_: String,
+    #[serde(rename = "In/Out")]
+    direction: BitpandaDirection,
+    #[serde(rename = "Amount Fiat", deserialize_with = "deserialize_optional_decimal")]
+    amount_fiat: Option<Decimal>,
+    #[serde(rename = "Fiat")]
+    fiat: String,
+    #[serde(rename = "Amount Asset", deserialize_with = "deserialize_optional_decimal")]
+    amount_asset: Option<Decimal>,
+    #[serde(rename = "Asset")]
+    asset: String,
+    #[serde(rename = "Asset class")]
+    asset_class: String,
+    #[serde(rename = "Fee", deserialize_with = "deserialize_optional_decimal")]
+    fee: Option<Decimal>,
+    #[serde(rename = "Fee asset")]
+    fee_asset: String,
+}
+
+fn deserialize_optional_decimal<'de, D: Deserializer<'de>>(d: D) -> std::result::Result<Option<Decimal>, D::Error> {
+    let raw: &str = Deserialize::deserialize(d)?;
+    match raw.trim() {
+        "" | "-" => Ok(None),
+        value => value.parse::<Decimal>()
+            .or_else(|_| Decimal::from_scientific(value))
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+impl BitpandaTransaction {
+    fn into_transaction(self) -> Result<Option<Transaction>> {
+        let timestamp = self.timestamp.naive_utc();
+        let fiat = || -> Result<Amount> {
+            Ok(Amount::new(self.amount_fiat.context("missing fiat amount")?, self.fiat.clone()))
+        };
+        let asset = || -> Result<Amount> {
+            Ok(Amount::new(self.amount_asset.context("missing asset amount")?, self.asset.clone()))
+        };
+        let is_fiat = self.asset_class == "Fiat";
+
+        let operation = match (self.type_.as_str(), &self.direction) {
+            // savings plan executions are exported as regular buys
+            ("buy", _) => Operation::Trade { incoming: asset()?, outgoing: fiat()? },
+            ("sell", _) => Operation::Trade { incoming: fiat()?, outgoing: asset()? },
+            ("deposit", _) if is_fiat => Operation::FiatDeposit(fiat()?),
+            ("deposit", _) => Operation::Receive(asset()?),
+            ("withdrawal", _) if is_fiat => Operation::FiatWithdrawal(fiat()?),
+            ("withdrawal", _) => Operation::Send(asset()?),
+            ("reward", _) => Operation::Staking(asset()?),
+            // moving funds to and from staking does not change the holdings
+            ("transfer(stake)" | "transfer(unstake)", _) => return Ok(None),
+            ("transfer", BitpandaDirection::Incoming) => Operation::Receive(asset()?),
+            ("transfer", BitpandaDirection::Outgoing) => Operation::Send(asset()?),
+            (type_, _) => return Err(anyhow!("unsupported transaction type '{}'", type_)),
+        };
+
+        let mut tx = Transaction::new(timestamp, operation);
+        tx.fee = match self.fee {
+            Some(fee) if !fee.is_zero() => Some(Amount::new(fee, self.fee_asset.clone())),
+            _ => None,
+        };
+        if !tx.operation.is_trade() && !is_fiat && self.fiat == "EUR" {
+            tx.value = self.amount_fiat.map(Amount::from_fiat);
+        }
+        tx.description = Some(self.transaction_id);
+        Ok(Some(tx))
+    }
+}
+
+// loads a Bitpanda transaction history CSV file into a list of unified transactions
+fn load_bitpanda_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut buf_reader = BufReader::new(File::open(input_path)?);
+    let mut line = String::new();
+    for _ in 0..BITPANDA_CSV_SPEC.skip_lines {
+        buf_reader.read_line(&mut line)?;
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_reader(buf_reader);
+
+    let mut transactions = Vec::new();
+    for result in rdr.deserialize() {
+        let record: BitpandaTransaction = result?;
+        let id = record.transaction_id.clone();
+        match record.into_transaction() {
+            Ok(Some(tx)) => transactions.push(tx),
+            Ok(None) => {}
+            Err(e) => println!("Bitpanda: skipping transaction {}: {}", id, e),
+        }
+    }
+
+    // the export lists the most recent transactions first
+    transactions.sort_by_key(|tx| tx.timestamp);
+
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static BITPANDA_CSV: TransactionSource = TransactionSource {
+    id: "BitpandaCsv",
+    label: "Bitpanda (CSV)",
+    csv: &[BITPANDA_CSV_SPEC],
+    load_sync: Some(load_bitpanda_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn load_transaction_history() {
+        let path = Path::new("tests/data/bitpanda.csv");
+        assert!(BITPANDA_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_bitpanda_csv(path, &LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None }).unwrap();
+        assert_eq!(transactions.len(), 6);
+
+        assert_eq!(transactions[0].operation, Operation::FiatDeposit(Amount::new(dec!(100.00), "EUR".to_owned())));
+
+        let savings_plan = &transactions[1];
+        assert_eq!(savings_plan.timestamp, parse_date_time("2024-03-01 07:00:05").unwrap());
+        assert_eq!(savings_plan.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.00042), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(25.00), "EUR".to_owned()),
+        });
+        assert_eq!(savings_plan.fee, Some(Amount::new(dec!(0.0123), "BEST".to_owned())));
+
+        let reward = &transactions[2];
+        assert_eq!(reward.operation, Operation::Staking(Amount::new(dec!(0.0015), "ETH".to_owned())));
+        assert_eq!(reward.value, Some(Amount::from_fiat(dec!(4.80))));
+
+        assert_eq!(transactions[3].operation, Operation::Trade {
+            incoming: Amount::new(dec!(30.10), "EUR".to_owned()),
+            outgoing: Amount::new(dec!(0.01), "ETH".to_owned()),
+        });
+        assert_eq!(transactions[4].operation, Operation::Send(Amount::new(dec!(0.0002), "BTC".to_owned())));
+        assert_eq!(transactions[5].operation, Operation::FiatWithdrawal(Amount::new(dec!(20.00), "EUR".to_owned())));
+    }
+}
@@ -7,7 +7,7 @@ use serde::{Deserialize, Deserializer};
 
 use crate::{
     base::{deserialize_amount, Amount, Operation, Transaction},
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -276,7 +276,7 @@ impl BitstampTransactionsConverter {
     }
 }
 
-fn load_bitstamp_old_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_bitstamp_old_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut converter = BitstampTransactionsConverter::new();
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
 
@@ -288,7 +288,7 @@ fn load_bitstamp_old_csv(input_path: &Path) -> Result<Vec<Transaction>> {
     Ok(converter.finish())
 }
 
-fn load_bitstamp_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_bitstamp_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut converter = BitstampTransactionsConverter::new();
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
 
@@ -307,9 +307,8 @@ static BITSTAMP_CSV: TransactionSource = TransactionSource {
     csv: &[CsvSpec::new(&[
         "Type", "Datetime", "Account", "Amount", "Value", "Rate", "Fee", "Sub Type",
     ])],
-    detect: None,
     load_sync: Some(load_bitstamp_old_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -332,7 +331,6 @@ static BITSTAMP_CSV_NEW: TransactionSource = TransactionSource {
         "Fee currency",
         "Order ID",
     ])],
-    detect: None,
     load_sync: Some(load_bitstamp_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
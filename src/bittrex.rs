@@ -5,7 +5,7 @@ use chrono::{NaiveDateTime, NaiveDate, NaiveTime};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Deserializer};
 
-use crate::{base::{Transaction, Amount}, CsvSpec, TransactionSource};
+use crate::{base::{Transaction, Amount}, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 // deserialize function for reading NaiveDateTime
@@ -120,7 +120,7 @@ impl From<BittrexTransaction> for Transaction {
 }
 
 // loads a Bittrex Order History CSV file into a list of unified transactions
-fn load_bittrex_order_history_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_bittrex_order_history_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -134,7 +134,7 @@ fn load_bittrex_order_history_csv(input_path: &Path) -> Result<Vec<Transaction>>
 }
 
 // loads a Bittrex Transaction History CSV file into a list of unified transactions
-fn load_bittrex_transaction_history_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_bittrex_transaction_history_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -154,9 +154,8 @@ static BITTREX_ORDER_HISTORY_CSV: TransactionSource = TransactionSource {
     csv: &[CsvSpec::new(&[
         "Date", "Market", "Side", "Type", "Price", "Quantity", "Total",
     ])],
-    detect: None,
     load_sync: Some(load_bittrex_order_history_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -166,7 +165,6 @@ static BITTREX_TRANSACTION_HISTORY_CSV: TransactionSource = TransactionSource {
     csv: &[CsvSpec::new(&[
         "Date", "Currency", "Type", "Address", "Memo/Tag", "TxId", "Amount",
     ])],
-    detect: None,
     load_sync: Some(load_bittrex_transaction_history_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
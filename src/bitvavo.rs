@@ -0,0 +1,188 @@
+use std::{path::Path, str::FromStr};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    base::{Amount, Operation, Transaction},
+    time::local_to_utc,
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+const BITVAVO_HEADERS: &[&str] = &[
+    "Timezone",
+    "Date",
+    "Time",
+    "Type",
+    "Currency",
+    "Amount",
+    "Quote Currency",
+    "Quote Price",
+    "Received / Paid Currency",
+    "Received / Paid Amount",
+    "Fee currency",
+    "Fee amount",
+    "Status",
+    "Transaction ID",
+    "Address",
+];
+
+// Amounts are signed: positive when received and negative when paid.
+#[derive(Debug, Deserialize)]
+struct BitvavoTransaction {
+    #[serde(rename = "Timezone")]
+    timezone: String,
+    #[serde(rename = "Date")]
+    date: NaiveDate,
+    #[serde(rename = "Time")]
+    time: NaiveTime,
+    #[serde(rename = "Type")]
+    type_: String,
+    #[serde(rename = "Currency")]
+    currency: String,
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+    #[serde(rename = "Quote Currency")]
+    quote_currency: Option<String>,
+    #[serde(rename = "Quote Price")]
+    quote_price: Option<Decimal>,
+    #[serde(rename = "Received / Paid Currency")]
+    received_paid_currency: Option<String>,
+    #[serde(rename = "Received / Paid Amount")]
+    received_paid_amount: Option<Decimal>,
+    #[serde(rename = "Fee currency")]
+    fee_currency: Option<String>,
+    #[serde(rename = "Fee amount")]
+    fee_amount: Option<Decimal>,
+    #[serde(rename = "Status")]
+    status: String,
+    #[serde(rename = "Transaction ID")]
+    transaction_id: String,
+    #[serde(rename = "Address")]
+    address: Option<String>,
+}
+
+impl BitvavoTransaction {
+    fn into_transaction(self, default_time_zone: Tz) -> Result<Transaction> {
+        // the date and time are in the time zone given with each row
+        let time_zone = match Tz::from_str(&self.timezone) {
+            Ok(time_zone) => time_zone,
+            Err(_) => {
+                println!("warning: unknown time zone '{}' in Bitvavo export, using {}", self.timezone, default_time_zone);
+                default_time_zone
+            }
+        };
+        let timestamp = local_to_utc(self.date.and_time(self.time), time_zone);
+
+        let amount = Amount::new(self.amount.abs(), self.currency.clone());
+        let is_fiat = amount.is_fiat();
+        let counter_amount = || -> Result<Amount> {
+            let currency = self.received_paid_currency.clone().context("missing received / paid currency")?;
+            let quantity = self.received_paid_amount.context("missing received / paid amount")?;
+            Ok(Amount::new(quantity.abs(), currency))
+        };
+
+        let operation = match self.type_.as_str() {
+            "buy" => Operation::Trade { incoming: amount, outgoing: counter_amount()? },
+            "sell" => Operation::Trade { incoming: counter_amount()?, outgoing: amount },
+            "deposit" if is_fiat => Operation::FiatDeposit(amount),
+            "deposit" => Operation::Receive(amount),
+            "withdrawal" if is_fiat => Operation::FiatWithdrawal(amount),
+            "withdrawal" => Operation::Send(amount),
+            "staking" | "fixed_staking" => Operation::Staking(amount),
+            "rebate" => Operation::Cashback(amount),
+            "affiliate" => Operation::Income(amount),
+            type_ => return Err(anyhow!("unsupported transaction type '{}'", type_)),
+        };
+
+        let mut tx = Transaction::new(timestamp, operation);
+        tx.fee = match (self.fee_amount, self.fee_currency) {
+            (Some(fee), Some(currency)) if !fee.is_zero() => Some(Amount::new(fee.abs(), currency)),
+            _ => None,
+        };
+        if !is_fiat && self.quote_currency.as_deref() == Some("EUR") {
+            tx.value = self.quote_price.map(|price| Amount::from_fiat(price * self.amount.abs()));
+        }
+        tx.description = Some(self.transaction_id);
+        tx.counterparty = self.address.filter(|address| !address.is_empty());
+        Ok(tx)
+    }
+}
+
+// loads a Bitvavo account transaction export into a list of unified transactions
+fn load_bitvavo_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions = Vec::new();
+    for result in rdr.deserialize() {
+        let record: BitvavoTransaction = result?;
+        if record.status != "Completed" {
+            continue;
+        }
+
+        let id = record.transaction_id.clone();
+        match record.into_transaction(options.time_zone) {
+            Ok(tx) => transactions.push(tx),
+            Err(e) => println!("Bitvavo: skipping transaction {}: {}", id, e),
+        }
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static BITVAVO_CSV: TransactionSource = TransactionSource {
+    id: "BitvavoCsv",
+    label: "Bitvavo (CSV)",
+    csv: &[CsvSpec::new(BITVAVO_HEADERS)],
+    load_sync: Some(load_bitvavo_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn load_account_transactions() {
+        let path = Path::new("tests/data/bitvavo.csv");
+        assert!(BITVAVO_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_bitvavo_csv(path, &LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None }).unwrap();
+        assert_eq!(transactions.len(), 6);
+
+        assert_eq!(transactions[0].operation, Operation::FiatDeposit(Amount::new(dec!(250), "EUR".to_owned())));
+
+        let buy = &transactions[1];
+        assert_eq!(buy.timestamp, parse_date_time("2024-02-01 09:15:30").unwrap());
+        assert_eq!(buy.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.0025), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(99.75), "EUR".to_owned()),
+        });
+        assert_eq!(buy.fee, Some(Amount::new(dec!(0.25), "EUR".to_owned())));
+
+        assert_eq!(transactions[2].operation, Operation::Staking(Amount::new(dec!(0.00012), "ETH".to_owned())));
+        assert_eq!(transactions[2].value, Some(Amount::from_fiat(dec!(0.264))));
+
+        assert_eq!(transactions[3].operation, Operation::Trade {
+            incoming: Amount::new(dec!(49.5), "EUR".to_owned()),
+            outgoing: Amount::new(dec!(0.001), "BTC".to_owned()),
+        });
+
+        let withdrawal = &transactions[4];
+        assert_eq!(withdrawal.operation, Operation::Send(Amount::new(dec!(0.0005), "BTC".to_owned())));
+        assert_eq!(withdrawal.fee, Some(Amount::new(dec!(0.00002), "BTC".to_owned())));
+        assert_eq!(withdrawal.counterparty.as_deref(), Some("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"));
+
+        assert_eq!(transactions[5].operation, Operation::FiatWithdrawal(Amount::new(dec!(40), "EUR".to_owned())));
+    }
+}
@@ -7,7 +7,7 @@ use serde::{Deserialize, Deserializer};
 
 use crate::{
     base::{Amount, Operation, Transaction},
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -48,9 +48,8 @@ static BLINK_CSV: TransactionSource = TransactionSource {
     id: "BlinkCsv",
     label: "Blink (CSV)",
     csv: &[CsvSpec::new(&BLINK_HEADERS)],
-    detect: None,
     load_sync: Some(load_blink_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -292,7 +291,7 @@ enum Direction {
     Outgoing,
 }
 
-fn load_blink_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_blink_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut reader = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut records = Vec::new();
 
@@ -0,0 +1,315 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    base::{blockchain_from_network, Amount, Operation, Transaction},
+    time::deserialize_date_time,
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+// Spot pairs are written without separator, like "BTCUSDT", so the quote
+// currency is recognized by its suffix.
+const QUOTE_CURRENCIES: &[&str] = &["USDT", "USDC", "USDE", "DAI", "EUR", "BRL", "TRY", "BTC", "ETH"];
+
+fn split_pair(pair: &str) -> Option<(&str, &str)> {
+    QUOTE_CURRENCIES.iter().find_map(|quote| {
+        pair.strip_suffix(quote)
+            .filter(|base| !base.is_empty())
+            .map(|base| (base, *quote))
+    })
+}
+
+#[derive(Debug, Deserialize)]
+enum Direction {
+    #[serde(alias = "BUY")]
+    Buy,
+    #[serde(alias = "SELL")]
+    Sell,
+}
+
+// struct for storing the following CSV columns:
+// Spot Pairs,Order No,Order Type,Direction,Filled Value,Filled Price,Filled Quantity,Fees,Transaction ID,Time(UTC)
+#[derive(Debug, Deserialize)]
+struct BybitSpotTrade {
+    #[serde(rename = "Spot Pairs")]
+    pair: String,
+    #[serde(rename = "Direction")]
+    direction: Direction,
+    /// Filled quantity of the quote currency.
+    #[serde(rename = "Filled Value")]
+    filled_value: Decimal,
+    /// Filled quantity of the base currency.
+    #[serde(rename = "Filled Quantity")]
+    filled_quantity: Decimal,
+    #[serde(rename = "Fees")]
+    fees: Decimal,
+    #[serde(rename = "Transaction ID")]
+    transaction_id: String,
+    #[serde(rename = "Time(UTC)", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+}
+
+impl TryFrom<BybitSpotTrade> for Transaction {
+    type Error = anyhow::Error;
+
+    fn try_from(item: BybitSpotTrade) -> Result<Self> {
+        let (base, quote) = split_pair(&item.pair)
+            .with_context(|| format!("unrecognized spot pair '{}'", item.pair))?;
+        let base = Amount::new(item.filled_quantity, base.to_owned());
+        let quote = Amount::new(item.filled_value, quote.to_owned());
+
+        let (incoming, outgoing) = match item.direction {
+            Direction::Buy => (base, quote),
+            Direction::Sell => (quote, base),
+        };
+
+        // spot fees are charged in the received currency
+        let fee_currency = incoming.currency.clone();
+        let mut tx = Transaction::trade(item.timestamp, incoming, outgoing);
+        if !item.fees.is_zero() {
+            tx.fee = Some(Amount::new(item.fees, fee_currency));
+        }
+        tx.description = Some(format!("Trade {}", item.transaction_id));
+        Ok(tx)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+enum TransferType {
+    Deposit,
+    Withdrawal,
+}
+
+// struct for storing the following CSV columns:
+// Time(UTC),Type,Coin,Chain,Amount,Fee,Status,Address,TxID
+#[derive(Debug, Deserialize)]
+struct BybitTransfer {
+    #[serde(rename = "Time(UTC)", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+    #[serde(rename = "Type")]
+    type_: TransferType,
+    #[serde(rename = "Coin")]
+    coin: String,
+    #[serde(rename = "Chain")]
+    chain: String,
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+    #[serde(rename = "Fee")]
+    fee: Option<Decimal>,
+    #[serde(rename = "Status")]
+    status: String,
+    #[serde(rename = "Address")]
+    address: String,
+    #[serde(rename = "TxID")]
+    tx_id: String,
+}
+
+impl From<BybitTransfer> for Transaction {
+    fn from(item: BybitTransfer) -> Self {
+        let amount = Amount::new(item.amount, item.coin.clone());
+        let mut tx = match item.type_ {
+            TransferType::Deposit => Transaction::receive(item.timestamp, amount),
+            TransferType::Withdrawal => {
+                let mut tx = Transaction::send(item.timestamp, amount);
+                // the deposit address is our own
+                if !item.address.is_empty() {
+                    tx.counterparty = Some(item.address);
+                }
+                tx
+            }
+        };
+        tx.fee = item.fee.filter(|fee| !fee.is_zero()).map(|fee| Amount::new(fee, item.coin.clone()));
+        if !item.tx_id.is_empty() {
+            tx.tx_hash = Some(item.tx_id);
+            tx.blockchain = Some(blockchain_from_network(&item.chain, &item.coin));
+        }
+        tx
+    }
+}
+
+// struct for storing the following CSV columns:
+// Time(UTC),Product,Coin,Yield
+#[derive(Debug, Deserialize)]
+struct BybitEarnYield {
+    #[serde(rename = "Time(UTC)", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+    #[serde(rename = "Product")]
+    product: String,
+    #[serde(rename = "Coin")]
+    coin: String,
+    #[serde(rename = "Yield")]
+    yield_: Decimal,
+}
+
+impl From<BybitEarnYield> for Transaction {
+    fn from(item: BybitEarnYield) -> Self {
+        let mut tx = Transaction::new(item.timestamp, Operation::Staking(Amount::new(item.yield_, item.coin)));
+        tx.description = Some(item.product);
+        tx
+    }
+}
+
+fn load_bybit_spot_trades_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for result in rdr.deserialize() {
+        let record: BybitSpotTrade = result?;
+        transactions.push(record.try_into()?);
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+fn load_bybit_deposits_withdrawals_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for result in rdr.deserialize() {
+        let record: BybitTransfer = result?;
+        match record.status.as_str() {
+            "Success" | "SUCCESS" => transactions.push(record.into()),
+            "Failed" | "FAILED" | "Rejected" | "Cancelled" => {}
+            status => return Err(anyhow!("unexpected status '{}' of transfer at {}", status, record.timestamp)),
+        }
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+fn load_bybit_earn_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for result in rdr.deserialize() {
+        let record: BybitEarnYield = result?;
+        transactions.push(record.into());
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static BYBIT_SPOT_TRADES_CSV: TransactionSource = TransactionSource {
+    id: "BybitSpotTradesCsv",
+    label: "Bybit Spot Trades (CSV)",
+    csv: &[CsvSpec::new(&[
+        "Spot Pairs",
+        "Order No",
+        "Order Type",
+        "Direction",
+        "Filled Value",
+        "Filled Price",
+        "Filled Quantity",
+        "Fees",
+        "Transaction ID",
+        "Time(UTC)",
+    ])],
+    load_sync: Some(load_bybit_spot_trades_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static BYBIT_DEPOSITS_WITHDRAWALS_CSV: TransactionSource = TransactionSource {
+    id: "BybitDepositsWithdrawalsCsv",
+    label: "Bybit Deposits/Withdrawals (CSV)",
+    csv: &[CsvSpec::new(&[
+        "Time(UTC)",
+        "Type",
+        "Coin",
+        "Chain",
+        "Amount",
+        "Fee",
+        "Status",
+        "Address",
+        "TxID",
+    ])],
+    load_sync: Some(load_bybit_deposits_withdrawals_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static BYBIT_EARN_CSV: TransactionSource = TransactionSource {
+    id: "BybitEarnCsv",
+    label: "Bybit Earn (CSV)",
+    csv: &[CsvSpec::new(&[
+        "Time(UTC)",
+        "Product",
+        "Coin",
+        "Yield",
+    ])],
+    load_sync: Some(load_bybit_earn_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const OPTIONS: LoadOptions = LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None };
+
+    #[test]
+    fn split_spot_pairs() {
+        assert_eq!(split_pair("BTCUSDT"), Some(("BTC", "USDT")));
+        assert_eq!(split_pair("ETHBTC"), Some(("ETH", "BTC")));
+        assert_eq!(split_pair("USDT"), None);
+    }
+
+    #[test]
+    fn load_spot_trades() {
+        let path = Path::new("tests/data/bybit_spot_trades.csv");
+        assert!(BYBIT_SPOT_TRADES_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_bybit_spot_trades_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.5), "ETH".to_owned()),
+            outgoing: Amount::new(dec!(1150), "USDT".to_owned()),
+        });
+        assert_eq!(transactions[0].fee, Some(Amount::new(dec!(0.0005), "ETH".to_owned())));
+        assert_eq!(transactions[1].operation, Operation::Trade {
+            incoming: Amount::new(dec!(580), "USDT".to_owned()),
+            outgoing: Amount::new(dec!(0.25), "ETH".to_owned()),
+        });
+        assert_eq!(transactions[1].fee, Some(Amount::new(dec!(0.58), "USDT".to_owned())));
+    }
+
+    #[test]
+    fn load_deposits_withdrawals() {
+        let path = Path::new("tests/data/bybit_deposits_withdrawals.csv");
+        assert!(BYBIT_DEPOSITS_WITHDRAWALS_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_bybit_deposits_withdrawals_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].operation, Operation::Receive(Amount::new(dec!(1200), "USDT".to_owned())));
+        assert_eq!(transactions[0].counterparty, None);
+        assert_eq!(transactions[0].blockchain.as_deref(), Some("TRX"));
+        assert_eq!(transactions[1].operation, Operation::Send(Amount::new(dec!(0.2), "ETH".to_owned())));
+        assert_eq!(transactions[1].fee, Some(Amount::new(dec!(0.0012), "ETH".to_owned())));
+        assert_eq!(transactions[1].tx_hash.as_deref(), Some("0x3a1b5c7d9e2f4a6b8c0d1e3f5a7b9c2d4e6f8a0b1c3d5e7f9a2b4c6d8e0f1a3b"));
+        assert_eq!(transactions[1].blockchain.as_deref(), Some("ETH"));
+    }
+
+    #[test]
+    fn load_earn_yield() {
+        let path = Path::new("tests/data/bybit_earn.csv");
+        assert!(BYBIT_EARN_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_bybit_earn_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].operation, Operation::Staking(Amount::new(dec!(0.08), "USDT".to_owned())));
+        assert_eq!(transactions[0].description.as_deref(), Some("Flexible Savings"));
+    }
+}
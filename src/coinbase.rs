@@ -0,0 +1,334 @@
+use std::{fs::File, io::{BufRead, BufReader}, path::Path, sync::LazyLock};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDateTime};
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    base::{Amount, Operation, Transaction},
+    csv_matches,
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+// Header of the transaction history report since 2024, which is preceded by
+// an empty line, "Transactions" and a line with the user name and ID.
+const COINBASE_HEADERS: &[&str] = &[
+    "ID",
+    "Timestamp",
+    "Transaction Type",
+    "Asset",
+    "Quantity Transacted",
+    "Price Currency",
+    "Price at Transaction",
+    "Subtotal",
+    "Total (inclusive of fees and/or spread)",
+    "Fees and/or Spread",
+    "Notes",
+];
+
+// Header of the older transaction history report, which is preceded by a
+// disclaimer and the user name.
+const COINBASE_HEADERS_OLD: &[&str] = &[
+    "Timestamp",
+    "Transaction Type",
+    "Asset",
+    "Quantity Transacted",
+    "Spot Price Currency",
+    "Spot Price at Transaction",
+    "Subtotal",
+    "Total (inclusive of fees and/or spread)",
+    "Fees and/or Spread",
+    "Notes",
+];
+
+const COINBASE_CSV_SPECS: &[CsvSpec] = &[
+    CsvSpec {
+        headers: COINBASE_HEADERS,
+        delimiters: b",",
+        skip_lines: 3,
+        trim: csv::Trim::None,
+    },
+    CsvSpec {
+        headers: COINBASE_HEADERS_OLD,
+        delimiters: b",",
+        skip_lines: 7,
+        trim: csv::Trim::None,
+    },
+];
+
+#[derive(Debug, Deserialize)]
+struct CoinbaseTransaction {
+    #[serde(rename = "ID", default)]
+    id: Option<String>,
+    #[serde(rename = "Timestamp")]
+    timestamp: String,
+    #[serde(rename = "Transaction Type")]
+    type_: String,
+    #[serde(rename = "Asset")]
+    asset: String,
+    #[serde(rename = "Quantity Transacted")]
+    quantity: String,
+    #[serde(rename = "Price Currency", alias = "Spot Price Currency")]
+    price_currency: String,
+    #[serde(rename = "Subtotal")]
+    subtotal: String,
+    #[serde(rename = "Fees and/or Spread")]
+    fees: String,
+    #[serde(rename = "Notes")]
+    notes: String,
+}
+
+/// Parses amounts like "€1,234.56", "-$10.00" or "0.001", returning their
+/// absolute value, since the sign is implied by the transaction type.
+fn parse_quantity(raw: &str) -> Result<Option<Decimal>> {
+    let number: String = raw.chars().filter(|c| c.is_ascii_digit() || *c == '.' || *c == 'e' || *c == 'E' || *c == '-').collect();
+    if number.is_empty() {
+        return Ok(None);
+    }
+    let quantity = Decimal::from_str_exact(&number)
+        .or_else(|_| Decimal::from_scientific(&number))
+        .with_context(|| format!("invalid amount '{}'", raw))?;
+    Ok(Some(quantity.abs()))
+}
+
+fn parse_timestamp(raw: &str) -> Result<NaiveDateTime> {
+    let raw = raw.trim();
+    match raw.strip_suffix(" UTC") {
+        Some(date_time) => Ok(NaiveDateTime::parse_from_str(date_time, "%Y-%m-%d %H:%M:%S")?),
+        None => Ok(DateTime::parse_from_rfc3339(raw)?.naive_utc()),
+    }
+}
+
+// Convert notes look like "Converted 0.01 ETH to 20.5 USDC"
+static CONVERT_NOTES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^Converted ([\d.,]+) (\S+) to ([\d.,]+) (\S+)").unwrap()
+});
+
+// Advanced Trade notes look like "Bought 0.05 ETH for 0.0025 BTC on ETH-BTC at
+// 0.05 BTC/ETH", where the quote asset may be another crypto currency
+static ADVANCED_TRADE_NOTES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:Bought|Sold) [\d.,]+ \S+ for ([\d.,]+) (\S+) on \S+-(\S+) ").unwrap()
+});
+
+// Send and receive notes end with the address, when known
+static COUNTERPARTY_NOTES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:Sent|Received) [\d.,]+ \S+ (?:to|from) (\w+)$").unwrap()
+});
+
+impl CoinbaseTransaction {
+    fn into_transaction(self) -> Result<Option<Transaction>> {
+        let timestamp = parse_timestamp(&self.timestamp)?;
+        let quantity = parse_quantity(&self.quantity)?.unwrap_or_default();
+        let amount = Amount::new(quantity, self.asset.clone());
+        let subtotal = parse_quantity(&self.subtotal)?.map(|subtotal| Amount::new(subtotal, self.price_currency.clone()));
+        let fee = parse_quantity(&self.fees)?
+            .filter(|fee| !fee.is_zero())
+            .map(|fee| Amount::new(fee, self.price_currency.clone()));
+
+        // The other side of a trade is the subtotal in the report currency,
+        // unless an Advanced Trade was made against another quote asset.
+        let counter_amount = || -> Result<Amount> {
+            if self.type_.starts_with("Advanced Trade") {
+                if let Some(captures) = ADVANCED_TRADE_NOTES.captures(&self.notes) {
+                    let quote_asset = &captures[3];
+                    if quote_asset != self.price_currency {
+                        if &captures[2] != quote_asset {
+                            return Err(anyhow!("unexpected quote amount in {} on {} pair", &captures[2], quote_asset));
+                        }
+                        let quantity = parse_quantity(&captures[1])?.context("missing quote amount")?;
+                        return Ok(Amount::new(quantity, quote_asset.to_owned()));
+                    }
+                }
+            }
+            subtotal.clone().with_context(|| format!("missing subtotal for {}", self.type_))
+        };
+
+        let operation = match self.type_.as_str() {
+            "Buy" | "Advanced Trade Buy" => Operation::Trade {
+                incoming: amount,
+                outgoing: counter_amount()?,
+            },
+            "Sell" | "Advanced Trade Sell" => Operation::Trade {
+                incoming: counter_amount()?,
+                outgoing: amount,
+            },
+            "Convert" => {
+                let captures = CONVERT_NOTES.captures(&self.notes)
+                    .with_context(|| format!("unrecognized convert notes '{}'", self.notes))?;
+                let parse = |index: usize| parse_quantity(&captures[index]).and_then(|q| q.context("missing convert amount"));
+                Operation::Trade {
+                    incoming: Amount::new(parse(3)?, captures[4].to_owned()),
+                    outgoing: Amount::new(parse(1)?, captures[2].to_owned()),
+                }
+            }
+            "Send" | "Pro Deposit" | "Exchange Deposit" => Operation::Send(amount),
+            "Receive" | "Pro Withdrawal" | "Exchange Withdrawal" => Operation::Receive(amount),
+            // only EUR is treated as fiat, other currencies like USD are tracked
+            "Deposit" if amount.is_fiat() => Operation::FiatDeposit(amount),
+            "Deposit" => Operation::Receive(amount),
+            "Withdrawal" if amount.is_fiat() => Operation::FiatWithdrawal(amount),
+            "Withdrawal" => Operation::Send(amount),
+            "Staking Income" | "Inflation Reward" => Operation::Staking(amount),
+            "Rewards Income" | "Learning Reward" | "Coinbase Earn" | "Incentives Rewards Payout" => Operation::Income(amount),
+            "Subscription Rebate" => Operation::Cashback(amount),
+            // moving funds between the spot and staking balance does not change the holdings
+            "Retail Staking Transfer" | "Retail Unstaking Transfer" => return Ok(None),
+            type_ => return Err(anyhow!("unsupported transaction type '{}'", type_)),
+        };
+
+        // the fees and/or spread are charged on trades, including converts
+        let is_trade = matches!(operation, Operation::Trade { .. });
+        let mut tx = Transaction::new(timestamp, operation);
+        if is_trade {
+            tx.fee = fee;
+        }
+        // Values are in EUR, so they are only taken from reports using EUR.
+        // The values of other reports, like those using USD, are estimated
+        // from the price history instead.
+        if self.price_currency == "EUR" && !self.asset.eq_ignore_ascii_case("EUR") {
+            tx.value = subtotal.filter(|subtotal| !subtotal.is_zero());
+        }
+        tx.counterparty = COUNTERPARTY_NOTES.captures(&self.notes).map(|captures| captures[1].to_owned());
+        tx.description = match (self.notes.is_empty(), self.id) {
+            (false, _) => Some(self.notes),
+            (true, id) => id,
+        };
+        Ok(Some(tx))
+    }
+}
+
+// loads a Coinbase transaction history CSV file into a list of unified transactions
+fn load_coinbase_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut spec = None;
+    for csv in COINBASE_CSV_SPECS {
+        if csv_matches(input_path, csv)? {
+            spec = Some(csv);
+            break;
+        }
+    }
+    let spec = spec.context("unrecognized Coinbase report header")?;
+
+    let mut buf_reader = BufReader::new(File::open(input_path)?);
+    let mut line = String::new();
+    for _ in 0..spec.skip_lines {
+        buf_reader.read_line(&mut line)?;
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(buf_reader);
+
+    let mut transactions = Vec::new();
+    for result in rdr.deserialize() {
+        let record: CoinbaseTransaction = result?;
+        let timestamp = record.timestamp.clone();
+        match record.into_transaction() {
+            Ok(Some(tx)) => transactions.push(tx),
+            Ok(None) => {}
+            Err(e) => println!("Coinbase: skipping transaction at {}: {}", timestamp, e),
+        }
+    }
+
+    // the report lists the most recent transactions first
+    transactions.sort_by_key(|tx| tx.timestamp);
+
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static COINBASE_CSV: TransactionSource = TransactionSource {
+    id: "CoinbaseCsv",
+    label: "Coinbase (CSV)",
+    csv: COINBASE_CSV_SPECS,
+    load_sync: Some(load_coinbase_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn load_transaction_history() {
+        let path = Path::new("tests/data/coinbase.csv");
+        assert!(COINBASE_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_coinbase_csv(path, &LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None }).unwrap();
+        assert_eq!(transactions.len(), 11);
+
+        let buy = &transactions[1];
+        assert_eq!(buy.timestamp, parse_date_time("2024-01-05 10:00:00").unwrap());
+        assert_eq!(buy.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.01), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(400.00), "EUR".to_owned()),
+        });
+        assert_eq!(buy.fee, Some(Amount::new(dec!(5.99), "EUR".to_owned())));
+        assert_eq!(buy.value, Some(Amount::new(dec!(400.00), "EUR".to_owned())));
+
+        assert_eq!(transactions[3].operation, Operation::Trade {
+            incoming: Amount::new(dec!(150.5), "EUR".to_owned()),
+            outgoing: Amount::new(dec!(0.1), "ETH".to_owned()),
+        });
+
+        assert_eq!(transactions[4].operation, Operation::Trade {
+            incoming: Amount::new(dec!(1000.25), "USDC".to_owned()),
+            outgoing: Amount::new(dec!(0.5), "ETH".to_owned()),
+        });
+        assert_eq!(transactions[4].fee, Some(Amount::new(dec!(10.00), "EUR".to_owned())));
+
+        assert!(matches!(transactions[5].operation, Operation::Staking(_)));
+        assert!(matches!(transactions[6].operation, Operation::Income(_)));
+
+        let send = &transactions[7];
+        assert_eq!(send.operation, Operation::Send(Amount::new(dec!(0.005), "BTC".to_owned())));
+        assert_eq!(send.counterparty.as_deref(), Some("bc1qxy2kgdygjrsqtzq2n0yrf2493p83kkfjhx0wlh"));
+
+        assert!(transactions[8].operation.is_send());
+
+        // an advanced trade against BTC is valued by its subtotal in EUR
+        let trade = &transactions[9];
+        assert_eq!(trade.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.05), "ETH".to_owned()),
+            outgoing: Amount::new(dec!(0.0025), "BTC".to_owned()),
+        });
+        assert_eq!(trade.fee, Some(Amount::new(dec!(0.50), "EUR".to_owned())));
+        assert_eq!(trade.value, Some(Amount::new(dec!(105.00), "EUR".to_owned())));
+
+        assert_eq!(transactions[10].operation, Operation::FiatWithdrawal(Amount::new(dec!(100), "EUR".to_owned())));
+    }
+
+    #[test]
+    fn load_usd_transaction_history() {
+        let path = Path::new("tests/data/coinbase_usd.csv");
+        assert!(COINBASE_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_coinbase_csv(path, &LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None }).unwrap();
+        assert_eq!(transactions.len(), 4);
+
+        // USD is not treated as fiat, but tracked like other currencies
+        assert_eq!(transactions[0].operation, Operation::Receive(Amount::new(dec!(1000), "USD".to_owned())));
+
+        let buy = &transactions[1];
+        assert_eq!(buy.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.01), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(430.00), "USD".to_owned()),
+        });
+        assert_eq!(buy.fee, Some(Amount::new(dec!(6.45), "USD".to_owned())));
+        // the value is estimated later
+        assert_eq!(buy.value, None);
+
+        let convert = &transactions[2];
+        assert_eq!(convert.operation, Operation::Trade {
+            incoming: Amount::new(dec!(85.7), "USDC".to_owned()),
+            outgoing: Amount::new(dec!(0.002), "BTC".to_owned()),
+        });
+        assert_eq!(convert.fee, Some(Amount::new(dec!(1.30), "USD".to_owned())));
+
+        assert_eq!(transactions[3].operation, Operation::Send(Amount::new(dec!(50), "USD".to_owned())));
+    }
+}
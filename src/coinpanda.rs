@@ -1,11 +1,18 @@
-use anyhow::Result;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
 use chrono::NaiveDateTime;
 use rust_decimal::Decimal;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{ctc::{CtcTxType, CtcTx}, time::serialize_date_time};
+use crate::{
+    base::{Amount, Operation, Transaction},
+    time::{deserialize_date_time, serialize_date_time},
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 enum CoinpandaTxType {
     /// Use this if your transaction involves both a buy and sell side - typically whenever you have exchanged one currency for another.
     Trade,
@@ -15,10 +22,10 @@ enum CoinpandaTxType {
     Send,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 struct CoinpandaTx<'a> {
     /// All dates should be UTC timezone
-    #[serde(rename = "Timestamp (UTC)", serialize_with = "serialize_date_time")]
+    #[serde(rename = "Timestamp (UTC)", serialize_with = "serialize_date_time", deserialize_with = "deserialize_date_time")]
     timestamp: NaiveDateTime,
 
     /// Can be added to make reading the file easier
@@ -70,170 +77,251 @@ struct CoinpandaTx<'a> {
     tx_hash: Option<&'a str>,
 }
 
-impl<'a> CoinpandaTx<'a> {
-    fn trade(timestamp: NaiveDateTime, received_amount: Decimal, received_currency: &'a str, sent_amount: Decimal, sent_currency: &'a str) -> Self {
+impl<'a> From<&'a Transaction> for CoinpandaTx<'a> {
+    fn from(item: &'a Transaction) -> Self {
+        let mut sent = None;
+        let mut received = None;
+        let mut fee = item.fee.as_ref();
+
+        let label = match &item.operation {
+            // A buy or sell is a receive or send that wasn't matched with
+            // another wallet, which it becomes again when imported.
+            Operation::Buy(amount) => { received = Some(amount); None }
+            Operation::Sell(amount) => { sent = Some(amount); None }
+            Operation::Trade { incoming, outgoing } => { received = Some(incoming); sent = Some(outgoing); None }
+            Operation::Swap { incoming, outgoing } => { received = Some(incoming); sent = Some(outgoing); Some("Swap") }
+            Operation::FiatDeposit(amount) | Operation::Receive(amount) => { received = Some(amount); None }
+            Operation::FiatWithdrawal(amount) | Operation::Send(amount) => { sent = Some(amount); None }
+            // a standalone fee is a send with only the fee columns filled
+            Operation::Fee(amount) => { fee = Some(amount); None }
+            Operation::ChainSplit(amount) => { received = Some(amount); Some("Fork") }
+            Operation::Expense(amount) => { sent = Some(amount); Some("Cost") }
+            Operation::Stolen(amount) => { sent = Some(amount); Some("Stolen") }
+            Operation::Lost(amount) => { sent = Some(amount); Some("Lost") }
+            Operation::Burn(amount) => { sent = Some(amount); Some("Burn") }
+            Operation::Income(amount) => { received = Some(amount); Some("Income") }
+            Operation::Airdrop(amount) => { received = Some(amount); Some("Airdrop") }
+            Operation::Staking(amount) => { received = Some(amount); Some("Staking") }
+            Operation::Cashback(amount) => { received = Some(amount); Some("Cashback") }
+            Operation::IncomingGift(amount) => { received = Some(amount); Some("Gift") }
+            Operation::OutgoingGift(amount) => { sent = Some(amount); Some("Gift") }
+            Operation::Spam(amount) => { received = Some(amount); Some("Spam") }
+        };
+
+        let type_ = match (sent, received) {
+            (Some(_), Some(_)) => CoinpandaTxType::Trade,
+            (None, Some(_)) => CoinpandaTxType::Receive,
+            _ => CoinpandaTxType::Send,
+        };
+
+        let net_worth = item.value.as_ref();
+
         Self {
-            timestamp,
-            type_: CoinpandaTxType::Trade,
-            sent_amount: Some(sent_amount),
-            sent_currency: Some(sent_currency),
-            received_amount: Some(received_amount),
-            received_currency: Some(received_currency),
-            fee_amount: None,
-            fee_currency: None,
-            net_worth_amount: None,
-            net_worth_currency: None,
-            label: None,
-            description: None,
-            tx_hash: None,
+            timestamp: item.timestamp,
+            type_,
+            sent_amount: sent.map(|amount| amount.quantity),
+            sent_currency: sent.map(|amount| amount.currency.as_str()),
+            received_amount: received.map(|amount| amount.quantity),
+            received_currency: received.map(|amount| amount.currency.as_str()),
+            fee_amount: fee.map(|amount| amount.quantity),
+            fee_currency: fee.map(|amount| amount.currency.as_str()),
+            net_worth_amount: net_worth.map(|amount| amount.quantity),
+            net_worth_currency: net_worth.map(|amount| amount.currency.as_str()),
+            label,
+            description: item.description.as_deref(),
+            tx_hash: item.tx_hash.as_deref(),
         }
     }
+}
 
-    fn send(timestamp: NaiveDateTime, sent_amount: Decimal, sent_currency: &'a str) -> Self {
-        Self {
-            timestamp,
-            type_: CoinpandaTxType::Send,
-            sent_amount: Some(sent_amount),
-            sent_currency: Some(sent_currency),
-            received_amount: None,
-            received_currency: None,
-            fee_amount: None,
-            fee_currency: None,
-            net_worth_amount: None,
-            net_worth_currency: None,
-            label: None,
-            description: None,
-            tx_hash: None,
-        }
+fn amount(quantity: Option<Decimal>, currency: Option<&str>) -> Option<Amount> {
+    match (quantity, currency) {
+        (Some(quantity), Some(currency)) if !currency.is_empty() => Some(Amount::new(quantity, currency.to_owned())),
+        _ => None,
     }
+}
 
-    fn receive(timestamp: NaiveDateTime, received_amount: Decimal, received_currency: &'a str) -> Self {
-        Self {
-            timestamp,
-            type_: CoinpandaTxType::Receive,
-            sent_amount: None,
-            sent_currency: None,
-            received_amount: Some(received_amount),
-            received_currency: Some(received_currency),
-            fee_amount: None,
-            fee_currency: None,
-            net_worth_amount: None,
-            net_worth_currency: None,
-            label: None,
-            description: None,
-            tx_hash: None,
+impl<'a> CoinpandaTx<'a> {
+    fn into_transaction(self) -> Result<Transaction> {
+        let sent = amount(self.sent_amount, self.sent_currency);
+        let received = amount(self.received_amount, self.received_currency);
+        let fee = amount(self.fee_amount, self.fee_currency);
+        let label = self.label.unwrap_or_default().to_lowercase();
+
+        let (operation, fee) = match (self.type_, sent, received) {
+            (CoinpandaTxType::Trade, Some(outgoing), Some(incoming)) => match label.as_str() {
+                "swap" => (Operation::Swap { incoming, outgoing }, fee),
+                _ => (Operation::Trade { incoming, outgoing }, fee),
+            },
+            (CoinpandaTxType::Trade, _, _) => return Err(anyhow!("trade without sent and received amounts")),
+            (CoinpandaTxType::Receive, _, Some(amount)) => (match label.as_str() {
+                "airdrop" => Operation::Airdrop(amount),
+                "fork" => Operation::ChainSplit(amount),
+                "mining" | "income" | "other income" | "interest" | "lending interest" | "reward" => Operation::Income(amount),
+                "staking" => Operation::Staking(amount),
+                "cashback" => Operation::Cashback(amount),
+                "gift" => Operation::IncomingGift(amount),
+                "spam" => Operation::Spam(amount),
+                _ if amount.is_fiat() => Operation::FiatDeposit(amount),
+                _ => Operation::Receive(amount),
+            }, fee),
+            (CoinpandaTxType::Receive, _, None) => return Err(anyhow!("receive without received amount")),
+            (CoinpandaTxType::Send, Some(amount), _) => (match label.as_str() {
+                "gift" | "donation" => Operation::OutgoingGift(amount),
+                "lost" => Operation::Lost(amount),
+                "stolen" => Operation::Stolen(amount),
+                "burn" => Operation::Burn(amount),
+                "cost" | "interest payment" | "margin fee" => Operation::Expense(amount),
+                _ if amount.is_fiat() => Operation::FiatWithdrawal(amount),
+                _ => Operation::Send(amount),
+            }, fee),
+            (CoinpandaTxType::Send, None, _) => (Operation::Fee(fee.context("send without sent or fee amount")?), None),
+        };
+
+        let mut tx = Transaction::new(self.timestamp, operation);
+        tx.fee = fee.filter(|fee| !fee.quantity.is_zero());
+        match (self.net_worth_amount, self.net_worth_currency) {
+            (Some(value), Some("EUR")) => tx.value = Some(Amount::from_fiat(value)),
+            // values are in EUR, so others will be estimated instead
+            (Some(value), Some(currency)) if !currency.is_empty() => {
+                println!("Coinpanda: ignoring net worth of {} {} at {}, only EUR is supported", value, currency, self.timestamp);
+            }
+            _ => {}
         }
+        tx.description = self.description.filter(|s| !s.is_empty()).map(|s| s.to_owned());
+        tx.tx_hash = self.tx_hash.filter(|s| !s.is_empty()).map(|s| s.to_owned());
+        Ok(tx)
     }
 }
 
-fn convert_ctc_to_coinpanda<'a>(ctc: &'a CtcTx) -> CoinpandaTx<'a> {
-    let mut tx = match ctc.operation {
-        CtcTxType::Buy => CoinpandaTx::trade(
-            ctc.timestamp,
-            ctc.base_amount,
-            ctc.base_currency,
-            ctc.quote_amount.expect("quote amount"),
-            ctc.quote_currency.expect("quote currency")
-        ),
-        CtcTxType::Sell => CoinpandaTx::trade(
-            ctc.timestamp,
-            ctc.quote_amount.expect("quote amount"),
-            ctc.quote_currency.expect("quote currency"),
-            ctc.base_amount,
-            ctc.base_currency
-        ),
-        CtcTxType::FiatDeposit => CoinpandaTx::receive(
-            ctc.timestamp,
-            ctc.base_amount,
-            ctc.base_currency
-        ),
-        CtcTxType::FiatWithdrawal => CoinpandaTx::send(
-            ctc.timestamp,
-            ctc.base_amount,
-            ctc.base_currency
-        ),
-        CtcTxType::Fee => CoinpandaTx::send(
-            ctc.timestamp,
-            ctc.fee_amount.expect("fee amount"),
-            ctc.fee_currency.expect("fee currency")
-        ),
-        CtcTxType::Approval => todo!(),
-        CtcTxType::Receive => CoinpandaTx::receive(
-            ctc.timestamp,
-            ctc.base_amount,
-            ctc.base_currency
-        ),
-        CtcTxType::Send => CoinpandaTx::send(
-            ctc.timestamp,
-            ctc.base_amount,
-            ctc.base_currency
-        ),
-        CtcTxType::ChainSplit => todo!(),
-        CtcTxType::Expense => todo!(),
-        CtcTxType::Stolen => todo!(),
-        CtcTxType::Lost => todo!(),
-        CtcTxType::Burn => todo!(),
-        CtcTxType::Income => todo!(),
-        CtcTxType::Interest => todo!(),
-        CtcTxType::Mining => todo!(),
-        CtcTxType::Airdrop => todo!(),
-        CtcTxType::Staking => todo!(),
-        CtcTxType::StakingDeposit => todo!(),
-        CtcTxType::StakingWithdrawal => todo!(),
-        CtcTxType::Cashback => todo!(),
-        CtcTxType::Royalties => todo!(),
-        CtcTxType::PersonalUse => todo!(),
-        CtcTxType::IncomingGift => CoinpandaTx::receive(
-            ctc.timestamp,
-            ctc.base_amount,
-            ctc.base_currency
-        ),
-        CtcTxType::OutgoingGift => CoinpandaTx::send(
-            ctc.timestamp,
-            ctc.base_amount,
-            ctc.base_currency
-        ),
-        CtcTxType::Borrow => todo!(),
-        CtcTxType::LoanRepayment => todo!(),
-        CtcTxType::Liquidate => todo!(),
-        CtcTxType::RealizedProfit => todo!(),
-        CtcTxType::RealizedLoss => todo!(),
-        CtcTxType::MarginFee => todo!(),
-        CtcTxType::BridgeIn => todo!(),
-        CtcTxType::BridgeOut => todo!(),
-        CtcTxType::Mint => todo!(),
-        CtcTxType::CollateralWithdrawal => todo!(),
-        CtcTxType::CollateralDeposit => todo!(),
-        CtcTxType::AddLiquidity => todo!(),
-        CtcTxType::ReceiveLpToken => todo!(),
-        CtcTxType::RemoveLiquidity => todo!(),
-        CtcTxType::ReturnLpToken => todo!(),
-        CtcTxType::FailedIn => todo!(),
-        CtcTxType::FailedOut => todo!(),
-        CtcTxType::Spam => todo!(),
-        CtcTxType::SwapIn => todo!(),
-        CtcTxType::SwapOut => todo!(),
-    };
-    tx.description = ctc.description;
-    // tx.fee_amount = ctc.fee_amount;
-    // tx.fee_currency = ctc.fee_currency;
-    tx
+pub(crate) fn save_transactions_to_coinpanda_csv(transactions: &[Transaction], output_path: &Path) -> Result<()> {
+    println!("Saving {}", output_path.display());
+
+    let mut wtr = csv::Writer::from_path(output_path)?;
+
+    for tx in transactions {
+        wtr.serialize(CoinpandaTx::from(tx))?;
+    }
+
+    Ok(())
 }
 
-#[allow(dead_code)] // todo: change to load into list of base::Transaction
-pub(crate) fn convert_ctc_csv_to_coinpanda_csv(input_path: &str, output_path: &str) -> Result<()> {
-    println!("Converting {} to {}", input_path, output_path);
+// loads a CSV file in the Coinpanda generic import format
+fn load_coinpanda_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new()
         .from_path(input_path)?;
     let mut raw_record = csv::StringRecord::new();
     let headers = rdr.headers()?.clone();
 
-    let mut wtr = csv::Writer::from_path(output_path)?;
-
+    let mut transactions = Vec::new();
     while rdr.read_record(&mut raw_record)? {
-        let record: CtcTx = raw_record.deserialize(Some(&headers))?;
-        wtr.serialize(convert_ctc_to_coinpanda(&record))?;
+        let record: CoinpandaTx = raw_record.deserialize(Some(&headers))?;
+        let timestamp = record.timestamp;
+        match record.into_transaction() {
+            Ok(tx) => transactions.push(tx),
+            Err(e) => println!("Coinpanda: skipping transaction at {}: {}", timestamp, e),
+        }
     }
 
-    Ok(())
+    transactions.sort_by_key(|tx| tx.timestamp);
+
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static COINPANDA_CSV: TransactionSource = TransactionSource {
+    id: "CoinpandaCsv",
+    label: "Coinpanda generic import (CSV)",
+    csv: &[CsvSpec::new(&[
+        "Timestamp (UTC)",
+        "Type",
+        "Sent Amount",
+        "Sent Currency",
+        "Received Amount",
+        "Received Currency",
+        "Fee Amount",
+        "Fee Currency",
+        "Net Worth Amount",
+        "Net Worth Currency",
+        "Label",
+        "Description",
+        "TxHash",
+    ])],
+    load_sync: Some(load_coinpanda_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::match_send_receive;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+    use std::collections::HashSet;
+
+    #[test]
+    fn load_and_save_generic_format() {
+        let path = Path::new("tests/data/coinpanda.csv");
+        assert!(COINPANDA_CSV.detect_from_file(path).unwrap());
+
+        let options = LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None };
+        let mut transactions = load_coinpanda_csv(path, &options).unwrap();
+        assert_eq!(transactions.len(), 9);
+
+        assert_eq!(transactions[0].operation, Operation::FiatDeposit(Amount::new(dec!(1000), "EUR".to_owned())));
+
+        let trade = &transactions[1];
+        assert_eq!(trade.timestamp, parse_date_time("2024-01-15 10:00:00").unwrap());
+        assert_eq!(trade.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.5), "ETH".to_owned()),
+            outgoing: Amount::new(dec!(1100), "EUR".to_owned()),
+        });
+        assert_eq!(trade.fee, Some(Amount::new(dec!(2.20), "EUR".to_owned())));
+
+        let receive = &transactions[2];
+        assert_eq!(receive.operation, Operation::Receive(Amount::new(dec!(0.02), "BTC".to_owned())));
+        assert_eq!(receive.value, Some(Amount::from_fiat(dec!(790))));
+
+        let staking = &transactions[3];
+        assert_eq!(staking.operation, Operation::Staking(Amount::new(dec!(0.001), "ETH".to_owned())));
+        assert_eq!(staking.value, Some(Amount::from_fiat(dec!(2.35))));
+
+        assert_eq!(transactions[4].operation, Operation::Fee(Amount::new(dec!(0.0005), "ETH".to_owned())));
+        assert_eq!(transactions[5].operation, Operation::Swap {
+            incoming: Amount::new(dec!(400), "USDC".to_owned()),
+            outgoing: Amount::new(dec!(0.2), "ETH".to_owned()),
+        });
+
+        let send = &transactions[6];
+        assert_eq!(send.operation, Operation::OutgoingGift(Amount::new(dec!(100), "USDC".to_owned())));
+        assert_eq!(send.description.as_deref(), Some("Sent USDC to mom"));
+        assert_eq!(send.fee, Some(Amount::new(dec!(1), "USDC".to_owned())));
+
+        // a net worth in another currency than EUR is ignored
+        let income = &transactions[8];
+        assert_eq!(income.operation, Operation::Income(Amount::new(dec!(5), "USDC".to_owned())));
+        assert_eq!(income.value, None);
+
+        // unmatched receives and sends become buys and sells
+        match_send_receive(&mut transactions, &[], &HashSet::new());
+        assert_eq!(transactions[2].operation, Operation::Buy(Amount::new(dec!(0.02), "BTC".to_owned())));
+        assert_eq!(transactions[7].operation, Operation::Sell(Amount::new(dec!(0.01), "BTC".to_owned())));
+        assert_eq!(transactions[7].value, Some(Amount::from_fiat(dec!(600))));
+
+        let output_path = std::env::temp_dir().join(format!("raccoin_coinpanda_{}.csv", std::process::id()));
+        save_transactions_to_coinpanda_csv(&transactions, &output_path).unwrap();
+        let mut reloaded = load_coinpanda_csv(&output_path, &options).unwrap();
+        std::fs::remove_file(output_path).unwrap();
+        match_send_receive(&mut reloaded, &[], &HashSet::new());
+
+        assert_eq!(reloaded.len(), transactions.len());
+        for (reloaded, tx) in reloaded.iter().zip(&transactions) {
+            assert_eq!(reloaded.timestamp, tx.timestamp);
+            assert_eq!(reloaded.operation, tx.operation);
+            assert_eq!(reloaded.fee, tx.fee);
+            assert_eq!(reloaded.value, tx.value);
+            assert_eq!(reloaded.description, tx.description);
+            assert_eq!(reloaded.tx_hash, tx.tx_hash);
+        }
+    }
 }
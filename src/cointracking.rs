@@ -0,0 +1,272 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base::{Amount, Operation, Transaction},
+    time::local_to_utc,
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+// The currency columns all share the same name, so rows are read by position.
+const COINTRACKING_HEADERS: &[&str] = &[
+    "Type",
+    "Buy",
+    "Cur.",
+    "Sell",
+    "Cur.",
+    "Fee",
+    "Cur.",
+    "Exchange",
+    "Group",
+    "Comment",
+    "Date",
+];
+
+const COINTRACKING_HEADERS_WITH_TX_ID: &[&str] = &[
+    "Type",
+    "Buy",
+    "Cur.",
+    "Sell",
+    "Cur.",
+    "Fee",
+    "Cur.",
+    "Exchange",
+    "Group",
+    "Comment",
+    "Date",
+    "Tx-ID",
+];
+
+/// A row of the CoinTracking "Trade Table". Incoming amounts are in the "Buy"
+/// columns and outgoing amounts in the "Sell" columns, for all types.
+#[derive(Debug, Serialize, Deserialize)]
+struct CoinTrackingTransaction {
+    type_: String,
+    buy_amount: Option<Decimal>,
+    buy_currency: Option<String>,
+    sell_amount: Option<Decimal>,
+    sell_currency: Option<String>,
+    fee_amount: Option<Decimal>,
+    fee_currency: Option<String>,
+    exchange: Option<String>,
+    group: Option<String>,
+    comment: Option<String>,
+    date: String,
+    #[serde(default)]
+    tx_id: Option<String>,
+}
+
+fn parse_cointracking_date(raw: &str) -> Result<NaiveDateTime> {
+    let raw = raw.trim();
+    ["%d.%m.%Y %H:%M:%S", "%d.%m.%Y %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok())
+        .with_context(|| format!("invalid date '{}'", raw))
+}
+
+fn amount(quantity: Option<Decimal>, currency: Option<String>) -> Option<Amount> {
+    match (quantity, currency) {
+        (Some(quantity), Some(currency)) if !currency.is_empty() => Some(Amount::new(quantity.abs(), currency)),
+        _ => None,
+    }
+}
+
+impl CoinTrackingTransaction {
+    fn into_transaction(self, options: &LoadOptions) -> Result<Transaction> {
+        let timestamp = local_to_utc(parse_cointracking_date(&self.date)?, options.time_zone);
+        let buy = amount(self.buy_amount, self.buy_currency);
+        let sell = amount(self.sell_amount, self.sell_currency);
+        let incoming = || buy.clone().context("missing buy amount");
+        let outgoing = || sell.clone().context("missing sell amount");
+
+        let operation = match self.type_.as_str() {
+            "Trade" | "Margin Trade" | "Derivatives / Futures Trade" => Operation::Trade { incoming: incoming()?, outgoing: outgoing()? },
+            "Deposit" => {
+                let amount = incoming()?;
+                if amount.is_fiat() { Operation::FiatDeposit(amount) } else { Operation::Receive(amount) }
+            }
+            "Withdrawal" => {
+                let amount = outgoing()?;
+                if amount.is_fiat() { Operation::FiatWithdrawal(amount) } else { Operation::Send(amount) }
+            }
+            "Income" | "Income (non taxable)" | "Other Income" | "Other Income (non taxable)" |
+            "Reward / Bonus" | "Mining" | "Mining (commercial)" | "Masternode" |
+            "Interest Income" | "Lending Income" | "Dividends Income" |
+            "Margin Profit" | "Derivatives / Futures Profit" => Operation::Income(incoming()?),
+            "Gift/Tip" => Operation::IncomingGift(incoming()?),
+            "Airdrop" | "Airdrop (non taxable)" => Operation::Airdrop(incoming()?),
+            "Staking" | "Minting" => Operation::Staking(incoming()?),
+            "Spend" | "Other Expense" | "Expense (non taxable)" => Operation::Expense(outgoing()?),
+            "Donation" | "Gift" => Operation::OutgoingGift(outgoing()?),
+            "Stolen" => Operation::Stolen(outgoing()?),
+            "Lost" | "Margin Loss" | "Derivatives / Futures Loss" => Operation::Lost(outgoing()?),
+            "Other Fee" | "Margin Fee" | "Borrowing Fee" | "Settlement Fee" => Operation::Fee(outgoing()?),
+            type_ => return Err(anyhow!("unsupported transaction type '{}'", type_)),
+        };
+
+        let mut tx = Transaction::new(timestamp, operation);
+        tx.fee = amount(self.fee_amount, self.fee_currency).filter(|fee| !fee.quantity.is_zero());
+        tx.description = self.comment.filter(|s| !s.is_empty());
+        tx.tx_hash = self.tx_id.filter(|s| !s.is_empty());
+        Ok(tx)
+    }
+
+    fn from_transaction(item: &Transaction) -> Option<Self> {
+        let mut buy = None;
+        let mut sell = None;
+
+        let type_ = match &item.operation {
+            // A buy or sell is a receive or send that wasn't matched with
+            // another wallet, which it becomes again when imported. The table
+            // has no column for its value.
+            Operation::Buy(amount) => { buy = Some(amount); "Deposit" }
+            Operation::Sell(amount) => { sell = Some(amount); "Withdrawal" }
+            Operation::Trade { incoming, outgoing } |
+            Operation::Swap { incoming, outgoing } => { buy = Some(incoming); sell = Some(outgoing); "Trade" }
+            Operation::FiatDeposit(amount) | Operation::Receive(amount) => { buy = Some(amount); "Deposit" }
+            Operation::FiatWithdrawal(amount) | Operation::Send(amount) => { sell = Some(amount); "Withdrawal" }
+            Operation::Fee(amount) => { sell = Some(amount); "Other Fee" }
+            Operation::ChainSplit(amount) => { buy = Some(amount); "Income (non taxable)" }
+            Operation::Expense(amount) => { sell = Some(amount); "Spend" }
+            Operation::Stolen(amount) => { sell = Some(amount); "Stolen" }
+            Operation::Lost(amount) | Operation::Burn(amount) => { sell = Some(amount); "Lost" }
+            Operation::Income(amount) => { buy = Some(amount); "Income" }
+            Operation::Airdrop(amount) => { buy = Some(amount); "Airdrop" }
+            Operation::Staking(amount) => { buy = Some(amount); "Staking" }
+            Operation::Cashback(amount) => { buy = Some(amount); "Reward / Bonus" }
+            Operation::IncomingGift(amount) => { buy = Some(amount); "Gift/Tip" }
+            Operation::OutgoingGift(amount) => { sell = Some(amount); "Gift" }
+            // CoinTracking has no type for spam, which is ignored anyway
+            Operation::Spam(_) => return None,
+        };
+
+        Some(Self {
+            type_: type_.to_owned(),
+            buy_amount: buy.map(|amount| amount.quantity),
+            buy_currency: buy.map(|amount| amount.currency.clone()),
+            sell_amount: sell.map(|amount| amount.quantity),
+            sell_currency: sell.map(|amount| amount.currency.clone()),
+            fee_amount: item.fee.as_ref().map(|fee| fee.quantity),
+            fee_currency: item.fee.as_ref().map(|fee| fee.currency.clone()),
+            exchange: None,
+            group: None,
+            comment: item.description.clone(),
+            date: item.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+            tx_id: item.tx_hash.clone(),
+        })
+    }
+}
+
+/// Saves the transactions as CoinTracking "Trade Table" with dates in UTC,
+/// which needs to be selected as the time zone when importing the file.
+/// Returns the number of exported transactions.
+pub(crate) fn save_transactions_to_cointracking_csv(transactions: &[Transaction], output_path: &Path) -> Result<usize> {
+    println!("Saving {}", output_path.display());
+
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_path(output_path)?;
+    wtr.write_record(COINTRACKING_HEADERS_WITH_TX_ID)?;
+
+    let mut count = 0;
+    for tx in transactions.iter().filter_map(|tx| CoinTrackingTransaction::from_transaction(tx)) {
+        wtr.serialize(tx)?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+// loads a CoinTracking "Trade Table" CSV export
+fn load_cointracking_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(input_path)?;
+    let mut raw_record = csv::StringRecord::new();
+
+    let mut transactions = Vec::new();
+    while rdr.read_record(&mut raw_record)? {
+        let record: CoinTrackingTransaction = raw_record.deserialize(None)?;
+        let date = record.date.clone();
+        match record.into_transaction(options) {
+            Ok(tx) => transactions.push(tx),
+            Err(e) => println!("CoinTracking: skipping transaction at {}: {}", date, e),
+        }
+    }
+
+    // the export lists the most recent transactions first
+    transactions.sort_by_key(|tx| tx.timestamp);
+
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static COINTRACKING_CSV: TransactionSource = TransactionSource {
+    id: "CoinTrackingCsv",
+    label: "CoinTracking Trade Table (CSV)",
+    csv: &[
+        CsvSpec::new(COINTRACKING_HEADERS),
+        CsvSpec::new(COINTRACKING_HEADERS_WITH_TX_ID),
+    ],
+    load_sync: Some(load_cointracking_csv),
+    local_time: true,
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn load_and_save_trade_table() {
+        let path = Path::new("tests/data/cointracking.csv");
+        assert!(COINTRACKING_CSV.detect_from_file(path).unwrap());
+
+        let options = LoadOptions { time_zone: chrono_tz::Europe::Berlin, account: None, custom_csv: None };
+        let transactions = load_cointracking_csv(path, &options).unwrap();
+        assert_eq!(transactions.len(), 6);
+
+        assert_eq!(transactions[0].operation, Operation::FiatDeposit(Amount::new(dec!(1000), "EUR".to_owned())));
+
+        let trade = &transactions[1];
+        assert_eq!(trade.timestamp, parse_date_time("2024-01-15 09:00:00").unwrap());
+        assert_eq!(trade.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.02), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(790), "EUR".to_owned()),
+        });
+        assert_eq!(trade.fee, Some(Amount::new(dec!(2.50), "EUR".to_owned())));
+
+        assert_eq!(transactions[2].operation, Operation::Staking(Amount::new(dec!(0.5), "DOT".to_owned())));
+        assert_eq!(transactions[3].operation, Operation::Expense(Amount::new(dec!(0.001), "BTC".to_owned())));
+        assert_eq!(transactions[3].description.as_deref(), Some("Coffee"));
+        assert_eq!(transactions[4].operation, Operation::IncomingGift(Amount::new(dec!(0.003), "BTC".to_owned())));
+
+        let withdrawal = &transactions[5];
+        assert_eq!(withdrawal.operation, Operation::Send(Amount::new(dec!(0.01), "BTC".to_owned())));
+        assert_eq!(withdrawal.fee, Some(Amount::new(dec!(0.0001), "BTC".to_owned())));
+
+        // an unmatched send is exported as withdrawal, without its value
+        let mut transactions = transactions;
+        let mut sell = Transaction::new(parse_date_time("2024-04-01 12:00:00").unwrap(), Operation::Sell(Amount::new(dec!(0.005), "BTC".to_owned())));
+        sell.value = Some(Amount::from_fiat(dec!(300)));
+        transactions.push(sell);
+
+        let output_path = std::env::temp_dir().join(format!("raccoin_cointracking_{}.csv", std::process::id()));
+        assert_eq!(save_transactions_to_cointracking_csv(&transactions, &output_path).unwrap(), 7);
+        assert!(COINTRACKING_CSV.detect_from_file(&output_path).unwrap());
+        // the dates are exported in UTC
+        let options = LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None };
+        let reloaded = load_cointracking_csv(&output_path, &options).unwrap();
+        std::fs::remove_file(output_path).unwrap();
+        assert_eq!(reloaded.iter().map(|tx| (tx.timestamp, &tx.operation)).take(6).collect::<Vec<_>>(),
+                   transactions.iter().map(|tx| (tx.timestamp, &tx.operation)).take(6).collect::<Vec<_>>());
+        assert_eq!(reloaded[6].operation, Operation::Send(Amount::new(dec!(0.005), "BTC".to_owned())));
+    }
+}
@@ -5,7 +5,7 @@ use chrono::NaiveDateTime;
 use rust_decimal::Decimal;
 use serde::{Serialize, Deserialize};
 
-use crate::{time::{serialize_date_time, deserialize_date_time}, base::{Transaction, Operation, Amount}, CsvSpec, TransactionSource};
+use crate::{time::{serialize_date_time, deserialize_date_time}, base::{Transaction, Operation, Amount}, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -399,7 +399,7 @@ pub(crate) fn save_transactions_to_ctc_csv(transactions: &Vec<Transaction>, outp
 }
 
 // loads a CSV file that was prepared in CryptoTaxCalculator import format
-fn load_ctc_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_ctc_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -436,7 +436,6 @@ static CTC_IMPORT_CSV: TransactionSource = TransactionSource {
         "Reference Price Per Unit (Optional)",
         "Reference Price Currency (Optional)",
     ])],
-    detect: None,
     load_sync: Some(load_ctc_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
@@ -0,0 +1,107 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::base::{Amount, Transaction};
+
+/// Maps a currency symbol to its canonical symbol, optionally only for
+/// transactions within a certain date range.
+///
+/// Aliases are applied to the transactions of every source after loading, so
+/// that variants of the same currency are treated as one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CurrencyAlias {
+    pub from: String,
+    pub to: String,
+    /// The first day on which the alias applies.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub since: Option<NaiveDate>,
+    /// The first day on which the alias no longer applies.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub until: Option<NaiveDate>,
+}
+
+impl CurrencyAlias {
+    fn applies(&self, currency: &str, timestamp: NaiveDateTime) -> bool {
+        self.from == currency &&
+            self.since.is_none_or(|since| timestamp.date() >= since) &&
+            self.until.is_none_or(|until| timestamp.date() < until)
+    }
+}
+
+fn apply_to_amount(aliases: &[CurrencyAlias], amount: &mut Amount, timestamp: NaiveDateTime) {
+    let effective_currency = amount.effective_currency();
+    let alias = aliases.iter().find(|alias| {
+        alias.applies(&amount.currency, timestamp) || alias.applies(&effective_currency, timestamp)
+    });
+    if let Some(alias) = alias {
+        amount.currency = alias.to.clone();
+        // the alias decides the identity of the token
+        amount.asset = None;
+    }
+}
+
+/// Replaces any aliased currencies in the given transactions by their
+/// canonical currency.
+pub(crate) fn apply_currency_aliases(aliases: &[CurrencyAlias], transactions: &mut [Transaction]) {
+    if aliases.is_empty() {
+        return;
+    }
+
+    for tx in transactions {
+        let timestamp = tx.timestamp;
+        let (incoming, outgoing) = tx.incoming_outgoing_mut();
+        for amount in [incoming, outgoing].into_iter().flatten() {
+            apply_to_amount(aliases, amount, timestamp);
+        }
+        if let Some(fee) = &mut tx.fee {
+            apply_to_amount(aliases, fee, timestamp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    fn receive(date_time: &str, currency: &str) -> Transaction {
+        Transaction::receive(parse_date_time(date_time).unwrap(), Amount::new(dec!(1), currency.to_owned()))
+    }
+
+    fn currency(tx: &Transaction) -> &str {
+        &tx.incoming_outgoing().0.unwrap().currency
+    }
+
+    #[test]
+    fn alias_with_date_range() {
+        let aliases = vec![
+            CurrencyAlias {
+                from: "LUNA".to_owned(),
+                to: "LUNC".to_owned(),
+                since: None,
+                until: NaiveDate::from_ymd_opt(2022, 5, 27),
+            },
+            CurrencyAlias {
+                from: "XBT".to_owned(),
+                to: "BTC".to_owned(),
+                since: None,
+                until: None,
+            },
+        ];
+
+        let mut transactions = vec![
+            receive("2022-05-26 23:59:59", "LUNA"),
+            receive("2022-05-27 00:00:00", "LUNA"),
+            receive("2022-05-27 00:00:00", "XBT"),
+        ];
+        transactions[2].fee = Some(Amount::new(dec!(0.0001), "XBT".to_owned()));
+
+        apply_currency_aliases(&aliases, &mut transactions);
+
+        assert_eq!(currency(&transactions[0]), "LUNC");
+        assert_eq!(currency(&transactions[1]), "LUNA");
+        assert_eq!(currency(&transactions[2]), "BTC");
+        assert_eq!(transactions[2].fee.as_ref().unwrap().currency, "BTC");
+    }
+}
@@ -0,0 +1,361 @@
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base::{Amount, Operation, OperationType, Transaction},
+    time::local_to_utc,
+    LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+/// The columns of a custom CSV file, by header name.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct CustomCsvColumns {
+    pub(crate) timestamp: String,
+    /// Without a type column, positive amounts are received and negative
+    /// amounts are sent.
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none", default)]
+    pub(crate) type_: Option<String>,
+    /// The amount received or sent. For trades, a positive amount is the
+    /// incoming side and a negative amount the outgoing side.
+    pub(crate) amount: String,
+    pub(crate) currency: String,
+    /// The other side of a trade, or the fiat value of other transactions.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) counter_amount: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) counter_currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) fee: Option<String>,
+    /// Defaults to the currency of the amount.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) fee_currency: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) tx_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) description: Option<String>,
+}
+
+/// A user-defined mapping of CSV columns to transaction fields, for exports
+/// of exchanges that are not supported out of the box.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct CustomCsvMapping {
+    pub(crate) name: String,
+    pub(crate) delimiter: char,
+    pub(crate) decimal_separator: char,
+    /// A chrono format string. When empty, RFC 3339 and "%Y-%m-%d %H:%M:%S"
+    /// are accepted.
+    #[serde(default)]
+    pub(crate) date_format: String,
+    pub(crate) columns: CustomCsvColumns,
+    /// The operation for each value of the type column. Rows with other
+    /// types are skipped.
+    #[serde(default)]
+    pub(crate) types: BTreeMap<String, OperationType>,
+}
+
+impl Default for CustomCsvMapping {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            delimiter: ',',
+            decimal_separator: '.',
+            date_format: String::new(),
+            columns: CustomCsvColumns::default(),
+            types: BTreeMap::new(),
+        }
+    }
+}
+
+impl CustomCsvMapping {
+    fn parse_decimal(&self, raw: &str) -> Result<Option<Decimal>> {
+        let raw: String = raw.chars().filter(|c| !c.is_whitespace()).collect();
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        let normalized = if self.decimal_separator == ',' {
+            raw.replace('.', "").replace(',', ".")
+        } else {
+            raw.replace(',', "")
+        };
+        Decimal::from_str(&normalized)
+            .or_else(|_| Decimal::from_scientific(&normalized))
+            .map(Some)
+            .with_context(|| format!("invalid number '{}'", raw))
+    }
+
+    fn parse_timestamp(&self, raw: &str, time_zone: Tz) -> Result<NaiveDateTime> {
+        let raw = raw.trim();
+        if self.date_format.is_empty() {
+            return DateTime::parse_from_rfc3339(raw).map(|date| date.naive_utc())
+                .or_else(|_| NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S").map(|date| local_to_utc(date, time_zone)))
+                .with_context(|| format!("invalid date '{}'", raw));
+        }
+
+        // formats with a time zone offset are converted to UTC, while dates
+        // without are in the time zone of the source
+        DateTime::parse_from_str(raw, &self.date_format).map(|date| date.naive_utc())
+            .or_else(|_| NaiveDateTime::parse_from_str(raw, &self.date_format).map(|date| local_to_utc(date, time_zone)))
+            .or_else(|_| NaiveDate::parse_from_str(raw, &self.date_format).map(|date| local_to_utc(date.into(), time_zone)))
+            .with_context(|| format!("date '{}' does not match format '{}'", raw, self.date_format))
+    }
+
+    fn parse_row(&self, row: &CustomCsvRow, time_zone: Tz) -> Result<Option<Transaction>> {
+        let columns = &self.columns;
+        let timestamp = self.parse_timestamp(row.get(&columns.timestamp)?, time_zone)?;
+        let quantity = self.parse_decimal(row.get(&columns.amount)?)?.context("missing amount")?;
+        let currency = row.get(&columns.currency)?.trim().to_owned();
+        let amount = Amount::new(quantity.abs(), currency.clone());
+
+        let counter = match (&columns.counter_amount, &columns.counter_currency) {
+            (Some(amount_column), Some(currency_column)) => {
+                let currency = row.get(currency_column)?.trim();
+                match self.parse_decimal(row.get(amount_column)?)? {
+                    Some(quantity) if !currency.is_empty() => Some(Amount::new(quantity.abs(), currency.to_owned())),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        let operation_type = match &columns.type_ {
+            Some(type_column) => {
+                let type_ = row.get(type_column)?.trim();
+                match self.types.get(type_) {
+                    Some(operation_type) => *operation_type,
+                    None => return Ok(None),
+                }
+            }
+            None if quantity.is_sign_negative() => {
+                if amount.is_fiat() { OperationType::FiatWithdrawal } else { OperationType::Send }
+            }
+            None => {
+                if amount.is_fiat() { OperationType::FiatDeposit } else { OperationType::Receive }
+            }
+        };
+
+        let mut tx = match operation_type {
+            OperationType::Trade | OperationType::Swap => {
+                let counter = counter.clone().context("missing counter amount for trade")?;
+                let (incoming, outgoing) = if quantity.is_sign_negative() { (counter, amount) } else { (amount, counter) };
+                let operation = Operation::Trade { incoming, outgoing };
+                Transaction::new(timestamp, operation.reclassified(operation_type).expect("trade can be a swap"))
+            }
+            _ => {
+                let operation = Operation::Receive(amount.clone()).reclassified(operation_type)
+                    .ok_or_else(|| anyhow!("cannot map to {}", operation_type))?;
+                let mut tx = Transaction::new(timestamp, operation);
+                tx.value = counter.filter(|counter| counter.is_fiat());
+                tx
+            }
+        };
+
+        if let Some(fee_column) = &columns.fee {
+            if let Some(fee) = self.parse_decimal(row.get(fee_column)?)?.filter(|fee| !fee.is_zero()) {
+                let fee_currency = match &columns.fee_currency {
+                    Some(currency_column) => row.get(currency_column)?.trim().to_owned(),
+                    None => currency,
+                };
+                tx.fee = Some(Amount::new(fee.abs(), fee_currency));
+            }
+        }
+        let text = |column: &Option<String>| -> Result<Option<String>> {
+            match column {
+                Some(column) => Ok(Some(row.get(column)?.trim().to_owned()).filter(|s| !s.is_empty())),
+                None => Ok(None),
+            }
+        };
+        tx.tx_hash = text(&columns.tx_hash)?;
+        tx.description = text(&columns.description)?;
+
+        Ok(Some(tx))
+    }
+}
+
+struct CustomCsvRow<'a> {
+    headers: &'a csv::StringRecord,
+    record: &'a csv::StringRecord,
+}
+
+impl CustomCsvRow<'_> {
+    fn get(&self, column: &str) -> Result<&str> {
+        let index = self.headers.iter().position(|header| header == column)
+            .with_context(|| format!("no column named '{}'", column))?;
+        Ok(self.record.get(index).unwrap_or_default())
+    }
+}
+
+/// Reads the column names of a CSV file.
+pub(crate) fn read_headers(input_path: &Path, delimiter: char) -> Result<Vec<String>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter as u8)
+        .from_path(input_path)?;
+    Ok(rdr.headers()?.iter().map(|header| header.to_owned()).collect())
+}
+
+/// Guesses the delimiter of a CSV file from its header line.
+pub(crate) fn guess_delimiter(input_path: &Path) -> char {
+    [',', ';', '\t'].into_iter()
+        .find(|&delimiter| read_headers(input_path, delimiter).is_ok_and(|headers| headers.len() > 1))
+        .unwrap_or(',')
+}
+
+/// Parses the rows of a CSV file using the given mapping. Returns the line
+/// number and either the transaction, `None` for skipped rows, or the error
+/// for each row.
+pub(crate) fn parse_rows(input_path: &Path, mapping: &CustomCsvMapping, time_zone: Tz) -> Result<Vec<(usize, Result<Option<Transaction>>)>> {
+    if !mapping.delimiter.is_ascii() {
+        return Err(anyhow!("the delimiter must be an ASCII character"));
+    }
+
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(mapping.delimiter as u8)
+        .flexible(true)
+        .from_path(input_path)?;
+    let headers = rdr.headers()?.clone();
+    let mut record = csv::StringRecord::new();
+
+    let mut rows = Vec::new();
+    while rdr.read_record(&mut record)? {
+        let line = record.position().map_or(0, |position| position.line() as usize);
+        let row = CustomCsvRow { headers: &headers, record: &record };
+        rows.push((line, mapping.parse_row(&row, time_zone)));
+    }
+
+    Ok(rows)
+}
+
+/// Parses a type mapping written as one "value = Operation" pair per line.
+pub(crate) fn parse_type_mapping(text: &str) -> Result<BTreeMap<String, OperationType>> {
+    let mut types = BTreeMap::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        let (value, operation) = line.rsplit_once('=')
+            .with_context(|| format!("expected 'value = Operation' instead of '{}'", line))?;
+        let operation = OperationType::from_str(operation.trim())
+            .with_context(|| format!("unknown operation '{}'", operation.trim()))?;
+        types.insert(value.trim().to_owned(), operation);
+    }
+    Ok(types)
+}
+
+pub(crate) fn format_type_mapping(types: &BTreeMap<String, OperationType>) -> String {
+    types.iter().map(|(value, operation)| format!("{} = {}\n", value, operation)).collect()
+}
+
+pub(crate) fn load_mapping(input_path: &Path) -> Result<CustomCsvMapping> {
+    let json = std::fs::read_to_string(input_path)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+pub(crate) fn save_mapping(mapping: &CustomCsvMapping, output_path: &Path) -> Result<()> {
+    std::fs::write(output_path, serde_json::to_string_pretty(mapping)?)?;
+    Ok(())
+}
+
+// loads a CSV file using the column mapping configured for the source
+fn load_custom_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mapping = options.custom_csv.as_ref().context("no column mapping configured for this source")?;
+
+    let mut transactions = Vec::new();
+    for (line, result) in parse_rows(input_path, mapping, options.time_zone)? {
+        match result {
+            Ok(tx) => transactions.extend(tx),
+            Err(e) => println!("{}: skipping line {}: {}", mapping.name, line, e),
+        }
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+
+    Ok(transactions)
+}
+
+// never detected, since sources of this type are added with a mapping
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+pub(crate) static CUSTOM_CSV: TransactionSource = TransactionSource {
+    id: "CustomCsv",
+    label: "Custom CSV",
+    load_sync: Some(load_custom_csv),
+    local_time: true,
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    fn example_mapping() -> CustomCsvMapping {
+        CustomCsvMapping {
+            name: "Example Exchange".to_owned(),
+            delimiter: ';',
+            decimal_separator: ',',
+            date_format: "%d.%m.%Y %H:%M".to_owned(),
+            columns: CustomCsvColumns {
+                timestamp: "Datum".to_owned(),
+                type_: Some("Art".to_owned()),
+                amount: "Menge".to_owned(),
+                currency: "Coin".to_owned(),
+                counter_amount: Some("Betrag".to_owned()),
+                counter_currency: Some("Währung".to_owned()),
+                fee: Some("Gebühr".to_owned()),
+                fee_currency: None,
+                tx_hash: Some("TxID".to_owned()),
+                description: None,
+            },
+            types: parse_type_mapping("Kauf = Trade\nVerkauf = Trade\nAuszahlung = Send\nZinsen = Staking\n").unwrap(),
+        }
+    }
+
+    #[test]
+    fn load_with_mapping() {
+        let path = Path::new("tests/data/custom.csv");
+        assert_eq!(read_headers(path, ';').unwrap(), vec!["Datum", "Art", "Menge", "Coin", "Betrag", "Währung", "Gebühr", "TxID"]);
+
+        let options = LoadOptions { time_zone: chrono_tz::Europe::Berlin, account: None, custom_csv: Some(example_mapping()) };
+        let transactions = load_custom_csv(path, &options).unwrap();
+        assert_eq!(transactions.len(), 4);
+
+        let buy = &transactions[0];
+        assert_eq!(buy.timestamp, parse_date_time("2024-01-15 09:30:00").unwrap());
+        assert_eq!(buy.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.05), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(2000), "EUR".to_owned()),
+        });
+        assert_eq!(buy.fee, Some(Amount::new(dec!(0.0001), "BTC".to_owned())));
+
+        assert_eq!(transactions[1].operation, Operation::Staking(Amount::new(dec!(0.0002), "BTC".to_owned())));
+        assert_eq!(transactions[1].value, Some(Amount::from_fiat(dec!(8.40))));
+
+        assert_eq!(transactions[2].operation, Operation::Trade {
+            incoming: Amount::new(dec!(1050), "EUR".to_owned()),
+            outgoing: Amount::new(dec!(0.025), "BTC".to_owned()),
+        });
+
+        let send = &transactions[3];
+        assert_eq!(send.operation, Operation::Send(Amount::new(dec!(0.02), "BTC".to_owned())));
+        assert_eq!(send.tx_hash.as_deref(), Some("a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d"));
+
+        // the row with an unmapped type is skipped
+        let rows = parse_rows(path, &example_mapping(), chrono_tz::UTC).unwrap();
+        assert_eq!(rows.len(), 5);
+        assert!(matches!(rows[3], (5, Ok(None))));
+    }
+
+    #[test]
+    fn mapping_round_trip() {
+        let mapping = example_mapping();
+        assert_eq!(parse_type_mapping(&format_type_mapping(&mapping.types)).unwrap(), mapping.types);
+        assert!(parse_type_mapping("Kauf = Purchase").is_err());
+
+        let output_path = std::env::temp_dir().join(format!("raccoin_custom_csv_mapping_{}.json", std::process::id()));
+        save_mapping(&mapping, &output_path).unwrap();
+        assert_eq!(load_mapping(&output_path).unwrap(), mapping);
+        std::fs::remove_file(output_path).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use chrono::Duration;
+use rust_decimal_macros::dec;
+
+use crate::base::{Amount, Transaction};
+
+/// Why two transactions were considered duplicates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DuplicateReason {
+    /// Both transactions have the same transaction hash.
+    SameTxHash,
+    /// Both transactions happened at nearly the same time and involve nearly
+    /// the same amounts.
+    SimilarTimeAndAmount,
+}
+
+/// Returns whether the given amounts are the same, or differ by at most 0.1%.
+fn similar_amounts(a: Option<&Amount>, b: Option<&Amount>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            a.effective_currency() == b.effective_currency() &&
+                (a.quantity - b.quantity).abs() <= a.quantity.abs().max(b.quantity.abs()) * dec!(0.001)
+        }
+        _ => false,
+    }
+}
+
+/// Returns whether the given amounts are in the same currency.
+fn same_currency(a: Option<&Amount>, b: Option<&Amount>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => a.effective_currency() == b.effective_currency(),
+        _ => false,
+    }
+}
+
+fn duplicate_reason(a: &Transaction, b: &Transaction) -> Option<DuplicateReason> {
+    let (a_incoming, a_outgoing) = a.incoming_outgoing();
+    let (b_incoming, b_outgoing) = b.incoming_outgoing();
+
+    match (&a.tx_hash, &b.tx_hash) {
+        // Sources may disagree about the amounts (for example whether the fee
+        // is included), so with the same hash only the currencies need to match.
+        (Some(a_hash), Some(b_hash)) if a_hash.eq_ignore_ascii_case(b_hash) => {
+            (same_currency(a_incoming, b_incoming) && same_currency(a_outgoing, b_outgoing))
+                .then_some(DuplicateReason::SameTxHash)
+        }
+        (Some(_), Some(_)) => None,
+        _ => {
+            ((a.timestamp - b.timestamp).abs() <= Duration::minutes(1) &&
+                similar_amounts(a_incoming, b_incoming) &&
+                similar_amounts(a_outgoing, b_outgoing))
+                .then_some(DuplicateReason::SimilarTimeAndAmount)
+        }
+    }
+}
+
+/// Finds transactions that appear to be the same, but were loaded from
+/// different sources of a wallet.
+///
+/// `sources` contains the source index for each transaction. Returns pairs of
+/// transaction indices, where each transaction is part of at most one pair.
+pub(crate) fn find_duplicates(transactions: &[Transaction], sources: &[usize]) -> Vec<(usize, usize, DuplicateReason)> {
+    let mut order: Vec<usize> = (0..transactions.len()).collect();
+    order.sort_by(|&a, &b| transactions[a].timestamp.cmp(&transactions[b].timestamp).then(sources[a].cmp(&sources[b])));
+
+    let mut by_tx_hash: HashMap<String, Vec<usize>> = HashMap::new();
+    for &index in &order {
+        if let Some(tx_hash) = &transactions[index].tx_hash {
+            by_tx_hash.entry(tx_hash.to_ascii_lowercase()).or_default().push(index);
+        }
+    }
+
+    let mut paired = vec![false; transactions.len()];
+    let mut duplicates = Vec::new();
+
+    let mut try_pair = |a: usize, b: usize| {
+        if paired[a] || paired[b] || sources[a] == sources[b] {
+            return;
+        }
+        if let Some(reason) = duplicate_reason(&transactions[a], &transactions[b]) {
+            paired[a] = true;
+            paired[b] = true;
+            duplicates.push((a, b, reason));
+        }
+    };
+
+    // transactions with the same hash can be far apart in time, for example
+    // when one of the sources used a different time zone
+    for group in by_tx_hash.values() {
+        for (position, &a) in group.iter().enumerate() {
+            for &b in &group[position + 1..] {
+                try_pair(a, b);
+            }
+        }
+    }
+
+    for (position, &a) in order.iter().enumerate() {
+        let latest = transactions[a].timestamp + Duration::minutes(1);
+        for &b in order[position + 1..].iter().take_while(|&&b| transactions[b].timestamp <= latest) {
+            try_pair(a, b);
+        }
+    }
+
+    duplicates.sort_by_key(|&(a, b, _)| (transactions[a].timestamp.min(transactions[b].timestamp), a.min(b)));
+    duplicates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal::Decimal;
+
+    fn send(date_time: &str, quantity: Decimal, tx_hash: Option<&str>) -> Transaction {
+        let mut tx = Transaction::send(parse_date_time(date_time).unwrap(), Amount::new(quantity, "BTC".to_owned()));
+        tx.tx_hash = tx_hash.map(str::to_owned);
+        tx
+    }
+
+    #[test]
+    fn same_tx_hash() {
+        let transactions = vec![
+            send("2023-01-01 10:00:00", dec!(1.0), Some("abc")),
+            send("2023-01-01 11:00:00", dec!(1.0001), Some("ABC")),
+            send("2023-01-01 11:00:00", dec!(1.0), Some("def")),
+        ];
+        let duplicates = find_duplicates(&transactions, &[0, 1, 1]);
+        assert_eq!(duplicates, vec![(0, 1, DuplicateReason::SameTxHash)]);
+    }
+
+    #[test]
+    fn similar_time_and_amount() {
+        let transactions = vec![
+            send("2023-01-01 10:00:00", dec!(1.0), None),
+            send("2023-01-01 10:00:30", dec!(1.0005), Some("abc")),
+            send("2023-01-01 10:00:10", dec!(1.0), None),
+            send("2023-01-01 10:05:00", dec!(1.0), None),
+        ];
+        let duplicates = find_duplicates(&transactions, &[0, 1, 0, 1]);
+        assert_eq!(duplicates, vec![(0, 1, DuplicateReason::SimilarTimeAndAmount)]);
+    }
+
+    #[test]
+    fn same_source_is_not_duplicate() {
+        let transactions = vec![
+            send("2023-01-01 10:00:00", dec!(1.0), Some("abc")),
+            send("2023-01-01 10:00:00", dec!(1.0), Some("abc")),
+        ];
+        assert!(find_duplicates(&transactions, &[0, 0]).is_empty());
+    }
+}
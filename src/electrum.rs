@@ -1,12 +1,12 @@
 use std::path::Path;
 
 use anyhow::Result;
-use chrono::{NaiveDateTime, TimeZone};
-use chrono_tz::Europe::Berlin;
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
-use crate::{time::deserialize_date_time, base::{Transaction, Amount}, CsvSpec, TransactionSource};
+use crate::{time::{deserialize_date_time, local_to_utc}, base::{Transaction, Amount}, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 #[derive(Debug, Deserialize)]
@@ -22,25 +22,25 @@ struct ElectrumHistoryItem {
     timestamp: NaiveDateTime,
 }
 
-impl From<ElectrumHistoryItem> for Transaction {
-    fn from(item: ElectrumHistoryItem) -> Self {
-        let utc_time = Berlin.from_local_datetime(&item.timestamp).unwrap().naive_utc();
-        let mut tx = if item.value < Decimal::ZERO {
-            let amount = -item.value - item.fee.unwrap_or_default();
+impl ElectrumHistoryItem {
+    fn into_transaction(self, time_zone: Tz) -> Transaction {
+        let utc_time = local_to_utc(self.timestamp, time_zone);
+        let mut tx = if self.value < Decimal::ZERO {
+            let amount = -self.value - self.fee.unwrap_or_default();
             Transaction::send(utc_time, Amount::new(amount, "BTC".to_owned()))
         } else {
-            Transaction::receive(utc_time, Amount::new(item.value, "BTC".to_owned()))
+            Transaction::receive(utc_time, Amount::new(self.value, "BTC".to_owned()))
         };
-        tx.description = if item.label.is_empty() { None } else { Some(item.label) };
-        tx.tx_hash = Some(item.transaction_hash);
+        tx.description = if self.label.is_empty() { None } else { Some(self.label) };
+        tx.tx_hash = Some(self.transaction_hash);
         tx.blockchain = Some("BTC".to_owned());
-        tx.fee = item.fee.map(|f| Amount::new(f, "BTC".to_string()));
+        tx.fee = self.fee.map(|f| Amount::new(f, "BTC".to_string()));
         tx
     }
 }
 
 // loads an Electrum CSV file into a list of unified transactions
-fn load_electrum_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_electrum_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -48,7 +48,7 @@ fn load_electrum_csv(input_path: &Path) -> Result<Vec<Transaction>> {
 
     for result in rdr.deserialize() {
         let record: ElectrumHistoryItem = result?;
-        transactions.push(record.into());
+        transactions.push(record.into_transaction(options.time_zone));
     }
 
     Ok(transactions)
@@ -68,7 +68,7 @@ static ELECTRUM_CSV: TransactionSource = TransactionSource {
         "fiat_fee",
         "timestamp",
     ])],
-    detect: None,
     load_sync: Some(load_electrum_csv),
-    load_async: None,
+    local_time: true,
+    ..TransactionSource::DEFAULT
 };
@@ -0,0 +1,217 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use bitcoin::{block, Amount, Script, ScriptBuf, Transaction, Txid};
+use electrum_client::{Client, ConfigBuilder, ElectrumApi, GetHistoryRes, Socks5Config};
+use esplora_client::{PrevOut, Tx, TxStatus, Vin, Vout};
+
+use crate::backend::{BackendSettings, RateLimiter};
+
+pub(crate) const DEFAULT_ELECTRUM_URL: &str = "ssl://electrum.blockstream.info:50002";
+
+/// A client for an Electrum server, like electrs or Fulcrum. The history is
+/// converted to the types used by Esplora, so that both backends produce the
+/// same transactions.
+pub(crate) struct ElectrumServer {
+    client: Arc<Client>,
+    rate_limiter: RateLimiter,
+}
+
+impl ElectrumServer {
+    pub(crate) async fn connect(settings: &BackendSettings) -> Result<Self> {
+        let mut config = ConfigBuilder::new().timeout(Some(30));
+        if let Some(proxy) = &settings.proxy {
+            // the Electrum client only supports SOCKS5 proxies
+            let address = proxy.strip_prefix("socks5h://")
+                .or_else(|| proxy.strip_prefix("socks5://"))
+                .with_context(|| format!("unsupported proxy {}, only SOCKS5 is supported", proxy))?;
+            config = config.socks5(Some(Socks5Config::new(address)));
+        }
+
+        let url = settings.url_or(DEFAULT_ELECTRUM_URL).to_owned();
+        let config = config.build();
+        let client = tokio::task::spawn_blocking(move || Client::from_config(&url, config)).await??;
+
+        Ok(Self {
+            client: Arc::new(client),
+            rate_limiter: settings.rate_limiter(0),
+        })
+    }
+
+    /// Returns the confirmed transactions of the given script, newest first
+    /// like Esplora does. Only the transactions that are not cached are
+    /// requested.
+    pub(crate) async fn script_txs(&self, script: &Script, cached: &[Tx]) -> Result<Vec<Tx>> {
+        self.rate_limiter.wait().await;
+
+        let client = self.client.clone();
+        let script = script.to_owned();
+        let cached = cached.to_vec();
+        tokio::task::spawn_blocking(move || script_txs(&client, &script, cached)).await?
+    }
+}
+
+fn script_txs(client: &Client, script: &ScriptBuf, cached: Vec<Tx>) -> Result<Vec<Tx>> {
+    // unconfirmed transactions have a height of 0 or -1
+    let history: Vec<_> = client.script_get_history(script)?
+        .into_iter()
+        .filter(|item| item.height > 0)
+        .collect();
+
+    let mut known: HashMap<Txid, Tx> = cached.into_iter().map(|tx| (tx.txid, tx)).collect();
+    let new_history: Vec<_> = history.iter().filter(|item| !known.contains_key(&item.tx_hash)).collect();
+    for tx in fetch_txs(client, &new_history)? {
+        known.insert(tx.txid, tx);
+    }
+
+    // cached transactions no longer in the history were removed by a reorg
+    let mut txs = history.iter()
+        .filter_map(|item| known.remove(&item.tx_hash))
+        .collect::<Vec<_>>();
+    txs.reverse();
+
+    Ok(txs)
+}
+
+fn fetch_txs(client: &Client, history: &[&GetHistoryRes]) -> Result<Vec<Tx>> {
+    if history.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let transactions = client.batch_transaction_get(history.iter().map(|item| &item.tx_hash))?;
+    let headers = client.batch_block_header(history.iter().map(|item| item.height as u32))?;
+
+    // the previous transactions provide the value and script of the inputs
+    let prev_txids: BTreeSet<Txid> = transactions.iter()
+        .flat_map(|tx| tx.input.iter())
+        .filter(|input| !input.previous_output.is_null())
+        .map(|input| input.previous_output.txid)
+        .collect();
+    let prev_txs: HashMap<Txid, Transaction> = prev_txids.iter()
+        .copied()
+        .zip(client.batch_transaction_get(&prev_txids)?)
+        .collect();
+
+    transactions.iter()
+        .zip(history)
+        .zip(&headers)
+        .map(|((tx, item), header)| esplora_tx(tx, item.height as u32, header, &prev_txs))
+        .collect()
+}
+
+/// Converts a transaction to the representation returned by Esplora.
+fn esplora_tx(
+    tx: &Transaction,
+    height: u32,
+    header: &block::Header,
+    prev_txs: &HashMap<Txid, Transaction>,
+) -> Result<Tx> {
+    let vin = tx.input.iter().map(|input| {
+        let is_coinbase = input.previous_output.is_null();
+        let prevout = if is_coinbase {
+            None
+        } else {
+            let outpoint = &input.previous_output;
+            let output = prev_txs.get(&outpoint.txid)
+                .and_then(|prev_tx| prev_tx.output.get(outpoint.vout as usize))
+                .ok_or_else(|| anyhow!("missing previous output {}", outpoint))?;
+            Some(PrevOut { value: output.value.to_sat(), scriptpubkey: output.script_pubkey.clone() })
+        };
+
+        Ok(Vin {
+            txid: input.previous_output.txid,
+            vout: input.previous_output.vout,
+            prevout,
+            scriptsig: input.script_sig.clone(),
+            witness: input.witness.to_vec(),
+            sequence: input.sequence.0,
+            is_coinbase,
+        })
+    }).collect::<Result<Vec<_>>>()?;
+
+    let vout: Vec<Vout> = tx.output.iter().map(|output| {
+        Vout { value: output.value.to_sat(), scriptpubkey: output.script_pubkey.clone() }
+    }).collect();
+
+    let total_in: u64 = vin.iter().filter_map(|vin| vin.prevout.as_ref().map(|prevout| prevout.value)).sum();
+    let total_out: u64 = tx.output.iter().map(|output| output.value).sum::<Amount>().to_sat();
+    let fee = if tx.is_coinbase() { 0 } else { total_in.saturating_sub(total_out) };
+
+    Ok(Tx {
+        txid: tx.compute_txid(),
+        version: tx.version.0,
+        locktime: tx.lock_time.to_consensus_u32(),
+        vin,
+        vout,
+        size: tx.total_size(),
+        weight: tx.weight().to_wu(),
+        status: TxStatus {
+            confirmed: true,
+            block_height: Some(height),
+            block_hash: Some(header.block_hash()),
+            block_time: Some(header.time as u64),
+        },
+        fee,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::esplora::tx_to_transaction;
+    use crate::base::{Amount as RaccoinAmount, Operation};
+    use bitcoin::{absolute::LockTime, hashes::Hash, transaction::Version, OutPoint, Sequence, TxIn, TxOut, Witness};
+    use std::collections::HashSet;
+
+    fn transaction(inputs: &[OutPoint], outputs: &[(u64, &ScriptBuf)]) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: inputs.iter().map(|&previous_output| TxIn {
+                previous_output,
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::MAX,
+                witness: Witness::new(),
+            }).collect(),
+            output: outputs.iter().map(|&(value, script)| TxOut {
+                value: Amount::from_sat(value),
+                script_pubkey: script.clone(),
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn convert_history_to_esplora_transactions() {
+        let own = ScriptBuf::new_op_return([1]);
+        let change = ScriptBuf::new_op_return([2]);
+        let other = ScriptBuf::new_op_return([3]);
+
+        let funding = transaction(&[OutPoint::new(Txid::from_byte_array([7; 32]), 0)], &[(100_000, &own)]);
+        let spending = transaction(&[OutPoint::new(funding.compute_txid(), 0)], &[(60_000, &other), (39_000, &change)]);
+
+        let header = block::Header {
+            version: block::Version::TWO,
+            prev_blockhash: bitcoin::BlockHash::from_byte_array([0; 32]),
+            merkle_root: bitcoin::TxMerkleNode::from_byte_array([0; 32]),
+            time: 1_700_000_000,
+            bits: bitcoin::CompactTarget::from_consensus(0),
+            nonce: 0,
+        };
+        let prev_txs = HashMap::from([(funding.compute_txid(), funding.clone())]);
+
+        let tx = esplora_tx(&spending, 800_000, &header, &prev_txs).unwrap();
+        assert_eq!(tx.fee, 1_000);
+        assert_eq!(tx.vin[0].prevout.as_ref().map(|prevout| prevout.value), Some(100_000));
+        assert_eq!(tx.status.block_time, Some(1_700_000_000));
+
+        let own_scripts = HashSet::from([own, change]);
+        let transaction = tx_to_transaction(&own_scripts, &tx);
+        assert_eq!(transaction.operation, Operation::Send(RaccoinAmount::from_satoshis(60_000)));
+        assert_eq!(transaction.fee, Some(RaccoinAmount::from_satoshis(1_000)));
+        assert_eq!(transaction.tx_hash, Some(spending.compute_txid().to_string()));
+
+        // the previous transaction of the funding is unknown
+        assert!(esplora_tx(&funding, 799_999, &header, &prev_txs).is_err());
+    }
+}
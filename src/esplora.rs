@@ -1,42 +1,161 @@
-use anyhow::Result;
-use std::{str::FromStr, collections::{HashMap, HashSet, hash_map::Entry}};
-use bitcoin::{Address, Network, bip32::{Xpub, DerivationPath, ChildNumber}, secp256k1::{Secp256k1, self}, base58, ScriptBuf};
+use anyhow::{anyhow, Context, Result};
+use std::{str::FromStr, collections::{HashMap, HashSet}};
+use bitcoin::{Address, Network, bip32::Xpub, base58, Script, ScriptBuf};
 use chrono::DateTime;
+use bitcoin::Txid;
 use esplora_client::{Builder, Tx};
 use esplora_client::r#async::AsyncClient;
+use futures::{stream, StreamExt};
+use miniscript::{Descriptor, DescriptorPublicKey};
 
-use crate::{base::{Transaction, Amount}, LoadFuture, TransactionSource};
+use crate::{backend::{BackendSettings, RateLimiter}, base::{Transaction, Amount}, bitcoin_cache::HistoryCache, electrum_server::ElectrumServer, LoadFuture, SyncOptions, TransactionSource};
 use linkme::distributed_slice;
 
-pub(crate) fn async_esplora_client() -> Result<AsyncClient, esplora_client::Error> {
-    let builder = Builder::new("https://blockstream.info/api");
-    builder.build_async()
+pub(crate) const DEFAULT_ESPLORA_URL: &str = "https://blockstream.info/api";
+
+/// An Esplora client which spreads out its requests as configured.
+pub(crate) struct Esplora {
+    client: AsyncClient,
+    rate_limiter: RateLimiter,
+}
+
+impl Esplora {
+    pub(crate) fn new(settings: &BackendSettings) -> Result<Self> {
+        let mut builder = Builder::new(settings.url_or(DEFAULT_ESPLORA_URL));
+        if let Some(proxy) = &settings.proxy {
+            builder = builder.proxy(proxy);
+        }
+        if let Some(api_key) = &settings.api_key {
+            builder = builder.header("Authorization", &format!("Bearer {}", api_key));
+        }
+
+        Ok(Self {
+            client: builder.build_async()?,
+            rate_limiter: settings.rate_limiter(0),
+        })
+    }
+
+    async fn scripthash_txs(&self, script: &Script, last_seen: Option<Txid>) -> Result<Vec<Tx>> {
+        self.rate_limiter.wait().await;
+        Ok(self.client.scripthash_txs(script, last_seen).await?)
+    }
+
+    async fn scripthash_tx_count(&self, script: &Script) -> Result<usize> {
+        self.rate_limiter.wait().await;
+        Ok(self.client.get_scripthash_stats(script).await?.chain_stats.tx_count as usize)
+    }
+
+    /// Returns the confirmed transactions of the given script, newest first.
+    /// Only the transactions newer than the cached ones are requested.
+    async fn script_txs(&self, script: &Script, cached: &[Tx]) -> Result<Vec<Tx>> {
+        // the statistics are cheaper to get than the history itself
+        let mut cached = cached;
+        if !cached.is_empty() {
+            let tx_count = self.scripthash_tx_count(script).await?;
+            if tx_count == cached.len() {
+                return Ok(cached.to_vec());
+            } else if tx_count < cached.len() {
+                // transactions disappeared in a reorg
+                cached = &[];
+            }
+        }
+        let known: HashSet<Txid> = cached.iter().map(|tx| tx.txid).collect();
+
+        let mut txs = self.scripthash_txs(script, None).await?;
+
+        // we may get up to 50 unconfirmed transactions, so filter them
+        txs.retain(|tx| tx.status.confirmed);
+
+        // repeat the request until we have all transactions, or reached the
+        // cached ones
+        let mut more = txs.len() == 25;
+        while more && !txs.iter().any(|tx| known.contains(&tx.txid)) {
+            let mut more_txs = self.scripthash_txs(script, Some(txs.last().unwrap().txid)).await?;
+            more = more_txs.len() == 25;
+            txs.append(&mut more_txs);
+        }
+
+        txs.retain(|tx| !known.contains(&tx.txid));
+        txs.extend(cached.iter().cloned());
+
+        Ok(txs)
+    }
+}
+
+/// The backends that can be used to sync Bitcoin sources.
+pub(crate) const BITCOIN_BACKENDS: &[&str] = &["Esplora", "Electrum"];
+
+/// The service used to fetch the history of Bitcoin scripts.
+pub(crate) enum BitcoinBackend {
+    Esplora(Esplora),
+    Electrum(ElectrumServer),
+}
+
+impl BitcoinBackend {
+    pub(crate) async fn new(options: &SyncOptions) -> Result<Self> {
+        match options.backend.as_deref() {
+            None | Some("Esplora") => Ok(Self::Esplora(Esplora::new(&options.backends.esplora)?)),
+            Some("Electrum") => Ok(Self::Electrum(ElectrumServer::connect(&options.backends.electrum).await?)),
+            Some(backend) => Err(anyhow!("unsupported backend {}", backend)),
+        }
+    }
+
+    async fn script_txs(&self, script: &Script, cached: &[Tx]) -> Result<Vec<Tx>> {
+        match self {
+            Self::Esplora(esplora) => esplora.script_txs(script, cached).await,
+            Self::Electrum(electrum) => electrum.script_txs(script, cached).await,
+        }
+    }
+}
+
+/// The default gap limit, as recommended by BIP-44.
+pub(crate) const DEFAULT_GAP_LIMIT: u32 = 20;
+
+/// The number of scripts for which the history is requested concurrently.
+const CONCURRENT_REQUESTS: usize = 8;
+
+/// Fetches the history of the given scripts concurrently, returning the
+/// results in the same order.
+async fn fetch_scripts(
+    backend: &BitcoinBackend,
+    cache: &HistoryCache,
+    scripts: Vec<ScriptBuf>,
+) -> Vec<(ScriptBuf, Result<Vec<Tx>>)> {
+    stream::iter(scripts)
+        .map(|script| async move {
+            let txs = backend.script_txs(&script, cache.get(&script)).await;
+            (script, txs)
+        })
+        .buffered(CONCURRENT_REQUESTS)
+        .collect()
+        .await
 }
 
 pub(crate) async fn address_transactions(
-    client: &AsyncClient,
+    backend: &BitcoinBackend,
+    cache: &mut HistoryCache,
     addresses: &Vec<String>,
 ) -> Result<Vec<Transaction>> {
     let mut pub_keys = HashSet::new();
-    let mut address_transactions: HashMap<Address, Result<Vec<Tx>>> = HashMap::new();
+    let mut scripts = Vec::new();
 
     for address in addresses {
         let address = Address::from_str(address)?.require_network(Network::Bitcoin)?;
-        pub_keys.insert(address.script_pubkey());
-
-        match address_transactions.entry(address) {
-            Entry::Occupied(_) => {}
-            Entry::Vacant(e) => {
-                let value = address_txs(client, e.key()).await;
-                e.insert(value);
-            }
-        };
+        let script = address.script_pubkey();
+        if pub_keys.insert(script.clone()) {
+            scripts.push(script);
+        }
     }
 
-    Ok(process_transactions(address_transactions, pub_keys))
+    let script_transactions: HashMap<ScriptBuf, Result<Vec<Tx>>> = fetch_scripts(backend, cache, scripts).await
+        .into_iter()
+        .collect();
+    cache.update(&script_transactions);
+
+    Ok(process_transactions(script_transactions, pub_keys))
 }
 
-fn tx_to_transaction(
+pub(crate) fn tx_to_transaction(
     addresses: &HashSet<ScriptBuf>,
     tx: &Tx
 ) -> Transaction {
@@ -96,190 +215,221 @@ fn tx_to_transaction(
     transaction
 }
 
-async fn address_txs(
-    client: &AsyncClient,
-    address: &Address,
-) -> Result<Vec<Tx>> {
-    let script_pubkey = address.script_pubkey();
-    let script = script_pubkey.as_script();
+/// Turns an extended public key into a multipath descriptor covering both
+/// the receive and change branches. The prefix determines the script type.
+fn xpub_descriptor(xpub: &str) -> Result<Descriptor<DescriptorPublicKey>> {
+    let mut xpub_data = base58::decode_check(xpub)?;
+    if xpub_data.len() < 4 {
+        return Err(anyhow!("invalid extended public key {}", xpub));
+    }
 
-    let mut txs = client.scripthash_txs(script, None).await?;
+    // replace the version bytes with 0488b21e, this way we can support ypub and zpub
+    xpub_data[0..4].copy_from_slice(&[0x04, 0x88, 0xb2, 0x1e]);
 
-    // we may get up to 50 unconfirmed transactions, so filter them
-    txs.retain(|tx| tx.status.confirmed);
+    let xpub_key = Xpub::decode(&xpub_data)?;
+    let descriptor = match xpub.get(..4) {
+        Some("xpub") => format!("pkh({}/<0;1>/*)", xpub_key),
+        Some("ypub") => format!("sh(wpkh({}/<0;1>/*))", xpub_key),
+        Some("zpub") => format!("wpkh({}/<0;1>/*)", xpub_key),
+        _ => return Err(anyhow!("unsupported extended public key prefix in {}", xpub)),
+    };
 
-    // repeat the request until we have all transactions
-    if txs.len() == 25 {
-        loop {
-            let mut more_txs = client.scripthash_txs(script, Some(txs.last().unwrap().txid)).await?;
-            let n = more_txs.len();
-            txs.append(&mut more_txs);
-            if n < 25 {
-                break;
-            }
-        }
-    }
+    Ok(Descriptor::from_str(&descriptor)?)
+}
 
-    Ok(txs)
+/// Parses an output descriptor, verifying its checksum when present, and
+/// splits multipath descriptors into one descriptor per branch.
+fn single_descriptors(descriptor: &str) -> Result<Vec<Descriptor<DescriptorPublicKey>>> {
+    let descriptor = Descriptor::<DescriptorPublicKey>::from_str(descriptor)
+        .with_context(|| format!("invalid output descriptor {}", descriptor))?;
+    Ok(descriptor.into_single_descriptors()?)
 }
 
-#[derive(Debug, Clone, Copy)]
-enum AddressType {
-    P2PKH,          // Legacy
-    P2SHWPKH,       // Legacy Segwit
-    P2WPKH,         // Segwit
+fn descriptor_script(descriptor: &Descriptor<DescriptorPublicKey>, index: u32) -> Result<ScriptBuf> {
+    Ok(descriptor.at_derivation_index(index)?.script_pubkey())
 }
 
-async fn scan_children<C: secp256k1::Verification>(
-    client: &AsyncClient,
-    address_transactions: &mut HashMap<Address, Result<Vec<Tx>>>,
-    secp: &Secp256k1<C>,
-    xpub_key: &Xpub,
-    derivation_path: &DerivationPath,
-    address_type: AddressType
+// Scans the scripts derived from a single-path descriptor, until `gap_limit`
+// consecutive ones without transactions are found. The scripts are fetched in
+// batches covering the gap after the last used script.
+async fn scan_descriptor(
+    backend: &BitcoinBackend,
+    cache: &HistoryCache,
+    script_transactions: &mut HashMap<ScriptBuf, Result<Vec<Tx>>>,
+    descriptor: &Descriptor<DescriptorPublicKey>,
+    gap_limit: u32,
 ) -> Result<()> {
-    let mut iter = derivation_path.normal_children();
-    let mut empty_addresses = 0;
-
-    for child in iter.by_ref() {
-        let key = xpub_key.derive_pub(secp, &child)?.to_pub();
-        let address = match address_type {
-            AddressType::P2PKH => Address::p2pkh(&key, Network::Bitcoin),
-            AddressType::P2SHWPKH => Address::p2shwpkh(&key, Network::Bitcoin),
-            AddressType::P2WPKH => Address::p2wpkh(&key, Network::Bitcoin),
-        };
-
-        println!("  checking address {}: {}", child, address);
-
-        let txs = match address_transactions.entry(address) {
-            Entry::Occupied(e) => {
-                e.into_mut()
+    // a descriptor without wildcard describes only a single script
+    let gap_limit = if descriptor.has_wildcard() { gap_limit } else { 1 };
+    let mut start = 0;
+    let mut end = gap_limit;
+
+    while start < end {
+        let batch_end = end;
+        let scripts = (start..batch_end)
+            .map(|index| descriptor_script(descriptor, index))
+            .collect::<Result<Vec<_>>>()?;
+        let new_scripts = scripts.iter()
+            .filter(|script| !script_transactions.contains_key(*script))
+            .cloned()
+            .collect();
+        script_transactions.extend(fetch_scripts(backend, cache, new_scripts).await);
+
+        for (index, script) in (start..batch_end).zip(scripts) {
+            let tx_count = script_transactions.get(&script)
+                .and_then(|txs| txs.as_ref().ok())
+                .map(Vec::len)
+                .unwrap_or_default();
+
+            match Address::from_script(&script, Network::Bitcoin) {
+                Ok(address) => println!("  address {}: {}, {} transactions", index, address, tx_count),
+                Err(_) => println!("  script {}: {}, {} transactions", index, script, tx_count),
             }
-            Entry::Vacant(e) => {
-                let value = address_txs(client, e.key()).await;
-                e.insert(value)
-            }
-        };
-
-        println!("   transaction count: {}", txs.as_ref().map(Vec::len).unwrap_or_default());
 
-        if !txs.as_ref().is_ok_and(|txs| { !txs.is_empty() }) {
-            empty_addresses += 1;
-            if empty_addresses > 10 {
-                break;
+            if tx_count > 0 {
+                end = end.max(index + 1 + gap_limit);
             }
         }
+
+        start = batch_end;
     }
 
     Ok(())
 }
 
-async fn xpub_addresses_and_txs<C: secp256k1::Verification>(
-    client: &AsyncClient,
-    secp: &Secp256k1<C>,
-    xpub: &str,
-    address_transactions: &mut HashMap<Address, Result<Vec<Tx>>>,
-) -> Result<()> {
-    let mut xpub_data = base58::decode_check(xpub)?;
-
-    // replace the version bytes with 0488b21e, this way we can support ypub and zpub
-    xpub_data[0..4].copy_from_slice(&[0x04, 0x88, 0xb2, 0x1e]);
-
-    let xpub_key = Xpub::decode(&xpub_data)?;
-    let xpub_prefix = xpub.split_at(4).0;
-    let address_type = match xpub_prefix {
-        "xpub" => AddressType::P2PKH,
-        "ypub" => AddressType::P2SHWPKH,
-        "zpub" => AddressType::P2WPKH,
-        _ => panic!("unsupported xpub prefix {}", xpub_prefix), // todo: return error instead
-    };
-
-    println!("iterating addresses from xpub {}", xpub);
-
-    println!(" receive addresses:");
+pub(crate) async fn descriptors_transactions(
+    backend: &BitcoinBackend,
+    cache: &mut HistoryCache,
+    descriptors: Vec<Descriptor<DescriptorPublicKey>>,
+    gap_limit: u32,
+) -> Result<Vec<Transaction>> {
+    // Collect all relevant transactions in a map from ScriptBuf -> Vec<Tx>
+    let mut script_transactions: HashMap<ScriptBuf, Result<Vec<Tx>>> = HashMap::new();
 
-    let receive_path = DerivationPath::master().child(ChildNumber::Normal { index: 0 });
-    scan_children(client, address_transactions, secp, &xpub_key, &receive_path, address_type).await?;
+    for descriptor in descriptors {
+        println!("iterating scripts from descriptor {}", descriptor);
+        scan_descriptor(backend, cache, &mut script_transactions, &descriptor, gap_limit).await?;
+    }
+    cache.update(&script_transactions);
 
-    println!(" change addresses:");
+    let mut pub_keys = HashSet::new();
+    pub_keys.extend(script_transactions.iter().filter_map(|(script, txs)| {
+        match txs {
+            Ok(txs) if !txs.is_empty() => Some(script.clone()),
+            _ => None,
+        }
+    }));
 
-    let change_path = DerivationPath::master().child(ChildNumber::Normal { index: 1 });
-    scan_children(client, address_transactions, secp, &xpub_key, &change_path, address_type).await?;
+    println!("collected {} active scripts (scanned {})", pub_keys.len(), script_transactions.len());
 
-    Ok(())
+    Ok(process_transactions(script_transactions, pub_keys))
 }
 
 pub(crate) async fn xpub_addresses_transactions(
-    client: &AsyncClient,
-    xpubs: &Vec<String>,
+    backend: &BitcoinBackend,
+    cache: &mut HistoryCache,
+    xpubs: &[String],
+    gap_limit: u32,
 ) -> Result<Vec<Transaction>> {
-    let secp = Secp256k1::new();
-
-    // Collect all relevant transactions in a map from Address -> Vec<Tx>
-    let mut address_transactions: HashMap<Address, Result<Vec<Tx>>> = HashMap::new();
-
-    // todo: do in parallel
+    let mut descriptors = Vec::new();
     for xpub in xpubs {
-        xpub_addresses_and_txs(client, &secp, xpub, &mut address_transactions).await?;
+        descriptors.extend(xpub_descriptor(xpub)?.into_single_descriptors()?);
     }
 
-    let mut pub_keys = HashSet::new();
-    pub_keys.extend(address_transactions.iter().filter_map(|(address, txs)| {
-        match txs {
-            Ok(txs) if !txs.is_empty() => Some(address.script_pubkey()),
-            _ => None,
-        }
-    }));
+    descriptors_transactions(backend, cache, descriptors, gap_limit).await
+}
 
-    println!("collected {} active addresses (scanned {})", pub_keys.len(), address_transactions.len());
+pub(crate) async fn output_descriptors_transactions(
+    backend: &BitcoinBackend,
+    cache: &mut HistoryCache,
+    output_descriptors: &[String],
+    gap_limit: u32,
+) -> Result<Vec<Transaction>> {
+    let mut descriptors = Vec::new();
+    for descriptor in output_descriptors {
+        descriptors.extend(single_descriptors(descriptor)?);
+    }
 
-    Ok(process_transactions(address_transactions, pub_keys))
+    descriptors_transactions(backend, cache, descriptors, gap_limit).await
 }
 
 fn split_whitespace_owned(value: &str) -> Vec<String> {
     value.split_ascii_whitespace().map(|item| item.to_owned()).collect()
 }
 
-pub(crate) fn load_bitcoin_addresses_async(source_path: String) -> LoadFuture {
+pub(crate) fn load_bitcoin_addresses_async(source_path: String, options: SyncOptions) -> LoadFuture {
     Box::pin(async move {
-        let esplora_client = async_esplora_client().unwrap();
-        address_transactions(&esplora_client, &split_whitespace_owned(&source_path)).await
+        let backend = BitcoinBackend::new(&options).await?;
+        let mut cache = HistoryCache::load(options.cache_dir.as_deref());
+        let transactions = address_transactions(&backend, &mut cache, &split_whitespace_owned(&source_path)).await;
+        save_cache(&cache);
+        transactions
     })
 }
 
-pub(crate) fn load_bitcoin_xpubs_async(source_path: String) -> LoadFuture {
+pub(crate) fn load_bitcoin_xpubs_async(source_path: String, options: SyncOptions) -> LoadFuture {
     Box::pin(async move {
-        let esplora_client = async_esplora_client().unwrap();
-        xpub_addresses_transactions(&esplora_client, &split_whitespace_owned(&source_path)).await
+        let backend = BitcoinBackend::new(&options).await?;
+        let mut cache = HistoryCache::load(options.cache_dir.as_deref());
+        let gap_limit = options.gap_limit.unwrap_or(DEFAULT_GAP_LIMIT);
+        let transactions = xpub_addresses_transactions(&backend, &mut cache, &split_whitespace_owned(&source_path), gap_limit).await;
+        save_cache(&cache);
+        transactions
     })
 }
 
+pub(crate) fn load_bitcoin_descriptors_async(source_path: String, options: SyncOptions) -> LoadFuture {
+    Box::pin(async move {
+        let backend = BitcoinBackend::new(&options).await?;
+        let mut cache = HistoryCache::load(options.cache_dir.as_deref());
+        let gap_limit = options.gap_limit.unwrap_or(DEFAULT_GAP_LIMIT);
+        let transactions = output_descriptors_transactions(&backend, &mut cache, &split_whitespace_owned(&source_path), gap_limit).await;
+        save_cache(&cache);
+        transactions
+    })
+}
+
+fn save_cache(cache: &HistoryCache) {
+    if let Err(e) = cache.save() {
+        println!("warning: failed to save the Bitcoin history cache: {}", e);
+    }
+}
+
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
 static BITCOIN_ADDRESSES: TransactionSource = TransactionSource {
     id: "BitcoinAddresses",
     label: "Bitcoin Address(es)",
-    csv: &[],
-    detect: None,
-    load_sync: None,
     load_async: Some(load_bitcoin_addresses_async),
+    backends: BITCOIN_BACKENDS,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
 static BITCOIN_XPUBS: TransactionSource = TransactionSource {
     id: "BitcoinXpubs",
     label: "Bitcoin HD Wallet(s)",
-    csv: &[],
-    detect: None,
-    load_sync: None,
     load_async: Some(load_bitcoin_xpubs_async),
+    backends: BITCOIN_BACKENDS,
+    gap_limit: Some(DEFAULT_GAP_LIMIT),
+    ..TransactionSource::DEFAULT
+};
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static BITCOIN_DESCRIPTORS: TransactionSource = TransactionSource {
+    id: "BitcoinDescriptors",
+    label: "Bitcoin Output Descriptor(s)",
+    load_async: Some(load_bitcoin_descriptors_async),
+    backends: BITCOIN_BACKENDS,
+    gap_limit: Some(DEFAULT_GAP_LIMIT),
+    ..TransactionSource::DEFAULT
 };
 
 // Converts the transactions, using a set of tx_hash to skip duplicates
-fn process_transactions(address_transactions: HashMap<Address, Result<Vec<Tx>>>, pub_keys: HashSet<ScriptBuf>) -> Vec<Transaction> {
+fn process_transactions(script_transactions: HashMap<ScriptBuf, Result<Vec<Tx>>>, pub_keys: HashSet<ScriptBuf>) -> Vec<Transaction> {
     let mut processed_txs = HashSet::new();
     let mut transactions = Vec::new();
 
-    address_transactions.values().for_each(|txs| {
+    script_transactions.values().for_each(|txs| {
         if let Ok(txs) = txs {
             // iterate in reverse order to make the transactions somewhat chronological (at least per address...)
             txs.iter().rev().for_each(|tx| {
@@ -294,3 +444,160 @@ fn process_transactions(address_transactions: HashMap<Address, Result<Vec<Tx>>>,
     println!("processed {} unique transactions", processed_txs.len());
     transactions
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+
+    // BIP-84 test vector, derived from "abandon abandon ... about"
+    const ZPUB: &str = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+
+    fn address(descriptor: &Descriptor<DescriptorPublicKey>, index: u32) -> String {
+        Address::from_script(&descriptor_script(descriptor, index).unwrap(), Network::Bitcoin).unwrap().to_string()
+    }
+
+    #[test]
+    fn derive_addresses_from_descriptors() {
+        let branches = xpub_descriptor(ZPUB).unwrap().into_single_descriptors().unwrap();
+        assert_eq!(branches.len(), 2);
+        assert_eq!(address(&branches[0], 0), "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu");
+        assert_eq!(address(&branches[0], 1), "bc1qnjg0jd8228aq7egyzacy8cys3knf9xvrerkf9g");
+        assert_eq!(address(&branches[1], 0), "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+
+        // the same wallet as descriptor with origin info and checksum
+        let receive = branches[0].to_string();
+        let (receive, _) = receive.split_once('#').unwrap();
+        let descriptor = receive.replace("wpkh(", "wpkh([73c5da0a/84'/0'/0']").replace("/0/*", "/<0;1>/*");
+        let descriptor = Descriptor::<DescriptorPublicKey>::from_str(&descriptor).unwrap().to_string();
+        let parsed = single_descriptors(&descriptor).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(address(&parsed[1], 0), "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el");
+
+        // a damaged checksum is rejected
+        let (body, checksum) = descriptor.split_once('#').unwrap();
+        let damaged = format!("{}#{}", body, checksum.chars().rev().collect::<String>());
+        assert!(single_descriptors(&damaged).is_err());
+
+        assert!(xpub_descriptor("tpubD6NzVbkrYhZ4XgiXtGrdW5XDAPFCL9h7we1vwNCpn8tGbBcgfVYjXyhWo4E1xkh56hjod1RhGjxbaTLV3X4FyWuejifB9jusQ46QzG87VKp").is_err());
+    }
+
+    #[test]
+    fn derive_multisig_and_taproot_scripts() {
+        // BIP-32 test vector 1 master key
+        let xpub = "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8";
+        let multisig = single_descriptors(&format!("wsh(multi(2,{xpub}/<0;1>/*,{xpub}/<2;3>/*))")).unwrap();
+        assert_eq!(multisig.len(), 2);
+        assert!(descriptor_script(&multisig[0], 0).unwrap().is_p2wsh());
+
+        // 2-of-3 multisig spent in transaction 5df912fda4becb1c29e928bec8d64d93e9ba8efa9b5b405bd683c86fd2c65667
+        let multisig = single_descriptors("wsh(multi(2,\
+            0375e00eb72e29da82b89367947f29ef34afb75e8654f6ea368e0acdfd92976b7c,\
+            03a1b26313f430c4b15bb1fdce663207659d8cac749a0e53d70eff01874496feff,\
+            03c96d495bfdd5ba4145e3e046fee45e84a8a48ad05bd8dbb395c011a32cf9f880))").unwrap();
+        assert_eq!(address(&multisig[0], 0), "bc1qwqdg6squsna38e46795at95yu9atm8azzmyvckulcc7kytlcckxswvvzej");
+
+        // BIP-86 test vectors, derived from "abandon abandon ... about"
+        let xpub = "xpub6BgBgsespWvERF3LHQu6CnqdvfEvtMcQjYrcRzx53QJjSxarj2afYWcLteoGVky7D3UKDP9QyrLprQ3VCECoY49yfdDEHGCtMMj92pReUsQ";
+        let taproot = single_descriptors(&format!("tr({xpub}/<0;1>/*)")).unwrap();
+        assert_eq!(taproot.len(), 2);
+        assert_eq!(address(&taproot[0], 0), "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr");
+        assert_eq!(address(&taproot[0], 1), "bc1p4qhjn9zdvkux4e44uhx8tc55attvtyu358kutcqkudyccelu0was9fqzwh");
+        assert_eq!(address(&taproot[1], 0), "bc1p3qkhfews2uk44qtvauqyr2ttdsw7svhkl9nkm9s9c3x4ax5h60wqwruhk7");
+    }
+
+    #[tokio::test]
+    async fn sync_using_configured_backend() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("GET", mockito::Matcher::Regex(r"^/scripthash/[0-9a-f]{64}/txs$".to_owned()))
+            .match_header("authorization", "Bearer secret")
+            .with_body("[]")
+            .create_async()
+            .await;
+
+        let settings = BackendSettings {
+            url: Some(server.url()),
+            api_key: Some("secret".to_owned()),
+            ..Default::default()
+        };
+        let backend = BitcoinBackend::Esplora(Esplora::new(&settings).unwrap());
+        let addresses = vec!["bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu".to_owned()];
+        let transactions = address_transactions(&backend, &mut HistoryCache::default(), &addresses).await.unwrap();
+        assert!(transactions.is_empty());
+        mock.assert_async().await;
+    }
+
+    fn script_path(script: &Script) -> String {
+        format!("/scripthash/{}", bitcoin::hashes::sha256::Hash::hash(script.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn resync_only_fetches_scripts_with_new_activity() {
+        let receive = xpub_descriptor(ZPUB).unwrap().into_single_descriptors().unwrap().remove(0);
+        let used = descriptor_script(&receive, 1).unwrap();
+        let tx = serde_json::json!([{
+            "txid": "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            "version": 2,
+            "locktime": 0,
+            "vin": [{
+                "txid": "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+                "vout": 0,
+                "prevout": { "scriptpubkey": "0014ffffffffffffffffffffffffffffffffffffffff", "value": 60_000 },
+                "scriptsig": "",
+                "witness": [],
+                "sequence": 4294967295u32,
+                "is_coinbase": false,
+            }],
+            "vout": [{ "scriptpubkey": used.to_hex_string(), "value": 50_000 }],
+            "size": 110,
+            "weight": 440,
+            "status": {
+                "confirmed": true,
+                "block_height": 800_000,
+                "block_hash": "00000000000000000002a7c4c1e48d76c5a37902165a270156b7a8d72728a054",
+                "block_time": 1_690_168_629,
+            },
+            "fee": 10_000,
+        }]);
+
+        let mut server = mockito::Server::new_async().await;
+        let used_txs = server.mock("GET", format!("{}/txs", script_path(&used)).as_str())
+            .with_body(tx.to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        let used_stats = server.mock("GET", script_path(&used).as_str())
+            .with_body(r#"{"chain_stats":{"funded_txo_count":1,"funded_txo_sum":50000,"spent_txo_count":0,"spent_txo_sum":0,"tx_count":1},"mempool_stats":{"funded_txo_count":0,"funded_txo_sum":0,"spent_txo_count":0,"spent_txo_sum":0,"tx_count":0}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let unused_txs = server.mock("GET", mockito::Matcher::Regex(r"^/scripthash/[0-9a-f]{64}/txs$".to_owned()))
+            .with_body("[]")
+            .expect(6)
+            .create_async()
+            .await;
+
+        let settings = BackendSettings { url: Some(server.url()), ..Default::default() };
+        let backend = BitcoinBackend::Esplora(Esplora::new(&settings).unwrap());
+        let cache_dir = std::env::temp_dir().join(format!("raccoin_bitcoin_cache_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&cache_dir);
+
+        // with a gap limit of 2, the scripts 0 to 3 are scanned
+        let mut cache = HistoryCache::load(Some(&cache_dir));
+        let transactions = descriptors_transactions(&backend, &mut cache, vec![receive.clone()], 2).await.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].operation, crate::base::Operation::Receive(Amount::from_satoshis(50_000)));
+        cache.save().unwrap();
+
+        // the second time, the cached history is used for the unchanged script
+        let mut cache = HistoryCache::load(Some(&cache_dir));
+        let resynced = descriptors_transactions(&backend, &mut cache, vec![receive], 2).await.unwrap();
+        assert_eq!(resynced.len(), 1);
+        assert_eq!(resynced[0].operation, transactions[0].operation);
+        assert_eq!(resynced[0].timestamp, transactions[0].timestamp);
+
+        used_txs.assert_async().await;
+        used_stats.assert_async().await;
+        unused_txs.assert_async().await;
+    }
+}
@@ -1,79 +1,133 @@
-use alloy_chains::Chain;
+use std::collections::HashMap;
+
+use alloy_chains::{Chain, NamedChain};
 use alloy_primitives::{ruint::UintTryTo, Address, B256, U256};
 use anyhow::{anyhow, Context, Result};
-use chrono::{DateTime, NaiveDateTime};
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use foundry_block_explorers::{account::*, Client};
+use reqwest::Url;
 use rust_decimal::{prelude::FromPrimitive, Decimal};
-use std::time::{Duration, Instant};
-use tokio::time::sleep;
 
-use crate::{base::{Amount, Operation, Transaction}, LoadFuture, TransactionSource};
+use crate::{backend::{BackendSettings, RateLimiter}, base::{Amount, AssetId, Operation, Transaction}, LoadFuture, SyncOptions, TransactionSource};
 use linkme::distributed_slice;
 
-fn u256_to_decimal(value: U256) -> Result<Decimal> {
-    Decimal::from_u128(value.uint_try_to()?).context("value cannot be represented")
+/// The Etherscan API, which serves all chains, selected by the `chainid`
+/// parameter.
+pub(crate) const DEFAULT_ETHERSCAN_URL: &str = "https://api.etherscan.io/v2/api";
+
+// shared key, used when none is configured for the portfolio
+const DEFAULT_API_KEY: &str = "YU7CJTKTFHYUKSK9KUGCAJ448QW1U26NUN";
+
+/// An EVM chain that can be synced using the Etherscan API.
+pub(crate) struct EvmChain {
+    /// The name shown in the chain selection.
+    name: &'static str,
+    chain: NamedChain,
+    /// Identifies the chain in the `blockchain` of transactions, which
+    /// determines the explorer used to open them.
+    blockchain: &'static str,
+    native_currency: &'static str,
+    /// Identifies the chain in the asset IDs of its tokens.
+    asset_chain: &'static str,
 }
 
-fn u256_to_eth(value: U256) -> Result<Decimal> {
-    let mut value = u256_to_decimal(value)?;
-    value.set_scale(18)?; // convert Wei to ETH
-    Ok(value)
+pub(crate) const EVM_CHAINS: &[EvmChain] = &[
+    EvmChain { name: "Ethereum", chain: NamedChain::Mainnet, blockchain: "ETH", native_currency: "ETH", asset_chain: "ethereum" },
+    EvmChain { name: "Arbitrum", chain: NamedChain::Arbitrum, blockchain: "ARB", native_currency: "ETH", asset_chain: "arbitrum" },
+    EvmChain { name: "Optimism", chain: NamedChain::Optimism, blockchain: "OP", native_currency: "ETH", asset_chain: "optimism" },
+    EvmChain { name: "Base", chain: NamedChain::Base, blockchain: "BASE", native_currency: "ETH", asset_chain: "base" },
+    EvmChain { name: "Polygon", chain: NamedChain::Polygon, blockchain: "POL", native_currency: "POL", asset_chain: "polygon" },
+    EvmChain { name: "BSC", chain: NamedChain::BinanceSmartChain, blockchain: "BSC", native_currency: "BNB", asset_chain: "bsc" },
+];
+
+/// Returns the names of the [`EVM_CHAINS`], for the chain selection of the
+/// source.
+const fn evm_chain_names() -> [&'static str; EVM_CHAINS.len()] {
+    let mut names = [""; EVM_CHAINS.len()];
+    let mut index = 0;
+    while index < EVM_CHAINS.len() {
+        names[index] = EVM_CHAINS[index].name;
+        index += 1;
+    }
+    names
 }
 
-struct RateLimiter {
-    min_interval: Duration,
-    last_call: Option<Instant>,
-}
+impl EvmChain {
+    fn by_name(name: &str) -> Result<&'static EvmChain> {
+        EVM_CHAINS.iter().find(|chain| chain.name == name).with_context(|| format!("unsupported chain {}", name))
+    }
 
-impl RateLimiter {
-    fn new(min_interval: Duration) -> Self {
-        Self { min_interval, last_call: None }
+    fn native_currency(&self, timestamp: NaiveDateTime) -> &'static str {
+        // MATIC was replaced by POL as the native currency of Polygon
+        let pol_migration = NaiveDate::from_ymd_opt(2024, 9, 4).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        if self.chain == NamedChain::Polygon && timestamp < pol_migration {
+            "MATIC"
+        } else {
+            self.native_currency
+        }
     }
 
-    async fn wait(&mut self) {
-        if let Some(last) = self.last_call {
-            let elapsed = last.elapsed();
-            if elapsed < self.min_interval {
-                sleep(self.min_interval - elapsed).await;
-            }
+    /// Returns the API URL for this chain, unless the configured URL already
+    /// selects a chain.
+    fn api_url(&self, settings: &BackendSettings) -> Result<Url> {
+        let mut url = Url::parse(settings.url_or(DEFAULT_ETHERSCAN_URL))?;
+        if !url.query_pairs().any(|(key, _)| key == "chainid") {
+            url.query_pairs_mut().append_pair("chainid", &(self.chain as u64).to_string());
         }
-        self.last_call = Some(Instant::now());
+        Ok(url)
     }
 }
 
+fn u256_to_decimal(value: U256) -> Result<Decimal> {
+    Decimal::from_u128(value.uint_try_to()?).context("value cannot be represented")
+}
+
+fn u256_to_eth(value: U256) -> Result<Decimal> {
+    let mut value = u256_to_decimal(value)?;
+    value.set_scale(18)?; // convert Wei to ETH
+    Ok(value)
+}
+
 // Generic interface to the many different transaction types in the Etherscan API
 trait EthereumTransaction {
     fn timestamp(&self) -> Result<NaiveDateTime> {
         let timestamp: i64 = self.timestamp_str().parse()?;
         DateTime::from_timestamp(timestamp, 0).map(|dt| dt.naive_utc()).context("invalid timestamp")
     }
-    fn value(&self) -> Result<Amount>;
+    fn value(&self, chain: &EvmChain) -> Result<Amount>;
     fn hash(&self) -> Option<String> {
         self.hash_b256().map(|hash| serde_json::to_string(hash).unwrap().trim_matches('"').to_owned())
     }
 
-    fn fee(&self) -> Result<Option<Amount>> {
+    fn native_value(&self, chain: &EvmChain, value: U256) -> Result<Amount> {
+        Ok(Amount::new(u256_to_eth(value)?, chain.native_currency(self.timestamp()?).to_owned()))
+    }
+
+    fn fee(&self, chain: &EvmChain) -> Result<Option<Amount>> {
         Ok(match self.gas_price() {
             Some(gas_price) => {
-                let fee = u256_to_eth(gas_price * self.gas_used())?;
+                let fee = self.native_value(chain, gas_price * self.gas_used())?;
                 if fee.is_zero() {
                     None
                 } else {
-                    Some(Amount::new(fee, "ETH".to_owned()))
+                    Some(fee)
                 }
             }
             None => None,
         })
     }
 
-    fn to_transaction(&self, own_address: &Address) -> Result<Transaction> {
+    fn to_transaction(&self, chain: &EvmChain, own_address: &Address) -> Result<Transaction> {
         let timestamp = self.timestamp()?;
         let mut fee: Option<Amount> = None;
+        let mut counterparty = None;
         let operation = if self.to().is_some_and(|from_address| from_address == own_address) {
-            Ok(Operation::Receive(self.value()?))
+            counterparty = self.from();
+            Ok(Operation::Receive(self.value(chain)?))
         } else if self.from().is_some_and(|from_address| from_address == own_address) {
-            fee = self.fee()?;
-            let value = self.value()?;
+            counterparty = self.to();
+            fee = self.fee(chain)?;
+            let value = self.value(chain)?;
             if value.is_zero() && fee.is_some() {
                 Ok(Operation::Fee(fee.take().unwrap()))
             } else {
@@ -85,7 +139,8 @@ trait EthereumTransaction {
 
         let mut tx = Transaction::new(timestamp, operation);
         tx.tx_hash = self.hash();
-        tx.blockchain = Some("ETH".to_owned());
+        tx.blockchain = Some(chain.blockchain.to_owned());
+        tx.counterparty = counterparty.map(|address| address.to_string());
         tx.fee = fee;
         Ok(tx)
     }
@@ -99,8 +154,8 @@ trait EthereumTransaction {
 }
 
 impl EthereumTransaction for NormalTransaction {
-    fn value(&self) -> Result<Amount> {
-        u256_to_eth(self.value).map(|v| Amount::new(v, "ETH".to_owned()))
+    fn value(&self, chain: &EvmChain) -> Result<Amount> {
+        self.native_value(chain, self.value)
     }
 
     fn timestamp_str(&self) -> &str { &self.time_stamp }
@@ -112,8 +167,8 @@ impl EthereumTransaction for NormalTransaction {
 }
 
 impl EthereumTransaction for InternalTransaction {
-    fn value(&self) -> Result<Amount> {
-        u256_to_eth(self.value).map(|v| Amount::new(v, "ETH".to_owned()))
+    fn value(&self, chain: &EvmChain) -> Result<Amount> {
+        self.native_value(chain, self.value)
     }
 
     fn timestamp_str(&self) -> &str { &self.time_stamp }
@@ -125,11 +180,12 @@ impl EthereumTransaction for InternalTransaction {
 }
 
 impl EthereumTransaction for ERC20TokenTransferEvent {
-    fn value(&self) -> Result<Amount> {
+    fn value(&self, chain: &EvmChain) -> Result<Amount> {
         let scale: u32 = self.token_decimal.parse()?;
         let mut value = u256_to_decimal(self.value)?;
         value.set_scale(scale)?;
-        Ok(Amount::new(value, format!("{} ({})", self.token_symbol, self.token_name)))
+        let asset = AssetId::new(chain.asset_chain, &self.contract_address.to_string().to_lowercase());
+        Ok(Amount::new_asset(value, format!("{} ({})", self.token_symbol, self.token_name), asset))
     }
 
     fn timestamp_str(&self) -> &str { &self.time_stamp }
@@ -141,7 +197,7 @@ impl EthereumTransaction for ERC20TokenTransferEvent {
 }
 
 impl EthereumTransaction for ERC721TokenTransferEvent {
-    fn value(&self) -> Result<Amount> {
+    fn value(&self, _chain: &EvmChain) -> Result<Amount> {
         Ok(Amount::new_token(self.token_id.clone(), format!("{} ({})", self.token_symbol, self.token_name)))
     }
 
@@ -154,7 +210,7 @@ impl EthereumTransaction for ERC721TokenTransferEvent {
 }
 
 impl EthereumTransaction for ERC1155TokenTransferEvent {
-    fn value(&self) -> Result<Amount> {
+    fn value(&self, _chain: &EvmChain) -> Result<Amount> {
         Ok(Amount::new_token(self.token_id.clone(), format!("{} ({})", self.token_symbol, self.token_name)))
     }
 
@@ -166,76 +222,167 @@ impl EthereumTransaction for ERC1155TokenTransferEvent {
     fn gas_used(&self) -> U256 { self.gas_used }
 }
 
+// Contracts through which swaps are made, with the protocol they belong to
+const SWAP_CONTRACTS: &[(&str, &str)] = &[
+    ("0x7a250d5630b4cf539739df2c5dacb4c659f2488d", "Uniswap V2"),   // Router02
+    ("0xe592427a0aece92de3edee1f18e0157c05861564", "Uniswap V3"),   // SwapRouter
+    ("0x68b3465833fb72a70ecdf485e0e4c7bd8665fc45", "Uniswap V3"),   // SwapRouter02
+    ("0xef1c6e67703c7bd7107eed8303fbe6ec2554bf6b", "Uniswap"),      // Universal Router
+    ("0x3fc91a3afd70395cd496c647d5a6cc9d4b2b7fad", "Uniswap"),      // Universal Router
+    ("0x66a9893cc07d91d95644aedd05d03f95e1dba8af", "Uniswap"),      // Universal Router (V4)
+    ("0x1111111254fb6c44bac0bed2854e76f90643097d", "1inch"),        // Aggregation Router V4
+    ("0x1111111254eeb25477b68fb85ed929f73a960582", "1inch"),        // Aggregation Router V5
+    ("0x111111125421ca6dc452d289314280a0f8842a65", "1inch"),        // Aggregation Router V6
+    ("0x9008d19f58aabd9ed0d60971565aa8510560ab41", "CoW Swap"),     // GPv2Settlement
+];
+
+fn swap_protocol(address: &str) -> Option<&'static str> {
+    SWAP_CONTRACTS.iter()
+        .find(|(contract, _)| contract.eq_ignore_ascii_case(address))
+        .map(|(_, protocol)| *protocol)
+}
+
+/// Combines the transfers that are part of the same transaction, so that a
+/// swap becomes a single trade of the net amounts.
+fn combine_transfers(transfers: Vec<Transaction>) -> Vec<Transaction> {
+    let mut groups: Vec<Vec<Transaction>> = Vec::new();
+    let mut group_by_hash: HashMap<String, usize> = HashMap::new();
+
+    for transfer in transfers {
+        match transfer.tx_hash.as_ref().and_then(|hash| group_by_hash.get(hash)) {
+            Some(&index) => groups[index].push(transfer),
+            None => {
+                if let Some(hash) = &transfer.tx_hash {
+                    group_by_hash.insert(hash.clone(), groups.len());
+                }
+                groups.push(vec![transfer]);
+            }
+        }
+    }
+
+    groups.into_iter().flat_map(combine_transaction_transfers).collect()
+}
+
+fn combine_transaction_transfers(mut transfers: Vec<Transaction>) -> Vec<Transaction> {
+    if transfers.len() == 1 {
+        return transfers;
+    }
+
+    // Sum up the changes by currency, which treats wrapped ether as ether and
+    // drops any tokens only passing through our address
+    let mut fee = None;
+    let mut changes: Vec<Amount> = Vec::new();
+    for transfer in &transfers {
+        let (amount, quantity) = match &transfer.operation {
+            Operation::Receive(amount) => (amount, amount.quantity),
+            Operation::Send(amount) => (amount, -amount.quantity),
+            Operation::Fee(amount) => {
+                fee = Some(amount.clone());
+                continue;
+            }
+            _ => continue,
+        };
+        if transfer.fee.is_some() {
+            fee = transfer.fee.clone();
+        }
+        match changes.iter_mut().find(|change| change.effective_currency() == amount.effective_currency()) {
+            Some(change) => change.quantity += quantity,
+            None => changes.push(Amount { quantity, ..amount.clone() }),
+        }
+    }
+    changes.retain(|change| !change.is_zero());
+
+    let (mut incoming, outgoing): (Vec<Amount>, Vec<Amount>) = changes.into_iter().partition(|change| change.quantity > Decimal::ZERO);
+    let mut outgoing: Vec<Amount> = outgoing.into_iter().map(|amount| Amount { quantity: -amount.quantity, ..amount }).collect();
+
+    let protocol = transfers.iter().find_map(|transfer| transfer.counterparty.as_deref().and_then(swap_protocol));
+    let operations: Vec<Operation> = if incoming.len() == 1 && outgoing.len() == 1 {
+        vec![Operation::Trade { incoming: incoming.remove(0), outgoing: outgoing.remove(0) }]
+    } else {
+        incoming.into_iter().map(Operation::Receive).chain(outgoing.into_iter().map(Operation::Send)).collect()
+    };
+
+    // The first transfer is the transaction sent from our address, if any
+    let template = transfers.swap_remove(0);
+    let mut transactions: Vec<Transaction> = operations.into_iter().map(|operation| {
+        let mut tx = Transaction::new(template.timestamp, operation);
+        tx.tx_hash = template.tx_hash.clone();
+        tx.blockchain = template.blockchain.clone();
+        tx.counterparty = template.counterparty.clone();
+        if let (Operation::Trade { .. }, Some(protocol)) = (&tx.operation, protocol) {
+            tx.description = Some(format!("{} swap", protocol));
+        }
+        tx
+    }).collect();
+
+    // Attach the fee to the first operation, or keep it as a separate fee
+    match transactions.first_mut() {
+        Some(tx) => tx.fee = fee,
+        None => if let Some(fee) = fee {
+            let mut tx = template;
+            tx.operation = Operation::Fee(fee);
+            tx.fee = None;
+            transactions.push(tx);
+        }
+    }
+
+    transactions
+}
+
+/// Loads the transactions of an address on each of the given chains.
 pub(crate) async fn address_transactions(
+    settings: &BackendSettings,
+    chains: &[&EvmChain],
     address: &str,
 ) -> Result<Vec<Transaction>> {
-    let client = Client::new(Chain::mainnet(), "YU7CJTKTFHYUKSK9KUGCAJ448QW1U26NUN")?;
+    // the API key is shared by all chains, and so is its rate limit
+    let rate_limiter = settings.rate_limiter(350);
+    let mut transactions = Vec::new();
+
+    for chain in chains {
+        println!("loading {} transactions", chain.name);
+        transactions.extend(chain_transactions(settings, &rate_limiter, chain, address).await?);
+    }
+
+    Ok(transactions)
+}
+
+async fn chain_transactions(
+    settings: &BackendSettings,
+    rate_limiter: &RateLimiter,
+    chain: &EvmChain,
+    address: &str,
+) -> Result<Vec<Transaction>> {
+    let client = Client::builder()
+        .with_client(settings.http_client()?)
+        .chain(Chain::from_named(chain.chain))?
+        .with_api_url(chain.api_url(settings)?)?
+        .with_api_key(settings.api_key.as_deref().unwrap_or(DEFAULT_API_KEY))
+        .build()?;
     let address = address.parse()?;
-    let mut rate_limiter = RateLimiter::new(Duration::from_millis(350));
 
     println!("requesting normal transactions for address: {:?}...", address);
     rate_limiter.wait().await;
     let normal_transactions = client.get_transactions(&address, None).await?;
     println!("received {} normal transactions", normal_transactions.len());
 
-    let mut transactions = Vec::new();
+    let mut transfers = Vec::new();
 
     for normal_transaction in normal_transactions {
-        match normal_transaction.to_transaction(&address) {
-            Ok(tx) => transactions.push(tx),
+        match normal_transaction.to_transaction(chain, &address) {
+            Ok(tx) => transfers.push(tx),
             Err(err) => println!("{:?}: {:?}", err, normal_transaction),
         }
     }
 
-    let mut merge_or_add_to_transactions = |transaction: Transaction| {
-        let mut merged = false;
-
-        // Try to find a transaction to merge with
-        if let Some(matching_tx) = transactions.iter_mut().find(|tx| tx.tx_hash == transaction.tx_hash) {
-            match (&matching_tx.operation, &transaction.operation) {
-                // Send + Receive => Trade (if different currencies)
-                (Operation::Send(send_amount), Operation::Receive(receive_amount)) |
-                (Operation::Receive(receive_amount), Operation::Send(send_amount)) => {
-                    if send_amount.currency == receive_amount.currency && send_amount.token_id.is_none() && receive_amount.token_id.is_none() {
-                        // Create a Send or a Receive, depending on the net change
-                        let change = receive_amount.quantity - send_amount.quantity;
-                        if change > Decimal::ZERO {
-                            matching_tx.operation = Operation::Receive(Amount::new(change, send_amount.currency.clone()));
-                        } else {
-                            matching_tx.operation = Operation::Send(Amount::new(-change, receive_amount.currency.clone()));
-                        }
-                    } else {
-                        matching_tx.operation = Operation::Trade { incoming: receive_amount.clone(), outgoing: send_amount.clone() };
-                    }
-                    merged = true;
-                }
-                // Transfer an existing Fee to the fee field for the operation
-                (Operation::Fee(fee_amount), op) => {
-                    assert!(matching_tx.fee.is_none());
-                    matching_tx.fee = Some(fee_amount.clone());
-                    matching_tx.operation = op.clone();
-                    if matching_tx.description.is_none() {
-                        matching_tx.description = transaction.description.clone();
-                    }
-                    merged = true;
-                }
-                _ => {},
-            }
-        }
-
-        if !merged {
-            transactions.push(transaction);
-        }
-    };
-
     println!("requesting internal transactions for address: {:?}...", address);
     rate_limiter.wait().await;
     let internal_transactions = client.get_internal_transactions(InternalTxQueryOption::ByAddress(address), None).await?;
     println!("received {} internal transactions", internal_transactions.len());
 
     for internal_transaction in internal_transactions {
-        match internal_transaction.to_transaction(&address) {
-            Ok(transaction) => merge_or_add_to_transactions(transaction),
+        match internal_transaction.to_transaction(chain, &address) {
+            Ok(transaction) => transfers.push(transaction),
             Err(err) => println!("{:?}: {:?}", err, internal_transaction),
         }
     }
@@ -246,8 +393,8 @@ pub(crate) async fn address_transactions(
     println!("received {} erc-20 token transfers", erc20_transfers.len());
 
     for token_transfer in erc20_transfers {
-        match token_transfer.to_transaction(&address) {
-            Ok(transaction) => merge_or_add_to_transactions(transaction),
+        match token_transfer.to_transaction(chain, &address) {
+            Ok(transaction) => transfers.push(transaction),
             Err(err) => println!("{:?}: {:?}", err, token_transfer),
         }
     }
@@ -258,8 +405,8 @@ pub(crate) async fn address_transactions(
     println!("received {} erc-721 token transfers", erc721_transfers.len());
 
     for token_transfer in erc721_transfers {
-        match token_transfer.to_transaction(&address) {
-            Ok(transaction) => merge_or_add_to_transactions(transaction),
+        match token_transfer.to_transaction(chain, &address) {
+            Ok(transaction) => transfers.push(transaction),
             Err(err) => println!("{:?}: {:?}", err, token_transfer),
         }
     }
@@ -270,12 +417,14 @@ pub(crate) async fn address_transactions(
     println!("received {} erc-1155 token transfers", erc1155_transfers.len());
 
     for token_transfer in erc1155_transfers {
-        match token_transfer.to_transaction(&address) {
-            Ok(transaction) => merge_or_add_to_transactions(transaction),
+        match token_transfer.to_transaction(chain, &address) {
+            Ok(transaction) => transfers.push(transaction),
             Err(err) => println!("{:?}: {:?}", err, token_transfer),
         }
     }
 
+    let mut transactions = combine_transfers(transfers);
+
     // Turn Trade into Swap for known cases
     const SWAP_PAIRS: &[(&str, &str)] = &[
         ("1INCH (1INCH Token)", "st1INCH (1INCH Token (Staked))"),
@@ -296,16 +445,214 @@ pub(crate) async fn address_transactions(
     Ok(transactions)
 }
 
-pub(crate) fn load_ethereum_address_async(source_path: String) -> LoadFuture {
-    Box::pin(async move { address_transactions(&source_path).await })
+pub(crate) fn load_ethereum_address_async(source_path: String, options: SyncOptions) -> LoadFuture {
+    Box::pin(async move { address_transactions(&options.backends.etherscan, &[&EVM_CHAINS[0]], &source_path).await })
+}
+
+pub(crate) fn load_evm_address_async(source_path: String, options: SyncOptions) -> LoadFuture {
+    Box::pin(async move {
+        let chains = options.chains.iter()
+            .map(|name| EvmChain::by_name(name))
+            .collect::<Result<Vec<_>>>()?;
+        address_transactions(&options.backends.etherscan, &chains, source_path.trim()).await
+    })
 }
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
 static ETHEREUM_ADDRESS: TransactionSource = TransactionSource {
     id: "EthereumAddress",
     label: "Ethereum Address",
-    csv: &[],
-    detect: None,
-    load_sync: None,
     load_async: Some(load_ethereum_address_async),
+    ..TransactionSource::DEFAULT
 };
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static EVM_ADDRESS: TransactionSource = TransactionSource {
+    id: "EvmAddress",
+    label: "EVM Address",
+    load_async: Some(load_evm_address_async),
+    chains: &evm_chain_names(),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sync_using_configured_backend() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("GET", "/api")
+            .match_query(mockito::Matcher::UrlEncoded("apikey".to_owned(), "secret".to_owned()))
+            .with_body(r#"{"status":"1","message":"OK","result":[]}"#)
+            .expect(5)
+            .create_async()
+            .await;
+
+        let settings = BackendSettings {
+            url: Some(format!("{}/api", server.url())),
+            api_key: Some("secret".to_owned()),
+            min_interval_ms: Some(0),
+            ..Default::default()
+        };
+        let transactions = address_transactions(&settings, &[&EVM_CHAINS[0]], "0x2c1ba59d6f58433fb1eaee7d20b26ed83bda51a3").await.unwrap();
+        assert!(transactions.is_empty());
+        mock.assert_async().await;
+    }
+
+    fn normal_transaction(timestamp: &str, from: &str, to: &str, value: &str) -> String {
+        serde_json::json!({
+            "status": "1",
+            "message": "OK",
+            "result": [{
+                "blockNumber": "50000000",
+                "timeStamp": timestamp,
+                "hash": "0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060",
+                "nonce": "1",
+                "blockHash": "0x3d6122660cc824376f11ee842f83addc3525e2dd6756b9bcf0affa6aa88cf741",
+                "transactionIndex": "1",
+                "from": from,
+                "to": to,
+                "value": value,
+                "gas": "21000",
+                "gasPrice": "100000000000",
+                "isError": "0",
+                "txreceipt_status": "1",
+                "input": "0x",
+                "contractAddress": "",
+                "cumulativeGasUsed": "21000",
+                "gasUsed": "21000",
+                "confirmations": "100",
+                "methodId": "0x",
+                "functionName": "",
+            }],
+        }).to_string()
+    }
+
+    #[tokio::test]
+    async fn sync_address_on_several_chains() {
+        const OWN: &str = "0x2c1ba59d6f58433fb1eaee7d20b26ed83bda51a3";
+        const OTHER: &str = "0x000000000000000000000000000000000000dead";
+
+        let mut server = mockito::Server::new_async().await;
+        let txlist = |chain_id: &str| mockito::Matcher::AllOf(vec![
+            mockito::Matcher::UrlEncoded("chainid".to_owned(), chain_id.to_owned()),
+            mockito::Matcher::UrlEncoded("action".to_owned(), "txlist".to_owned()),
+        ]);
+        let polygon = server.mock("GET", "/api")
+            .match_query(txlist("137"))
+            .with_body(normal_transaction("1700000000", OTHER, OWN, "2000000000000000000"))
+            .create_async()
+            .await;
+        let bsc = server.mock("GET", "/api")
+            .match_query(txlist("56"))
+            .with_body(normal_transaction("1730000000", OWN, OTHER, "0"))
+            .create_async()
+            .await;
+        let others = server.mock("GET", "/api")
+            .match_query(mockito::Matcher::Any)
+            .with_body(r#"{"status":"1","message":"OK","result":[]}"#)
+            .expect(8)
+            .create_async()
+            .await;
+
+        let settings = BackendSettings {
+            url: Some(format!("{}/api", server.url())),
+            min_interval_ms: Some(0),
+            ..Default::default()
+        };
+        let chains = [EvmChain::by_name("Polygon").unwrap(), EvmChain::by_name("BSC").unwrap()];
+        let transactions = address_transactions(&settings, &chains, OWN).await.unwrap();
+        polygon.assert_async().await;
+        bsc.assert_async().await;
+        others.assert_async().await;
+
+        assert_eq!(transactions.len(), 2);
+
+        // before the migration to POL, the native currency of Polygon was MATIC
+        assert_eq!(transactions[0].operation, Operation::Receive(Amount::new(Decimal::from(2), "MATIC".to_owned())));
+        assert_eq!(transactions[0].blockchain.as_deref(), Some("POL"));
+
+        assert_eq!(transactions[1].operation, Operation::Fee(Amount::new(Decimal::new(21, 4), "BNB".to_owned())));
+        assert_eq!(transactions[1].blockchain.as_deref(), Some("BSC"));
+    }
+
+    const USDC: &str = "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48";
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const DAI: &str = "0x6b175474e89094c44da98b954eedeac495271d0f";
+
+    fn transfer(operation: Operation, counterparty: &str) -> Transaction {
+        let mut tx = Transaction::new(DateTime::from_timestamp(1700000000, 0).unwrap().naive_utc(), operation);
+        tx.tx_hash = Some("0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060".to_owned());
+        tx.blockchain = Some("ETH".to_owned());
+        tx.counterparty = Some(counterparty.to_owned());
+        tx
+    }
+
+    fn token(quantity: Decimal, currency: &str, contract: &str) -> Amount {
+        Amount::new_asset(quantity, currency.to_owned(), AssetId::new("ethereum", contract))
+    }
+
+    fn eth(quantity: Decimal) -> Amount {
+        Amount::new(quantity, "ETH".to_owned())
+    }
+
+    #[test]
+    fn combine_multi_hop_swap() {
+        const ROUTER: &str = "0xE592427A0AEce92De3Edee1F18E0157C05861564";
+        const POOL: &str = "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640";
+        let fee = eth(Decimal::new(2, 3));
+
+        // USDC is sold for DAI, with WETH passing through our address
+        let transactions = combine_transfers(vec![
+            transfer(Operation::Fee(fee.clone()), ROUTER),
+            transfer(Operation::Send(token(Decimal::from(1000), "USDC (USD Coin)", USDC)), POOL),
+            transfer(Operation::Receive(token(Decimal::new(5, 1), "WETH (Wrapped Ether)", WETH)), POOL),
+            transfer(Operation::Send(token(Decimal::new(5, 1), "WETH (Wrapped Ether)", WETH)), POOL),
+            transfer(Operation::Receive(token(Decimal::from(999), "DAI (Dai Stablecoin)", DAI)), POOL),
+        ]);
+
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].operation, Operation::Trade {
+            incoming: token(Decimal::from(999), "DAI (Dai Stablecoin)", DAI),
+            outgoing: token(Decimal::from(1000), "USDC (USD Coin)", USDC),
+        });
+        assert_eq!(transactions[0].fee, Some(fee));
+        assert_eq!(transactions[0].description.as_deref(), Some("Uniswap V3 swap"));
+    }
+
+    #[test]
+    fn combine_swap_treating_weth_as_eth() {
+        const ROUTER: &str = "0x1111111254EEB25477B68fb85Ed929f73A960582";
+
+        // ETH is sent and swapped for USDC, with WETH refunded
+        let transactions = combine_transfers(vec![
+            transfer(Operation::Send(eth(Decimal::ONE)), ROUTER),
+            transfer(Operation::Receive(token(Decimal::new(1, 1), "WETH (Wrapped Ether)", WETH)), ROUTER),
+            transfer(Operation::Receive(token(Decimal::from(1800), "USDC (USD Coin)", USDC)), ROUTER),
+        ]);
+
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].operation, Operation::Trade {
+            incoming: token(Decimal::from(1800), "USDC (USD Coin)", USDC),
+            outgoing: eth(Decimal::new(9, 1)),
+        });
+        assert_eq!(transactions[0].description.as_deref(), Some("1inch swap"));
+    }
+
+    #[test]
+    fn combine_wrapping_into_fee() {
+        let fee = eth(Decimal::new(1, 3));
+        let mut wrap = transfer(Operation::Send(eth(Decimal::ONE)), WETH);
+        wrap.fee = Some(fee.clone());
+
+        let transactions = combine_transfers(vec![
+            wrap,
+            transfer(Operation::Receive(token(Decimal::ONE, "WETH (Wrapped Ether)", WETH)), WETH),
+        ]);
+
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].operation, Operation::Fee(fee));
+        assert_eq!(transactions[0].fee, None);
+    }
+}
@@ -442,6 +442,7 @@ impl FIFO {
                     quantity: lot.quantity,
                     currency: outgoing.currency.clone(),
                     token_id: outgoing.token_id.clone(),
+                    asset: outgoing.asset.clone(),
                 },
                 cost,
                 proceeds: lot.quantity * sold_unit_price,
@@ -450,7 +451,7 @@ impl FIFO {
 
         if missing_quantity > Decimal::ZERO {
             println!("warning: at {} a remaining sold amount of {} {} was not found in the holdings", transaction.timestamp, missing_quantity, outgoing.currency);
-            return Err(GainError::InsufficientBalance(Amount::new(missing_quantity, outgoing.currency.clone())));
+            return Err(GainError::InsufficientBalance(Amount { quantity: missing_quantity, ..outgoing.clone() }));
         }
 
         cost_base_error.map(|_| capital_gains)
@@ -522,7 +523,7 @@ impl FIFO {
                 // Transfer the original acquisition costs and timestamps to the
                 // newly acquired currency
                 for gain in gains {
-                    let amount = Amount::new(ratio * gain.amount.quantity, incoming.currency.clone());
+                    let amount = Amount { quantity: ratio * gain.amount.quantity, ..incoming.clone() };
                     self.add_holdings_with_timestamp(transaction, &amount, Some(&Amount::from_fiat(gain.cost)), gain.bought);
                 }
                 Ok(Decimal::ZERO)
@@ -615,6 +616,7 @@ pub(crate) fn save_gains_to_csv(gains: &Vec<CapitalGain>, output_path: &Path) ->
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::base::AssetId;
     use crate::time::parse_date_time;
     use chrono::NaiveDateTime;
     use rust_decimal::Decimal;
@@ -743,6 +745,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fifo_tokens_with_same_symbol() {
+        // Two unrelated tokens both calling themselves USDT, and the real one
+        let usdt = |quantity: i64, contract: &str| {
+            Amount::new_asset(Decimal::new(quantity, 0), "USDT".to_owned(), AssetId::new("ethereum", contract))
+        };
+        let mut txs = vec![
+            Transaction::new(dt("2021-01-01 00:00:00"), Operation::Buy(usdt(10, "0xdac17f958d2ee523a2206206994597c13d831ec7"))),
+            Transaction::new(dt("2021-01-02 00:00:00"), Operation::Spam(usdt(1000, "0x0000000000000000000000000000000000000001"))),
+            Transaction::new(dt("2021-01-03 00:00:00"), Operation::Sell(usdt(100, "0x0000000000000000000000000000000000000002"))),
+        ];
+        txs[0].value = Some(Amount::from_fiat(Decimal::new(10, 0)));
+        txs[2].value = Some(Amount::from_fiat(Decimal::new(100, 0)));
+
+        let mut fifo = FIFO::with_tracking(CostBasisTracking::Universal);
+        process_txs(&mut fifo, &mut txs);
+
+        // The sale isn't covered by the holdings of the other tokens
+        assert!(matches!(txs[2].gain, Some(Err(GainError::InsufficientBalance(_)))));
+        assert_eq!(fifo.holdings().currency_balance("USDT"), Decimal::new(10, 0));
+    }
+
     #[test]
     fn fifo_per_wallet_with_transfer() {
         // Scenario:
@@ -5,7 +5,7 @@ use chrono::{NaiveDateTime, FixedOffset, DateTime};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Deserializer};
 
-use crate::{base::{Transaction, Amount}, CsvSpec, TransactionSource};
+use crate::{base::{Transaction, Amount}, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 // function for reading NaiveDateTime in the format "2/25/2021, 2:24:46 PM"
@@ -136,7 +136,7 @@ impl TryFrom<FtxTrade> for Transaction {
     }
 }
 
-fn load_ftx_deposits_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_ftx_deposits_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -148,7 +148,7 @@ fn load_ftx_deposits_csv(input_path: &Path) -> Result<Vec<Transaction>> {
     Ok(transactions)
 }
 
-fn load_ftx_withdrawals_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_ftx_withdrawals_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -160,7 +160,7 @@ fn load_ftx_withdrawals_csv(input_path: &Path) -> Result<Vec<Transaction>> {
     Ok(transactions)
 }
 
-fn load_ftx_trades_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_ftx_trades_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -185,9 +185,8 @@ static FTX_DEPOSITS_CSV: TransactionSource = TransactionSource {
         "Additional info",
         "Transaction ID",
     ])],
-    detect: None,
     load_sync: Some(load_ftx_deposits_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -204,9 +203,8 @@ static FTX_WITHDRAWALS_CSV: TransactionSource = TransactionSource {
         "Transaction ID",
         "fee",
     ])],
-    detect: None,
     load_sync: Some(load_ftx_withdrawals_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -226,7 +224,6 @@ static FTX_TRADES_CSV: TransactionSource = TransactionSource {
         "Fee Currency",
         "TWAP",
     ])],
-    detect: None,
     load_sync: Some(load_ftx_trades_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
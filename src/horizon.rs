@@ -1,22 +1,85 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
 use anyhow::{anyhow, Context, Result};
 use chrono::NaiveDateTime;
 use futures::try_join;
 use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Serialize};
 use stellar_base::amount::Stroops;
 use stellar_base::PublicKey;
 use stellar_horizon::api;
-use stellar_horizon::client::{HorizonClient, HorizonHttpClient};
-use stellar_horizon::request::PageRequest;
-use stellar_horizon::resources::{Effect, Asset, operation};
+use stellar_horizon::page::Page;
+use stellar_horizon::request::{PageRequest, Request};
+use stellar_horizon::resources::{Effect, Asset, AssetAmount, operation::{self, OperationBase}};
 
-use crate::{base::{Transaction, Amount, Operation}, LoadFuture, TransactionSource};
+use crate::{backend::{BackendSettings, RateLimiter}, base::{Transaction, Amount, AssetId, Operation}, LoadFuture, SyncOptions, TransactionSource};
 use linkme::distributed_slice;
 
 const STELLAR_SCALE: u32 = 7;
-const PAGE_LIMIT: u64 = 20;
+const PAGE_LIMIT: u64 = 200;
+const CLAIMABLE_BALANCE_PREFIX: &str = "Claimable Balance ID ";
+pub(crate) const DEFAULT_HORIZON_URL: &str = "https://horizon.stellar.org";
+
+/// A Horizon client which spreads out its requests as configured.
+///
+/// The requests are sent using our own HTTP client rather than the one from
+/// `stellar_horizon`, since that one doesn't support a proxy.
+struct Horizon {
+    client: reqwest::Client,
+    host: reqwest::Url,
+    api_key: Option<String>,
+    rate_limiter: RateLimiter,
+}
+
+impl Horizon {
+    fn new(settings: &BackendSettings) -> Result<Self> {
+        Ok(Self {
+            client: settings.http_client()?,
+            host: reqwest::Url::parse(settings.url_or(DEFAULT_HORIZON_URL))?,
+            api_key: settings.api_key.clone(),
+            rate_limiter: settings.rate_limiter(0),
+        })
+    }
+
+    async fn request<R: Request>(&self, request: R) -> Result<R::Response> {
+        self.rate_limiter.wait().await;
+        let mut builder = self.client.get(request.uri(&self.host)?);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response = builder.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(anyhow!("Horizon request failed with status {}: {}", status, response.text().await?));
+        }
+        Ok(serde_json::from_slice(&response.bytes().await?)?)
+    }
+
+    /// Requests the records from all pages, until an empty page is returned.
+    async fn all_records<R, T>(&self, request: R, paging_token: fn(&T) -> &str) -> Result<Vec<T>>
+    where
+        R: PageRequest<Response = Page<T>> + Clone,
+        T: DeserializeOwned + Serialize + Clone,
+    {
+        let mut records = Vec::new();
+        let mut cursor = String::new();
+
+        loop {
+            let request = request.clone()
+                .with_limit(PAGE_LIMIT)
+                .with_cursor(&cursor);
+            let response = self.request(request).await?;
+            match response.records.last() {
+                Some(last) => cursor = paging_token(last).to_owned(),
+                None => break,
+            }
+            records.extend(response.records);
+        }
+
+        Ok(records)
+    }
+}
 
 impl From<&Stroops> for Amount {
     fn from(stroops: &Stroops) -> Self {
@@ -25,232 +88,326 @@ impl From<&Stroops> for Amount {
     }
 }
 
-fn normalize_asset(code: &str, issuer: &str) -> String {
-    match (code, issuer) {
-        ("AQUA", "GBNZILSTVQZ4R7IKQDGHYGY2QXL5QOFJYQMXPKWRRM5PAV7Y4M67AQUA") => "AQUA".to_owned(),
-        ("USDC", "GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN") => "USDC".to_owned(),
-        _ => format!("{}:{}", code, issuer),
-    }
+fn asset_amount(quantity: Decimal, code: &str, issuer: &str) -> Amount {
+    Amount::new_asset(quantity, code.to_owned(), AssetId::new("stellar", issuer))
 }
 
-fn asset_to_string(asset: &Asset) -> String {
+fn amount_of_asset(quantity: Decimal, asset: &Asset) -> Amount {
     match (&asset.asset_code, &asset.asset_issuer) {
-        (Some(code), Some(issuer)) => normalize_asset(code.as_str(), issuer.as_str()),
-        _ => "XLM".to_owned(),
+        (Some(code), Some(issuer)) => asset_amount(quantity, code, issuer),
+        _ => Amount::new(quantity, "XLM".to_owned()),
     }
 }
 
-async fn address_fees(client: &HorizonHttpClient, address: &str) -> Result<HashMap<String, (NaiveDateTime, Amount)>> {
-    println!("Loading transactions for {}...", address);
+// Parses assets given as "native" or "CODE:ISSUER"
+fn amount_of_asset_str(quantity: &str, asset: &str) -> Result<Amount> {
+    let quantity = Decimal::from_str(quantity)?;
+    if asset == "native" {
+        Ok(Amount::new(quantity, "XLM".to_owned()))
+    } else {
+        let mut split = asset.split(':');
+        match (split.next(), split.next()) {
+            (Some(code), Some(issuer)) => Ok(asset_amount(quantity, code, issuer)),
+            _ => Err(anyhow!("Invalid asset value, expected: 'NAME:ISSUER'")),
+        }
+    }
+}
 
-    let account = PublicKey::from_account_id(address)?;
-    let mut cursor = "".to_string();
+/// Returns the ID of the claimable balance created or claimed by a Stellar
+/// send or receive. Both sides of a balance share this ID, while their
+/// transaction hashes differ and the claim can happen much later.
+pub(crate) fn claimable_balance_id(tx: &Transaction) -> Option<&str> {
+    if tx.blockchain.as_deref() != Some("XLM") {
+        return None;
+    }
+    tx.description.as_deref()?.strip_prefix(CLAIMABLE_BALANCE_PREFIX)
+}
 
-    let mut transactions = HashMap::new();
+fn operation_transaction(base: &OperationBase, operation: Operation) -> Transaction {
+    let mut tx = Transaction::new(base.created_at.naive_utc(), operation);
+    tx.tx_hash = Some(base.transaction_hash.clone());
+    tx.blockchain = Some("XLM".to_owned());
+    tx
+}
 
-    loop {
-        let request = api::transactions::for_account(&account)
-            .with_limit(PAGE_LIMIT)
-            .with_cursor(&cursor);
-        let (_, response) = client.request(request).await?;
-        let records_len = response.records.len();
+fn payment_transaction(address: &str, base: &OperationBase, sender: String, destination: String, amount: Amount) -> Option<Transaction> {
+    if sender == destination {
+        return None;
+    }
 
-        println!("Processing {} transactions", records_len);
+    let (operation, counterparty) = if sender == address {
+        (Operation::Send(amount), destination)
+    } else {
+        // Crude spam recognition
+        let operation = if amount.currency == "XLM" && amount.asset.is_none() && amount.quantity > Decimal::ZERO && amount.quantity < (Decimal::ONE / Decimal::ONE_HUNDRED) {
+            Operation::Spam(amount)
+        } else {
+            Operation::Receive(amount)
+        };
+        (operation, sender)
+    };
+
+    let mut tx = operation_transaction(base, operation);
+    tx.counterparty = (!counterparty.is_empty()).then_some(counterparty);
+    Some(tx)
+}
 
-        for tx in response.records {
-            cursor = tx.paging_token;
+/// A path payment sent from our address is a trade of the source asset for
+/// the destination asset, followed by a payment of the destination asset
+/// unless it was sent to ourselves.
+fn path_payment_transactions(address: &str, base: &OperationBase, from: String, to: String, source_amount: Amount, amount: Amount) -> Vec<Transaction> {
+    let mut transactions = Vec::new();
 
-            if tx.fee_account != address {
-                continue;
-            }
+    if from == address && source_amount.effective_currency() != amount.effective_currency() {
+        let mut tx = operation_transaction(base, Operation::Trade { incoming: amount.clone(), outgoing: source_amount });
+        tx.description = Some("Path payment".to_owned());
+        transactions.push(tx);
+    }
 
-            let timestamp = tx.created_at.naive_utc();
-            let amount = Amount::new(Decimal::new(tx.fee_charged, STELLAR_SCALE), "XLM".to_owned());
-            let previous = transactions.insert(tx.hash, (timestamp, amount));
-            assert!(previous.is_none());
-        }
+    transactions.extend(payment_transaction(address, base, from, to, amount));
+    transactions
+}
+
+/// Returns the shares of a liquidity pool, named after its reserves.
+fn pool_shares(quantity: &str, pool_id: &str, reserves: &[Amount]) -> Result<Amount> {
+    let name = reserves.iter().map(|reserve| reserve.currency.as_str()).collect::<Vec<_>>().join("/");
+    Ok(Amount::new_asset(Decimal::from_str(quantity)?, format!("{} Pool Share", name), AssetId::new("stellar", pool_id)))
+}
+
+fn pool_reserves(reserves: &[AssetAmount]) -> Result<Vec<Amount>> {
+    reserves.iter().map(|reserve| {
+        let asset = reserve.asset.as_deref().context("Missing asset of liquidity pool reserve")?;
+        amount_of_asset_str(&reserve.amount, asset)
+    }).collect()
+}
+
+// Since pool deposits and withdrawals are made at the pool price, each of the
+// reserves is worth an equal part of the shares.
+fn split_shares(shares: &Amount, parts: usize) -> Amount {
+    Amount { quantity: shares.quantity / Decimal::from(parts), ..shares.clone() }
+}
+
+async fn address_fees(horizon: &Horizon, address: &str) -> Result<HashMap<String, (NaiveDateTime, Amount)>> {
+    println!("Loading transactions for {}...", address);
+
+    let account = PublicKey::from_account_id(address)?;
+    let records = horizon.all_records(api::transactions::for_account(&account), |tx| &tx.paging_token).await?;
+
+    println!("Processing {} transactions", records.len());
 
-        if records_len < PAGE_LIMIT as usize {
-            break;
+    let mut transactions = HashMap::new();
+
+    for tx in records {
+        if tx.fee_account != address {
+            continue;
         }
+
+        let timestamp = tx.created_at.naive_utc();
+        let amount = Amount::new(Decimal::new(tx.fee_charged, STELLAR_SCALE), "XLM".to_owned());
+        let previous = transactions.insert(tx.hash, (timestamp, amount));
+        assert!(previous.is_none());
     }
 
     Ok(transactions)
 }
 
-async fn address_payments(client: &HorizonHttpClient, address: &str) -> Result<Vec<Transaction>> {
+/// Loads the payments made and received by the address. Also returns the IDs
+/// of the path payments sent from the address, which include the conversion
+/// of the sent asset.
+async fn address_payments(horizon: &Horizon, address: &str) -> Result<(Vec<Transaction>, HashSet<String>)> {
     println!("Loading operations for {}...", address);
 
     let account = PublicKey::from_account_id(address)?;
-    let mut cursor = "".to_string();
+    let operations = horizon.all_records(api::operations::for_account(&account), |operation| &operation.base().paging_token).await?;
 
-    let mut transactions = Vec::new();
-
-    loop {
-        let request = api::operations::for_account(&account)
-            .with_limit(PAGE_LIMIT)
-            .with_cursor(&cursor);
-        let (_, response) = client.request(request).await?;
-        let records_len = response.records.len();
+    println!("Processing {} operations", operations.len());
 
-        println!("Processing {} operations", records_len);
+    let mut transactions = Vec::new();
+    let mut path_payments = HashSet::new();
 
-        for operation in response.records {
-            let (base, sender, destination, amount, description) = match operation {
-                operation::Operation::CreateAccount(op) => {
-                    (op.base, op.funder, op.account, Amount::new(Decimal::from_str(&op.starting_balance)?, "XLM".to_owned()), None)
-                }
-                operation::Operation::Payment(op) => {
-                    (op.base, op.from, op.to, Amount::new(Decimal::from_str(&op.amount)?, asset_to_string(&op.asset)), None)
-                }
-                operation::Operation::PathPaymentStrictReceive(op) => {
-                    (op.base, op.from, op.to, Amount::new(Decimal::from_str(&op.amount)?, asset_to_string(&op.asset)), None)
-                }
-                operation::Operation::PathPaymentStrictSend(op) => {
-                    (op.base, op.from, op.to, Amount::new(Decimal::from_str(&op.amount)?, asset_to_string(&op.asset)), None)
+    for operation in operations {
+        let (base, sender, destination, amount, description) = match operation {
+            operation::Operation::CreateAccount(op) => {
+                (op.base, op.funder, op.account, Amount::new(Decimal::from_str(&op.starting_balance)?, "XLM".to_owned()), None)
+            }
+            operation::Operation::Payment(op) => {
+                (op.base, op.from, op.to, amount_of_asset(Decimal::from_str(&op.amount)?, &op.asset), None)
+            }
+            operation::Operation::PathPaymentStrictReceive(op) => {
+                let source_amount = amount_of_asset(Decimal::from_str(&op.source_amount)?, &op.source_asset);
+                let amount = amount_of_asset(Decimal::from_str(&op.amount)?, &op.asset);
+                if op.from == address {
+                    path_payments.insert(op.base.id.clone());
                 }
-                operation::Operation::AccountMerge(op) => {
-                    let request = api::effects::for_operation(&op.base.id);
-                    let (_, response) = client.request(request).await?;
-                    println!("Looking up merged XLM amount for account merge...");
-                    let amount = response.records.into_iter().find_map(|effect| {
-                        match effect {
-                            Effect::AccountDebited(effect) => {
-                                let quantity = Decimal::from_str(&effect.amount);
-                                let currency = asset_to_string(&effect.asset);
-                                Some(quantity.map(|quantity| Amount::new(quantity, currency)))
-                            }
-                            _ => None,
-                        }
-                    }).context("Missing Effect::AccountDebited for Operation::AccountMerge")?;
-                    (op.base, op.account, op.into, amount?, None)
+                transactions.extend(path_payment_transactions(address, &op.base, op.from, op.to, source_amount, amount));
+                continue;
+            }
+            operation::Operation::PathPaymentStrictSend(op) => {
+                let source_amount = amount_of_asset(Decimal::from_str(&op.source_amount)?, &op.source_asset);
+                let amount = amount_of_asset(Decimal::from_str(&op.amount)?, &op.asset);
+                if op.from == address {
+                    path_payments.insert(op.base.id.clone());
                 }
-                operation::Operation::ClaimClaimableBalance(op) => {
-                    let request = api::effects::for_operation(&op.base.id);
-                    let (_, response) = client.request(request).await?;
-                    println!("Looking up claimed amount for balance ID {}...", op.balance_id);
-                    let amount = response.records.into_iter().find_map(|effect| {
-                        match effect {
-                            Effect::ClaimableBalanceClaimed(effect) => {
-                                let quantity = Decimal::from_str(&effect.amount).map_err(anyhow::Error::from);
-                                let currency = if effect.asset == "native" {
-                                    Ok("XLM".to_owned())
-                                } else {
-                                    let mut split = effect.asset.split(':');
-                                    match (split.next(), split.next()) {
-                                        (Some(code), Some(issuer)) => {
-                                            Ok(normalize_asset(code, issuer))
-                                        }
-                                        _ => Err(anyhow!("Invalid asset value, expected: 'NAME:ISSUER'")),
-                                    }
-                                };
-                                Some(match (quantity, currency) {
-                                    (Ok(quantity), Ok(currency)) => Ok(Amount::new(quantity, currency)),
-                                    (Err(e), _) | (_, Err(e)) => Err(e)
-                                })
-                            },
-                            _ => None
+                transactions.extend(path_payment_transactions(address, &op.base, op.from, op.to, source_amount, amount));
+                continue;
+            }
+            operation::Operation::AccountMerge(op) => {
+                let request = api::effects::for_operation(&op.base.id);
+                let response = horizon.request(request).await?;
+                println!("Looking up merged XLM amount for account merge...");
+                let amount = response.records.into_iter().find_map(|effect| {
+                    match effect {
+                        Effect::AccountDebited(effect) => {
+                            let quantity = Decimal::from_str(&effect.amount);
+                            Some(quantity.map(|quantity| amount_of_asset(quantity, &effect.asset)))
                         }
-                    }).context("Missing Effect::ClaimableBalanceClaimed for Operation::ClaimClaimableBalance")?;
-                    // todo: determine sender?
-                    (op.base, String::new(), op.claimant, amount?, Some(format!("Claimable Balance ID {}", op.balance_id)))
+                        _ => None,
+                    }
+                }).context("Missing Effect::AccountDebited for Operation::AccountMerge")?;
+                (op.base, op.account, op.into, amount?, None)
+            }
+            operation::Operation::CreateClaimableBalance(op) => {
+                // claimants only receive the balance once they claim it
+                if op.base.source_account != address {
+                    continue;
                 }
-                operation => {
-                    // assuming other operations are not relevant to account balance
-                    cursor = operation.base().paging_token.clone();
+                let amount = amount_of_asset_str(&op.amount, &op.asset)?;
+                let destination = op.claimants.into_iter()
+                    .map(|claimant| claimant.destination)
+                    .find(|destination| destination != address)
+                    .unwrap_or_else(|| address.to_owned());
+
+                // the ID links this send to the receive of whoever claims it
+                let request = api::effects::for_operation(&op.base.id);
+                let response = horizon.request(request).await?;
+                println!("Looking up ID of created claimable balance...");
+                let balance_id = response.records.into_iter().find_map(|effect| {
+                    match effect {
+                        Effect::ClaimableBalanceCreated(effect) => Some(effect.balance_id),
+                        _ => None
+                    }
+                }).context("Missing Effect::ClaimableBalanceCreated for Operation::CreateClaimableBalance")?;
+
+                (op.base, address.to_owned(), destination, amount, Some(format!("{}{}", CLAIMABLE_BALANCE_PREFIX, balance_id)))
+            }
+            operation::Operation::ClaimClaimableBalance(op) => {
+                if op.claimant != address {
                     continue;
                 }
-            };
-
-            cursor = base.paging_token;
-
-            let timestamp = base.created_at.naive_utc();
-            let tx_hash = base.transaction_hash;
+                let request = api::effects::for_operation(&op.base.id);
+                let response = horizon.request(request).await?;
+                println!("Looking up claimed amount for balance ID {}...", op.balance_id);
+                let amount = response.records.into_iter().find_map(|effect| {
+                    match effect {
+                        Effect::ClaimableBalanceClaimed(effect) => Some(amount_of_asset_str(&effect.amount, &effect.asset)),
+                        _ => None
+                    }
+                }).context("Missing Effect::ClaimableBalanceClaimed for Operation::ClaimClaimableBalance")?;
+
+                println!("Looking up sender of balance ID {}...", op.balance_id);
+                let request = api::operations::for_claimbable_balance(&op.balance_id);
+                let response = horizon.request(request).await?;
+                let creation = response.records.into_iter().find_map(|operation| {
+                    match operation {
+                        operation::Operation::CreateClaimableBalance(op) => Some(op),
+                        _ => None,
+                    }
+                }).context("Missing Operation::CreateClaimableBalance for Operation::ClaimClaimableBalance")?;
+
+                // When reclaiming a balance we created for someone else, it
+                // is returned by them
+                let sender = if creation.base.source_account == address {
+                    creation.claimants.into_iter()
+                        .map(|claimant| claimant.destination)
+                        .find(|destination| destination != address)
+                        .unwrap_or_else(|| address.to_owned())
+                } else {
+                    creation.base.source_account
+                };
 
-            if sender == destination {
+                (op.base, sender, op.claimant, amount?, Some(format!("{}{}", CLAIMABLE_BALANCE_PREFIX, op.balance_id)))
+            }
+            operation::Operation::LiquidityPoolDeposit(op) => {
+                let reserves = pool_reserves(&op.reserves_deposited)?;
+                let shares = pool_shares(&op.shares_received, &op.liquidity_pool_id, &reserves)?;
+                let parts = reserves.len();
+                for reserve in reserves {
+                    let mut tx = operation_transaction(&op.base, Operation::Trade { incoming: split_shares(&shares, parts), outgoing: reserve });
+                    tx.description = Some("Liquidity pool deposit".to_owned());
+                    transactions.push(tx);
+                }
                 continue;
             }
-
-            let operation = if sender == address {
-                Operation::Send(amount)
-            } else {
-                // Crude spam recognition
-                if amount.currency == "XLM" && amount.quantity > Decimal::ZERO && amount.quantity < (Decimal::ONE / Decimal::ONE_HUNDRED) {
-                    Operation::Spam(amount)
-                } else {
-                    Operation::Receive(amount)
+            operation::Operation::LiquidityPoolWithdraw(op) => {
+                let reserves = pool_reserves(&op.reserves_received)?;
+                let shares = pool_shares(&op.shares, &op.liquidity_pool_id, &reserves)?;
+                let parts = reserves.len();
+                for reserve in reserves {
+                    let mut tx = operation_transaction(&op.base, Operation::Trade { incoming: reserve, outgoing: split_shares(&shares, parts) });
+                    tx.description = Some("Liquidity pool withdrawal".to_owned());
+                    transactions.push(tx);
                 }
-            };
+                continue;
+            }
+            _ => {
+                // assuming other operations are not relevant to account balance
+                continue;
+            }
+        };
 
-            let mut tx = Transaction::new(timestamp, operation);
+        if let Some(mut tx) = payment_transaction(address, &base, sender, destination, amount) {
             tx.description = description;
-            tx.tx_hash = Some(tx_hash);
-            tx.blockchain = Some("XLM".to_owned());
             transactions.push(tx);
         }
-
-        if records_len < PAGE_LIMIT as usize {
-            break;
-        }
     }
 
-    Ok(transactions)
+    Ok((transactions, path_payments))
 }
 
-async fn address_trades(client: &HorizonHttpClient, address: &str) -> Result<Vec<Transaction>> {
+/// Loads the trades of the address, along with the ID of the operation that
+/// made each trade.
+async fn address_trades(horizon: &Horizon, address: &str) -> Result<Vec<(String, Transaction)>> {
     println!("Loading trades for {}...", address);
 
     let account = PublicKey::from_account_id(address)?;
-    let mut cursor = "".to_string();
-
-    let mut transactions: Vec<Transaction> = Vec::new();
-
-    loop {
-        let request = api::trades::for_account(&account)
-            .with_limit(PAGE_LIMIT)
-            .with_cursor(&cursor);
-        let (_, response) = client.request(request).await?;
-        let records_len = response.records.len();
+    let trades = horizon.all_records(api::trades::for_account(&account), |trade| &trade.paging_token).await?;
 
-        println!("Processing {} trades", records_len);
+    println!("Processing {} trades", trades.len());
 
-        for trade in response.records {
-            cursor = trade.paging_token;
+    let mut transactions = Vec::new();
 
-            let timestamp = trade.ledger_close_time.naive_utc();
+    for trade in trades {
+        let timestamp = trade.ledger_close_time.naive_utc();
 
-            let base_currency = asset_to_string(&trade.base_asset);
-            let base_amount = Amount::new(Decimal::from_str(&trade.base_amount)?, base_currency);
-            let counter_currency = asset_to_string(&trade.counter_asset);
-            let counter_amount = Amount::new(Decimal::from_str(&trade.counter_amount)?, counter_currency);
+        let base_amount = amount_of_asset(Decimal::from_str(&trade.base_amount)?, &trade.base_asset);
+        let counter_amount = amount_of_asset(Decimal::from_str(&trade.counter_amount)?, &trade.counter_asset);
 
-            let operation = if trade.base_account.is_some_and(|base| base == address) {
-                Operation::Trade { incoming: counter_amount, outgoing: base_amount }
-            } else {
-                Operation::Trade { incoming: base_amount, outgoing: counter_amount }
-            };
+        let operation = if trade.base_account.is_some_and(|base| base == address) {
+            Operation::Trade { incoming: counter_amount, outgoing: base_amount }
+        } else {
+            Operation::Trade { incoming: base_amount, outgoing: counter_amount }
+        };
 
-            let mut transaction = Transaction::new(timestamp, operation);
-            transaction.blockchain = Some("XLM".to_owned());
-            transactions.push(transaction);
-        }
+        // trade IDs are made up of the operation ID and an index
+        let operation_id = trade.id.split('-').next().unwrap_or_default().to_owned();
 
-        if records_len < PAGE_LIMIT as usize {
-            break;
-        }
+        let mut transaction = Transaction::new(timestamp, operation);
+        transaction.blockchain = Some("XLM".to_owned());
+        transactions.push((operation_id, transaction));
     }
 
     Ok(transactions)
 }
 
 pub(crate) async fn address_transactions(
+    settings: &BackendSettings,
     address: &str,
 ) -> Result<Vec<Transaction>> {
-    let client = HorizonHttpClient::new_from_str("https://horizon.stellar.org")?;
-    let (mut payments, trades, mut fees) = try_join!(
-        address_payments(&client, address),
-        address_trades(&client, address),
-        address_fees(&client, address)
+    let horizon = Horizon::new(settings)?;
+    let ((mut payments, path_payments), trades, mut fees) = try_join!(
+        address_payments(&horizon, address),
+        address_trades(&horizon, address),
+        address_fees(&horizon, address)
     )?;
 
     // Associate fees with the payments
@@ -271,21 +428,196 @@ pub(crate) async fn address_transactions(
     }
 
     transactions.extend(payments);
-    transactions.extend(trades);
+
+    // The trades made by path payments are already part of those payments
+    transactions.extend(trades.into_iter()
+        .filter(|(operation_id, _)| !path_payments.contains(operation_id))
+        .map(|(_, trade)| trade));
 
     Ok(transactions)
 }
 
-pub(crate) fn load_stellar_account_async(source_path: String) -> LoadFuture {
-    Box::pin(async move { address_transactions(&source_path).await })
+pub(crate) fn load_stellar_account_async(source_path: String, options: SyncOptions) -> LoadFuture {
+    Box::pin(async move { address_transactions(&options.backends.horizon, &source_path).await })
 }
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
 static STELLAR_ACCOUNT: TransactionSource = TransactionSource {
     id: "StellarAccount",
     label: "Stellar Account",
-    csv: &[],
-    detect: None,
-    load_sync: None,
     load_async: Some(load_stellar_account_async),
+    ..TransactionSource::DEFAULT
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use serde_json::{json, Value};
+
+    const OWN: &str = "GBNZILSTVQZ4R7IKQDGHYGY2QXL5QOFJYQMXPKWRRM5PAV7Y4M67AQUA";
+    const OTHER: &str = "GA5ZSEJYB37JRC5AVCIA5MOP4RHTM335X2KGX3IHOJAPP5RE34K4KZVN";
+
+    fn link() -> Value {
+        json!({ "href": "https://horizon.stellar.org/" })
+    }
+
+    fn page(records: Vec<Value>) -> String {
+        json!({
+            "_links": { "self": link(), "next": link(), "prev": link() },
+            "_embedded": { "records": records },
+        }).to_string()
+    }
+
+    fn operation(id: &str, kind: &str, source_account: &str, fields: Value) -> Value {
+        let mut operation = json!({
+            "_links": { "self": link(), "transaction": link(), "effects": link(), "succeeds": link(), "precedes": link() },
+            "id": id,
+            "paging_token": id,
+            "transaction_successful": true,
+            "source_account": source_account,
+            "type": kind,
+            "type_i": 0,
+            "created_at": "2024-01-01T12:00:00Z",
+            "transaction_hash": format!("{:0>64}", id),
+        });
+        operation.as_object_mut().unwrap().extend(fields.as_object().unwrap().clone());
+        operation
+    }
+
+    async fn mock_pages(server: &mut mockito::ServerGuard, path: &str, records: Vec<Value>, last_cursor: &str) {
+        server.mock("GET", path)
+            .match_query(Matcher::UrlEncoded("cursor".to_owned(), "".to_owned()))
+            .with_body(page(records))
+            .create_async()
+            .await;
+        server.mock("GET", path)
+            .match_query(Matcher::UrlEncoded("cursor".to_owned(), last_cursor.to_owned()))
+            .with_body(page(vec![]))
+            .create_async()
+            .await;
+    }
+
+    fn usdc(quantity: Decimal) -> Amount {
+        asset_amount(quantity, "USDC", OTHER)
+    }
+
+    #[tokio::test]
+    async fn sync_payments_balances_and_pools() {
+        let mut server = mockito::Server::new_async().await;
+
+        mock_pages(&mut server, &format!("/accounts/{}/operations", OWN), vec![
+            operation("100", "path_payment_strict_send", OWN, json!({
+                "asset_type": "credit_alphanum4", "asset_code": "USDC", "asset_issuer": OTHER,
+                "from": OWN, "to": OTHER, "amount": "10.0000000", "path": [],
+                "source_amount": "100.0000000", "destination_min": "9.0000000", "source_asset_type": "native",
+            })),
+            operation("200", "claim_claimable_balance", OWN, json!({
+                "balance_id": "00000000abc", "claimant": OWN,
+            })),
+            operation("250", "create_claimable_balance", OWN, json!({
+                "asset": format!("USDC:{}", OTHER),
+                "amount": "7.0000000",
+                "claimants": [{ "destination": OTHER, "predicate": { "unconditional": true } }],
+            })),
+            operation("300", "liquidity_pool_deposit", OWN, json!({
+                "liquidity_pool_id": "pool",
+                "reserves_max": [],
+                "min_price": "0.1", "min_price_r": { "n": 1, "d": 10 },
+                "max_price": "0.1", "max_price_r": { "n": 1, "d": 10 },
+                "reserves_deposited": [
+                    { "asset": "native", "amount": "50.0000000" },
+                    { "asset": format!("USDC:{}", OTHER), "amount": "5.0000000" },
+                ],
+                "shares_received": "10.0000000",
+            })),
+        ], "300").await;
+
+        // the trade made by the path payment is not counted separately
+        mock_pages(&mut server, &format!("/accounts/{}/trades", OWN), vec![json!({
+            "_links": { "self": link(), "base": link(), "counter": link(), "operation": link() },
+            "id": "100-0",
+            "paging_token": "100-0",
+            "ledger_close_time": "2024-01-01T12:00:00Z",
+            "trade_type": "orderbook",
+            "base_account": OWN,
+            "base_amount": "100.0000000",
+            "base_asset_type": "native",
+            "counter_account": OTHER,
+            "counter_amount": "10.0000000",
+            "counter_asset_type": "credit_alphanum4",
+            "counter_asset_code": "USDC",
+            "counter_asset_issuer": OTHER,
+            "base_is_seller": true,
+        })], "100-0").await;
+
+        mock_pages(&mut server, &format!("/accounts/{}/transactions", OWN), vec![], "").await;
+
+        server.mock("GET", "/operations/200/effects")
+            .match_query(Matcher::Any)
+            .with_body(page(vec![json!({
+                "_links": { "operation": link(), "succeeds": link(), "precedes": link() },
+                "id": "200-1",
+                "paging_token": "200-1",
+                "account": OWN,
+                "type": "claimable_balance_claimed",
+                "type_i": 0,
+                "created_at": "2024-01-01T12:00:00Z",
+                "asset": format!("USDC:{}", OTHER),
+                "balance_id": "00000000abc",
+                "amount": "25.0000000",
+            })]))
+            .create_async()
+            .await;
+
+        server.mock("GET", "/operations/250/effects")
+            .match_query(Matcher::Any)
+            .with_body(page(vec![json!({
+                "_links": { "operation": link(), "succeeds": link(), "precedes": link() },
+                "id": "250-1",
+                "paging_token": "250-1",
+                "account": OWN,
+                "type": "claimable_balance_created",
+                "type_i": 0,
+                "created_at": "2024-01-01T12:00:00Z",
+                "asset": format!("USDC:{}", OTHER),
+                "balance_id": "00000000def",
+                "amount": "7.0000000",
+            })]))
+            .create_async()
+            .await;
+
+        server.mock("GET", "/claimable_balances/00000000abc/operations")
+            .match_query(Matcher::Any)
+            .with_body(page(vec![operation("150", "create_claimable_balance", OTHER, json!({
+                "asset": format!("USDC:{}", OTHER),
+                "amount": "25.0000000",
+                "claimants": [{ "destination": OWN, "predicate": { "unconditional": true } }],
+            }))]))
+            .create_async()
+            .await;
+
+        let settings = BackendSettings {
+            url: Some(server.url()),
+            ..Default::default()
+        };
+        let transactions = address_transactions(&settings, OWN).await.unwrap();
+        let operations: Vec<(&Operation, Option<&str>)> = transactions.iter()
+            .map(|tx| (&tx.operation, tx.counterparty.as_deref()))
+            .collect();
+
+        let shares = Amount::new_asset(Decimal::from(5), "XLM/USDC Pool Share".to_owned(), AssetId::new("stellar", "pool"));
+        assert_eq!(operations, vec![
+            (&Operation::Trade { incoming: usdc(Decimal::from(10)), outgoing: Amount::new(Decimal::from(100), "XLM".to_owned()) }, None),
+            (&Operation::Send(usdc(Decimal::from(10))), Some(OTHER)),
+            (&Operation::Receive(usdc(Decimal::from(25))), Some(OTHER)),
+            (&Operation::Send(usdc(Decimal::from(7))), Some(OTHER)),
+            (&Operation::Trade { incoming: shares.clone(), outgoing: Amount::new(Decimal::from(50), "XLM".to_owned()) }, None),
+            (&Operation::Trade { incoming: shares, outgoing: usdc(Decimal::from(5)) }, None),
+        ]);
+
+        // both sides of a claimable balance can be matched by its ID
+        let balance_ids: Vec<Option<&str>> = transactions.iter().map(claimable_balance_id).collect();
+        assert_eq!(balance_ids, vec![None, None, Some("00000000abc"), Some("00000000def"), None, None]);
+    }
+}
@@ -0,0 +1,268 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    base::{Amount, Operation, Transaction},
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+const KOINLY_HEADERS: &[&str] = &[
+    "Date",
+    "Sent Amount",
+    "Sent Currency",
+    "Received Amount",
+    "Received Currency",
+    "Fee Amount",
+    "Fee Currency",
+    "Net Worth Amount",
+    "Net Worth Currency",
+    "Label",
+    "Description",
+    "TxHash",
+];
+
+/// A row in the Koinly universal format. A row with only a sent amount is a
+/// withdrawal, a row with only a received amount a deposit and a row with
+/// both a trade. The label further specifies the kind of transaction.
+#[derive(Debug, Serialize, Deserialize)]
+struct KoinlyTransaction {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Sent Amount")]
+    sent_amount: Option<Decimal>,
+    #[serde(rename = "Sent Currency")]
+    sent_currency: Option<String>,
+    #[serde(rename = "Received Amount")]
+    received_amount: Option<Decimal>,
+    #[serde(rename = "Received Currency")]
+    received_currency: Option<String>,
+    #[serde(rename = "Fee Amount")]
+    fee_amount: Option<Decimal>,
+    #[serde(rename = "Fee Currency")]
+    fee_currency: Option<String>,
+    #[serde(rename = "Net Worth Amount")]
+    net_worth_amount: Option<Decimal>,
+    #[serde(rename = "Net Worth Currency")]
+    net_worth_currency: Option<String>,
+    #[serde(rename = "Label")]
+    label: Option<String>,
+    #[serde(rename = "Description")]
+    description: Option<String>,
+    #[serde(rename = "TxHash")]
+    tx_hash: Option<String>,
+}
+
+// Koinly writes "2024-01-15 10:00:00 UTC", but accepts a few variations
+fn parse_koinly_date(raw: &str) -> Result<NaiveDateTime> {
+    let raw = raw.trim();
+    let without_zone = raw.strip_suffix("UTC").unwrap_or(raw).trim_end();
+    NaiveDateTime::parse_from_str(without_zone, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(without_zone, "%Y-%m-%d %H:%M"))
+        .or_else(|_| DateTime::parse_from_rfc3339(raw).map(|date| date.naive_utc()))
+        .with_context(|| format!("invalid date '{}'", raw))
+}
+
+fn amount(quantity: Option<Decimal>, currency: Option<String>) -> Option<Amount> {
+    match (quantity, currency) {
+        (Some(quantity), Some(currency)) if !currency.is_empty() => Some(Amount::new(quantity, currency)),
+        _ => None,
+    }
+}
+
+impl KoinlyTransaction {
+    fn into_transaction(self) -> Result<Transaction> {
+        let timestamp = parse_koinly_date(&self.date)?;
+        let sent = amount(self.sent_amount, self.sent_currency);
+        let received = amount(self.received_amount, self.received_currency);
+        let fee = amount(self.fee_amount, self.fee_currency);
+        let label = self.label.unwrap_or_default().to_lowercase();
+
+        let (operation, fee) = match (sent, received) {
+            (Some(outgoing), Some(incoming)) => match label.as_str() {
+                "swap" | "liquidity in" | "liquidity out" => (Operation::Swap { incoming, outgoing }, fee),
+                _ => (Operation::Trade { incoming, outgoing }, fee),
+            },
+            (None, Some(amount)) => (match label.as_str() {
+                "airdrop" => Operation::Airdrop(amount),
+                "fork" => Operation::ChainSplit(amount),
+                "mining" | "reward" | "income" | "other income" | "loan interest" | "lending interest" | "realized gain" => Operation::Income(amount),
+                "staking" => Operation::Staking(amount),
+                "cashback" => Operation::Cashback(amount),
+                "gift" => Operation::IncomingGift(amount),
+                "spam" => Operation::Spam(amount),
+                _ if amount.is_fiat() => Operation::FiatDeposit(amount),
+                _ => Operation::Receive(amount),
+            }, fee),
+            (Some(amount), None) => (match label.as_str() {
+                "gift" | "donation" => Operation::OutgoingGift(amount),
+                "lost" => Operation::Lost(amount),
+                "stolen" => Operation::Stolen(amount),
+                "burn" => Operation::Burn(amount),
+                "cost" | "interest payment" | "margin fee" => Operation::Expense(amount),
+                _ if amount.is_fiat() => Operation::FiatWithdrawal(amount),
+                _ => Operation::Send(amount),
+            }, fee),
+            // a row with only a fee represents a fee payment
+            (None, None) => (Operation::Fee(fee.context("row has no amounts")?), None),
+        };
+
+        let mut tx = Transaction::new(timestamp, operation);
+        tx.fee = fee.filter(|fee| !fee.quantity.is_zero());
+        if self.net_worth_currency.as_deref() == Some("EUR") {
+            tx.value = self.net_worth_amount.map(Amount::from_fiat);
+        }
+        tx.description = self.description.filter(|s| !s.is_empty());
+        tx.tx_hash = self.tx_hash.filter(|s| !s.is_empty());
+        Ok(tx)
+    }
+}
+
+impl From<&Transaction> for KoinlyTransaction {
+    fn from(item: &Transaction) -> Self {
+        let mut sent = None;
+        let mut received = None;
+        let mut fee = item.fee.as_ref();
+
+        let label = match &item.operation {
+            // A buy or sell is a receive or send that wasn't matched with
+            // another wallet, which it becomes again when imported.
+            Operation::Buy(amount) => { received = Some(amount); None }
+            Operation::Sell(amount) => { sent = Some(amount); None }
+            Operation::Trade { incoming, outgoing } => { received = Some(incoming); sent = Some(outgoing); None }
+            Operation::Swap { incoming, outgoing } => { received = Some(incoming); sent = Some(outgoing); Some("swap") }
+            Operation::FiatDeposit(amount) | Operation::Receive(amount) => { received = Some(amount); None }
+            Operation::FiatWithdrawal(amount) | Operation::Send(amount) => { sent = Some(amount); None }
+            Operation::Fee(amount) => { fee = Some(amount); None }
+            Operation::ChainSplit(amount) => { received = Some(amount); Some("fork") }
+            Operation::Expense(amount) => { sent = Some(amount); Some("cost") }
+            Operation::Stolen(amount) => { sent = Some(amount); Some("stolen") }
+            Operation::Lost(amount) => { sent = Some(amount); Some("lost") }
+            Operation::Burn(amount) => { sent = Some(amount); Some("burn") }
+            Operation::Income(amount) => { received = Some(amount); Some("income") }
+            Operation::Airdrop(amount) => { received = Some(amount); Some("airdrop") }
+            Operation::Staking(amount) => { received = Some(amount); Some("staking") }
+            Operation::Cashback(amount) => { received = Some(amount); Some("cashback") }
+            Operation::IncomingGift(amount) => { received = Some(amount); Some("gift") }
+            Operation::OutgoingGift(amount) => { sent = Some(amount); Some("gift") }
+            Operation::Spam(amount) => { received = Some(amount); Some("spam") }
+        };
+
+        let net_worth = item.value.as_ref();
+
+        Self {
+            date: format!("{} UTC", item.timestamp.format("%Y-%m-%d %H:%M:%S")),
+            sent_amount: sent.map(|amount| amount.quantity),
+            sent_currency: sent.map(|amount| amount.currency.clone()),
+            received_amount: received.map(|amount| amount.quantity),
+            received_currency: received.map(|amount| amount.currency.clone()),
+            fee_amount: fee.map(|amount| amount.quantity),
+            fee_currency: fee.map(|amount| amount.currency.clone()),
+            net_worth_amount: net_worth.map(|amount| amount.quantity),
+            net_worth_currency: net_worth.map(|amount| amount.currency.clone()),
+            label: label.map(|label| label.to_owned()),
+            description: item.description.clone(),
+            tx_hash: item.tx_hash.clone(),
+        }
+    }
+}
+
+pub(crate) fn save_transactions_to_koinly_csv(transactions: &[Transaction], output_path: &Path) -> Result<()> {
+    println!("Saving {}", output_path.display());
+
+    let mut wtr = csv::Writer::from_path(output_path)?;
+
+    for tx in transactions {
+        wtr.serialize(KoinlyTransaction::from(tx))?;
+    }
+
+    Ok(())
+}
+
+// loads a CSV file in the Koinly universal format
+fn load_koinly_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions = Vec::new();
+    for (index, result) in rdr.deserialize().enumerate() {
+        let record: KoinlyTransaction = result?;
+        match record.into_transaction() {
+            Ok(tx) => transactions.push(tx),
+            Err(e) => println!("Koinly: skipping row {}: {}", index + 1, e),
+        }
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static KOINLY_CSV: TransactionSource = TransactionSource {
+    id: "KoinlyCsv",
+    label: "Koinly universal format (CSV)",
+    csv: &[CsvSpec::new(KOINLY_HEADERS)],
+    load_sync: Some(load_koinly_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn load_and_save_universal_format() {
+        let path = Path::new("tests/data/koinly.csv");
+        assert!(KOINLY_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_koinly_csv(path, &LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None }).unwrap();
+        assert_eq!(transactions.len(), 7);
+
+        assert_eq!(transactions[0].operation, Operation::FiatDeposit(Amount::new(dec!(500), "EUR".to_owned())));
+
+        let trade = &transactions[1];
+        assert_eq!(trade.timestamp, parse_date_time("2024-01-15 10:00:00").unwrap());
+        assert_eq!(trade.operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.01), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(395), "EUR".to_owned()),
+        });
+        assert_eq!(trade.fee, Some(Amount::new(dec!(1.50), "EUR".to_owned())));
+
+        let staking = &transactions[2];
+        assert_eq!(staking.operation, Operation::Staking(Amount::new(dec!(0.002), "ETH".to_owned())));
+        assert_eq!(staking.value, Some(Amount::from_fiat(dec!(4.40))));
+
+        assert_eq!(transactions[3].operation, Operation::OutgoingGift(Amount::new(dec!(0.001), "BTC".to_owned())));
+        assert_eq!(transactions[4].operation, Operation::Fee(Amount::new(dec!(0.0004), "ETH".to_owned())));
+        assert_eq!(transactions[5].operation, Operation::Swap {
+            incoming: Amount::new(dec!(10), "DOT".to_owned()),
+            outgoing: Amount::new(dec!(0.02), "ETH".to_owned()),
+        });
+
+        let send = &transactions[6];
+        assert_eq!(send.operation, Operation::Send(Amount::new(dec!(0.005), "BTC".to_owned())));
+        assert_eq!(send.tx_hash.as_deref(), Some("a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d"));
+
+        // an unmatched receive is exported as deposit with its value as net worth
+        let mut transactions = transactions;
+        let mut buy = Transaction::new(parse_date_time("2024-04-01 12:00:00").unwrap(), Operation::Buy(Amount::new(dec!(0.01), "BTC".to_owned())));
+        buy.value = Some(Amount::from_fiat(dec!(600)));
+        transactions.push(buy);
+
+        let output_path = std::env::temp_dir().join(format!("raccoin_koinly_{}.csv", std::process::id()));
+        save_transactions_to_koinly_csv(&transactions, &output_path).unwrap();
+        let reloaded = load_koinly_csv(&output_path, &LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None }).unwrap();
+        std::fs::remove_file(output_path).unwrap();
+        assert_eq!(reloaded.iter().map(|tx| &tx.operation).take(7).collect::<Vec<_>>(), transactions.iter().map(|tx| &tx.operation).take(7).collect::<Vec<_>>());
+        assert_eq!(reloaded[2].value, transactions[2].value);
+        assert_eq!(reloaded[7].operation, Operation::Receive(Amount::new(dec!(0.01), "BTC".to_owned())));
+        assert_eq!(reloaded[7].value, Some(Amount::from_fiat(dec!(600))));
+    }
+}
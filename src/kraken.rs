@@ -9,7 +9,7 @@ use serde::de::IntoDeserializer;
 use crate::{
     base::{Amount, Transaction},
     time::parse_date_time,
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -506,7 +506,7 @@ fn drain_pending_kraken_trades(
     Ok(())
 }
 
-fn load_kraken_ledger_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_kraken_ledger_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut reader = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
     let mut pending_by_refid: HashMap<String, PendingLedgerTrade> = HashMap::new();
@@ -521,7 +521,7 @@ fn load_kraken_ledger_csv(input_path: &Path) -> Result<Vec<Transaction>> {
     Ok(transactions)
 }
 
-fn load_kraken_trades_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_kraken_trades_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut reader = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -544,9 +544,8 @@ static KRAKEN_LEDGER_CSV: TransactionSource = TransactionSource {
         CsvSpec::new(KRAKEN_LEDGER_HEADERS),
         CsvSpec::new(KRAKEN_LEDGER_HEADERS_EXTENDED),
     ],
-    detect: None,
     load_sync: Some(load_kraken_ledger_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -557,9 +556,8 @@ static KRAKEN_TRADES_CSV: TransactionSource = TransactionSource {
         CsvSpec::new(KRAKEN_TRADES_HEADERS),
         CsvSpec::new(KRAKEN_TRADES_HEADERS_EXTENDED),
     ],
-    detect: None,
     load_sync: Some(load_kraken_trades_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 fn deserialize_kraken_timestamp<'de, D>(d: D) -> std::result::Result<NaiveDateTime, D::Error>
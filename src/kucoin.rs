@@ -0,0 +1,357 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    base::{blockchain_from_network, Amount, Operation, Transaction},
+    time::deserialize_date_time,
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+#[derive(Debug, Deserialize)]
+enum Side {
+    #[serde(alias = "BUY", alias = "buy")]
+    Buy,
+    #[serde(alias = "SELL", alias = "sell")]
+    Sell,
+}
+
+// struct for storing the following CSV columns of "Spot Orders_Filled Orders":
+// UID,Account Type,Order ID,Order Time(UTC),Symbol,Side,Order Type,Order Price,Order Amount,Avg. Filled Price,Filled Amount,Filled Volume,Filled Volume (USDT),Filled Time(UTC),Fee,Fee Currency,Tax,Status
+#[derive(Debug, Deserialize)]
+struct KuCoinSpotOrder {
+    #[serde(rename = "Order ID")]
+    order_id: String,
+    #[serde(rename = "Filled Time(UTC)", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+    /// Base and quote currency, like "BTC-USDT".
+    #[serde(rename = "Symbol")]
+    symbol: String,
+    #[serde(rename = "Side")]
+    side: Side,
+    /// Filled quantity of the base currency.
+    #[serde(rename = "Filled Amount")]
+    filled_amount: Decimal,
+    /// Filled quantity of the quote currency.
+    #[serde(rename = "Filled Volume")]
+    filled_volume: Decimal,
+    #[serde(rename = "Fee")]
+    fee: Decimal,
+    #[serde(rename = "Fee Currency")]
+    fee_currency: String,
+}
+
+impl TryFrom<KuCoinSpotOrder> for Transaction {
+    type Error = anyhow::Error;
+
+    fn try_from(item: KuCoinSpotOrder) -> Result<Self> {
+        let (base, quote) = item.symbol.split_once('-')
+            .with_context(|| format!("unrecognized symbol '{}'", item.symbol))?;
+        let base = Amount::new(item.filled_amount, base.to_owned());
+        let quote = Amount::new(item.filled_volume, quote.to_owned());
+
+        let mut tx = match item.side {
+            Side::Buy => Transaction::trade(item.timestamp, base, quote),
+            Side::Sell => Transaction::trade(item.timestamp, quote, base),
+        };
+        if !item.fee.is_zero() {
+            tx.fee = Some(Amount::new(item.fee, item.fee_currency));
+        }
+        tx.description = Some(format!("Order {}", item.order_id));
+        Ok(tx)
+    }
+}
+
+// struct for storing the following CSV columns of the deposit and withdrawal history:
+// UID,Account Type,Time(UTC),Coin,Amount,Fee,Hash,Deposit Address,Transfer Network,Status,Remarks
+// UID,Account Type,Time(UTC),Coin,Amount,Fee,Hash,Withdrawal Address/Account,Transfer Network,Status,Remarks
+#[derive(Debug, Deserialize)]
+struct KuCoinTransfer {
+    #[serde(rename = "Time(UTC)", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+    #[serde(rename = "Coin")]
+    coin: String,
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+    #[serde(rename = "Fee")]
+    fee: Option<Decimal>,
+    #[serde(rename = "Hash")]
+    hash: String,
+    #[serde(rename = "Deposit Address", alias = "Withdrawal Address/Account")]
+    address: String,
+    #[serde(rename = "Transfer Network")]
+    network: String,
+    #[serde(rename = "Status")]
+    status: String,
+    #[serde(rename = "Remarks")]
+    remarks: String,
+}
+
+impl KuCoinTransfer {
+    fn into_transaction(self, withdrawal: bool) -> Transaction {
+        let amount = Amount::new(self.amount, self.coin.clone());
+        let mut tx = match (withdrawal, amount.is_fiat()) {
+            (false, false) => Transaction::receive(self.timestamp, amount),
+            (false, true) => Transaction::fiat_deposit(self.timestamp, amount),
+            (true, false) => Transaction::send(self.timestamp, amount),
+            (true, true) => Transaction::fiat_withdrawal(self.timestamp, amount),
+        };
+        tx.fee = self.fee.filter(|fee| !fee.is_zero()).map(|fee| Amount::new(fee, self.coin.clone()));
+        if !self.hash.is_empty() {
+            tx.tx_hash = Some(self.hash);
+            tx.blockchain = Some(blockchain_from_network(&self.network, &self.coin));
+        }
+        // the deposit address is our own
+        if withdrawal && !self.address.is_empty() {
+            tx.counterparty = Some(self.address);
+        }
+        if !self.remarks.is_empty() {
+            tx.description = Some(self.remarks);
+        }
+        tx
+    }
+}
+
+// struct for storing the following CSV columns of "Account History":
+// UID,Account Type,Currency,Side,Amount,Fee,Time(UTC),Remark,Type
+#[derive(Debug, Deserialize)]
+struct KuCoinAccountEntry {
+    #[serde(rename = "Time(UTC)", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+    #[serde(rename = "Currency")]
+    currency: String,
+    #[serde(rename = "Side")]
+    side: String,
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+    #[serde(rename = "Remark")]
+    remark: String,
+    #[serde(rename = "Type")]
+    type_: String,
+}
+
+// Account history types only used for earn and staking profits
+const KUCOIN_PROFIT_TYPES: &[&str] = &[
+    "Staking Profits",
+    "Soft Staking Profits",
+    "Savings Interest",
+    "Lending Interest",
+];
+
+// The general earn and staking types are also used for subscriptions and
+// redemptions, so only entries with one of these remarks are profits.
+const KUCOIN_EARN_TYPES: &[&str] = &["Earn", "Staking"];
+const KUCOIN_PROFIT_REMARKS: &[&str] = &[
+    "Staking profits",
+    "Earn profits",
+    "Interest",
+    "Rewards",
+];
+
+impl KuCoinAccountEntry {
+    /// Only earn and staking profits are loaded from the account history,
+    /// since trades, deposits and withdrawals have their own exports.
+    fn into_transaction(self) -> Option<Transaction> {
+        let is_one_of = |value: &str, list: &[&str]| list.iter().any(|item| item.eq_ignore_ascii_case(value.trim()));
+        let is_profit = is_one_of(&self.type_, KUCOIN_PROFIT_TYPES) ||
+            (is_one_of(&self.type_, KUCOIN_EARN_TYPES) && is_one_of(&self.remark, KUCOIN_PROFIT_REMARKS));
+        if !is_profit || self.side != "Deposit" {
+            return None;
+        }
+
+        let mut tx = Transaction::new(self.timestamp, Operation::Staking(Amount::new(self.amount, self.currency)));
+        tx.description = Some(if self.remark.is_empty() { self.type_ } else { self.remark });
+        Some(tx)
+    }
+}
+
+fn load_kucoin_spot_orders_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for result in rdr.deserialize() {
+        let record: KuCoinSpotOrder = result?;
+        if record.filled_amount.is_zero() {
+            continue;
+        }
+        transactions.push(record.try_into()?);
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+fn load_kucoin_deposits_withdrawals_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+    let withdrawal = rdr.headers()?.iter().any(|header| header == "Withdrawal Address/Account");
+
+    let mut transactions = Vec::new();
+    for result in rdr.deserialize() {
+        let record: KuCoinTransfer = result?;
+        match record.status.as_str() {
+            "SUCCESS" | "Success" => transactions.push(record.into_transaction(withdrawal)),
+            "FAILURE" | "Failure" | "CANCEL" | "Canceled" => {}
+            status => return Err(anyhow!("unexpected status '{}' of transfer at {}", status, record.timestamp)),
+        }
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+fn load_kucoin_account_history_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions = Vec::new();
+    for result in rdr.deserialize() {
+        let record: KuCoinAccountEntry = result?;
+        transactions.extend(record.into_transaction());
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static KUCOIN_SPOT_ORDERS_CSV: TransactionSource = TransactionSource {
+    id: "KuCoinSpotOrdersCsv",
+    label: "KuCoin Spot Orders (CSV)",
+    csv: &[CsvSpec::new(&[
+        "UID",
+        "Account Type",
+        "Order ID",
+        "Order Time(UTC)",
+        "Symbol",
+        "Side",
+        "Order Type",
+        "Order Price",
+        "Order Amount",
+        "Avg. Filled Price",
+        "Filled Amount",
+        "Filled Volume",
+        "Filled Volume (USDT)",
+        "Filled Time(UTC)",
+        "Fee",
+        "Fee Currency",
+        "Tax",
+        "Status",
+    ])],
+    load_sync: Some(load_kucoin_spot_orders_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static KUCOIN_DEPOSITS_WITHDRAWALS_CSV: TransactionSource = TransactionSource {
+    id: "KuCoinDepositsWithdrawalsCsv",
+    label: "KuCoin Deposits/Withdrawals (CSV)",
+    csv: &[
+        CsvSpec::new(&[
+            "UID",
+            "Account Type",
+            "Time(UTC)",
+            "Coin",
+            "Amount",
+            "Fee",
+            "Hash",
+            "Deposit Address",
+            "Transfer Network",
+            "Status",
+            "Remarks",
+        ]),
+        CsvSpec::new(&[
+            "UID",
+            "Account Type",
+            "Time(UTC)",
+            "Coin",
+            "Amount",
+            "Fee",
+            "Hash",
+            "Withdrawal Address/Account",
+            "Transfer Network",
+            "Status",
+            "Remarks",
+        ]),
+    ],
+    load_sync: Some(load_kucoin_deposits_withdrawals_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static KUCOIN_ACCOUNT_HISTORY_CSV: TransactionSource = TransactionSource {
+    id: "KuCoinAccountHistoryCsv",
+    label: "KuCoin Earn/Staking (CSV)",
+    csv: &[CsvSpec::new(&[
+        "UID",
+        "Account Type",
+        "Currency",
+        "Side",
+        "Amount",
+        "Fee",
+        "Time(UTC)",
+        "Remark",
+        "Type",
+    ])],
+    load_sync: Some(load_kucoin_account_history_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const OPTIONS: LoadOptions = LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None };
+
+    #[test]
+    fn load_spot_orders() {
+        let path = Path::new("tests/data/kucoin_spot_orders.csv");
+        assert!(KUCOIN_SPOT_ORDERS_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_kucoin_spot_orders_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.01), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(425.5), "USDT".to_owned()),
+        });
+        assert_eq!(transactions[0].fee, Some(Amount::new(dec!(0.4255), "USDT".to_owned())));
+        assert_eq!(transactions[1].operation, Operation::Trade {
+            incoming: Amount::new(dec!(230.4), "USDT".to_owned()),
+            outgoing: Amount::new(dec!(0.1), "ETH".to_owned()),
+        });
+    }
+
+    #[test]
+    fn load_withdrawals() {
+        let path = Path::new("tests/data/kucoin_withdrawals.csv");
+        assert!(KUCOIN_DEPOSITS_WITHDRAWALS_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_kucoin_deposits_withdrawals_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 1);
+        let tx = &transactions[0];
+        assert_eq!(tx.operation, Operation::Send(Amount::new(dec!(0.0095), "BTC".to_owned())));
+        assert_eq!(tx.fee, Some(Amount::new(dec!(0.0005), "BTC".to_owned())));
+        assert_eq!(tx.tx_hash.as_deref(), Some("5d8f5c6a4e0b3f2e1a9c8b7d6e5f4a3b2c1d0e9f8a7b6c5d4e3f2a1b0c9d8e7f"));
+        assert_eq!(tx.counterparty.as_deref(), Some("bc1qm34lsc65zpw79lxes69zkqmk6ee3ewf0j77s3h"));
+        assert_eq!(tx.blockchain.as_deref(), Some("BTC"));
+    }
+
+    #[test]
+    fn load_earn_profits() {
+        let path = Path::new("tests/data/kucoin_account_history.csv");
+        assert!(KUCOIN_ACCOUNT_HISTORY_CSV.detect_from_file(path).unwrap());
+
+        // subscriptions and redemptions are skipped
+        let transactions = load_kucoin_account_history_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].operation, Operation::Staking(Amount::new(dec!(0.0123), "DOT".to_owned())));
+        assert_eq!(transactions[1].operation, Operation::Staking(Amount::new(dec!(0.42), "USDT".to_owned())));
+        assert_eq!(transactions[1].description.as_deref(), Some("Savings Interest"));
+    }
+}
@@ -0,0 +1,234 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    base::{blockchain_from_network, Amount, Operation, Transaction},
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+// CSV columns of the operations export; older versions lack the "Status" column
+const LEDGER_LIVE_HEADERS: &[&str] = &[
+    "Operation Date",
+    "Status",
+    "Currency Ticker",
+    "Operation Type",
+    "Operation Amount",
+    "Operation Fees",
+    "Operation Hash",
+    "Account Name",
+    "Account xpub",
+    "Countervalue Ticker",
+    "Countervalue at Operation Date",
+    "Countervalue at CSV Export",
+];
+
+const LEDGER_LIVE_HEADERS_WITHOUT_STATUS: &[&str] = &[
+    "Operation Date",
+    "Currency Ticker",
+    "Operation Type",
+    "Operation Amount",
+    "Operation Fees",
+    "Operation Hash",
+    "Account Name",
+    "Account xpub",
+    "Countervalue Ticker",
+    "Countervalue at Operation Date",
+    "Countervalue at CSV Export",
+];
+
+#[derive(Debug, Deserialize)]
+struct LedgerLiveOperation {
+    #[serde(rename = "Operation Date")]
+    date: DateTime<FixedOffset>,
+    #[serde(rename = "Status", default)]
+    status: Option<String>,
+    #[serde(rename = "Currency Ticker")]
+    ticker: String,
+    #[serde(rename = "Operation Type")]
+    type_: String,
+    /// For outgoing operations, the amount includes the fees.
+    #[serde(rename = "Operation Amount")]
+    amount: Decimal,
+    /// Empty for token accounts, whose fees are paid by the parent account.
+    #[serde(rename = "Operation Fees")]
+    fees: Option<Decimal>,
+    #[serde(rename = "Operation Hash")]
+    hash: String,
+    #[serde(rename = "Account Name")]
+    account_name: String,
+    #[serde(rename = "Account xpub")]
+    xpub: String,
+    #[serde(rename = "Countervalue Ticker")]
+    countervalue_ticker: String,
+    #[serde(rename = "Countervalue at Operation Date")]
+    countervalue: Option<Decimal>,
+}
+
+impl LedgerLiveOperation {
+    /// Returns the chain of the operation. Token operations use the ticker of
+    /// the token, so their chain is derived from the name of the account,
+    /// which defaults to the network name like "Polygon 1".
+    fn blockchain(&self) -> String {
+        // only token operations have no fees of their own
+        if self.fees.is_some() {
+            return self.ticker.clone();
+        }
+        let network = self.account_name.trim_end_matches(|c: char| c.is_ascii_digit() || c.is_whitespace());
+        let blockchain = blockchain_from_network(network, &self.ticker);
+        if blockchain != network {
+            blockchain
+        } else if self.xpub.starts_with("0x") {
+            // a renamed account holding tokens at an EVM address
+            "ETH".to_owned()
+        } else {
+            self.ticker.clone()
+        }
+    }
+
+    fn into_transaction(self) -> Result<Option<Transaction>> {
+        let timestamp = self.date.naive_utc();
+        let fee = self.fees
+            .filter(|fee| !fee.is_zero())
+            .map(|fee| Amount::new(fee, self.ticker.clone()));
+        let amount = Amount::new(self.amount, self.ticker.clone());
+
+        // failed operations still pay their fees
+        let failed = self.status.as_deref() == Some("Failed");
+
+        let mut tx = match self.type_.as_str() {
+            _ if failed => match fee {
+                Some(fee) => Transaction::fee(timestamp, fee),
+                None => return Ok(None),
+            },
+            "IN" => Transaction::receive(timestamp, amount),
+            "OUT" => {
+                let sent = match &fee {
+                    Some(fee) => Amount::new(self.amount - fee.quantity, self.ticker.clone()),
+                    None => amount,
+                };
+                let mut tx = Transaction::send(timestamp, sent);
+                tx.fee = fee;
+                tx
+            }
+            // operations that only cost fees, like (un)delegating or opting in to a token
+            "FEES" | "DELEGATE" | "UNDELEGATE" | "REDELEGATE" | "OPT_IN" | "OPT_OUT" => match fee {
+                Some(fee) => Transaction::fee(timestamp, fee),
+                None => return Ok(None),
+            },
+            "REWARD" | "REWARD_PAYOUT" => Transaction::new(timestamp, Operation::Staking(amount)),
+            type_ => return Err(anyhow!("unsupported operation type '{}'", type_)),
+        };
+
+        if !failed && self.countervalue_ticker == "EUR" && !matches!(tx.operation, Operation::Fee(_)) {
+            tx.value = self.countervalue.map(Amount::from_fiat);
+        }
+        tx.blockchain = Some(self.blockchain());
+        tx.tx_hash = Some(self.hash).filter(|hash| !hash.is_empty());
+        tx.description = Some(self.account_name);
+        Ok(Some(tx))
+    }
+}
+
+// loads a Ledger Live operations CSV file into a list of unified transactions
+fn load_ledger_live_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions = Vec::new();
+    for result in rdr.deserialize() {
+        let record: LedgerLiveOperation = result?;
+        if options.account.as_ref().is_some_and(|account| account != &record.account_name) {
+            continue;
+        }
+
+        let hash = record.hash.clone();
+        match record.into_transaction() {
+            Ok(Some(tx)) => transactions.push(tx),
+            Ok(None) => {}
+            Err(e) => println!("Ledger Live: skipping operation {}: {}", hash, e),
+        }
+    }
+
+    // the export lists the most recent operations first
+    transactions.sort_by_key(|tx| tx.timestamp);
+
+    Ok(transactions)
+}
+
+// lists the accounts in a Ledger Live operations CSV file, in order of appearance
+fn ledger_live_accounts(input_path: &Path) -> Result<Vec<String>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut accounts: Vec<String> = Vec::new();
+    for result in rdr.deserialize() {
+        let record: LedgerLiveOperation = result?;
+        if !accounts.contains(&record.account_name) {
+            accounts.push(record.account_name);
+        }
+    }
+
+    Ok(accounts)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static LEDGER_LIVE_CSV: TransactionSource = TransactionSource {
+    id: "LedgerLiveCsv",
+    label: "Ledger Live (CSV)",
+    csv: &[
+        CsvSpec::new(LEDGER_LIVE_HEADERS),
+        CsvSpec::new(LEDGER_LIVE_HEADERS_WITHOUT_STATUS),
+    ],
+    load_sync: Some(load_ledger_live_csv),
+    accounts: Some(ledger_live_accounts),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn load_operations() {
+        let path = Path::new("tests/data/ledger_live.csv");
+        assert!(LEDGER_LIVE_CSV.detect_from_file(path).unwrap());
+        assert_eq!(ledger_live_accounts(path).unwrap(), vec!["Ethereum 1", "Cosmos 1", "Polygon 1", "Bitcoin 1"]);
+
+        let transactions = load_ledger_live_csv(path, &LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None }).unwrap();
+        assert_eq!(transactions.len(), 7);
+
+        let receive = &transactions[0];
+        assert_eq!(receive.timestamp, parse_date_time("2024-01-10 08:30:00").unwrap());
+        assert_eq!(receive.operation, Operation::Receive(Amount::new(dec!(0.05), "BTC".to_owned())));
+        assert_eq!(receive.blockchain.as_deref(), Some("BTC"));
+        assert_eq!(receive.value, Some(Amount::from_fiat(dec!(2000.00))));
+
+        let send = &transactions[2];
+        assert_eq!(send.operation, Operation::Send(Amount::new(dec!(0.01), "BTC".to_owned())));
+        assert_eq!(send.fee, Some(Amount::new(dec!(0.00002), "BTC".to_owned())));
+        assert_eq!(send.tx_hash.as_deref(), Some("a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d"));
+
+        // token operations are on the chain of their parent account
+        assert_eq!(transactions[1].operation, Operation::Receive(Amount::new(dec!(100), "USDT".to_owned())));
+        assert_eq!(transactions[1].blockchain.as_deref(), Some("ETH"));
+        assert_eq!(transactions[3].operation, Operation::Receive(Amount::new(dec!(50), "USDC".to_owned())));
+        assert_eq!(transactions[3].blockchain.as_deref(), Some("POL"));
+
+        assert_eq!(transactions[4].operation, Operation::Fee(Amount::new(dec!(0.005), "ATOM".to_owned())));
+        assert_eq!(transactions[4].blockchain.as_deref(), Some("ATOM"));
+        assert_eq!(transactions[5].operation, Operation::Staking(Amount::new(dec!(0.12), "ATOM".to_owned())));
+        assert_eq!(transactions[6].operation, Operation::Fee(Amount::new(dec!(0.00042), "ETH".to_owned())));
+
+        let options = LoadOptions { time_zone: chrono_tz::UTC, account: Some("Cosmos 1".to_owned()), custom_csv: None };
+        let transactions = load_ledger_live_csv(path, &options).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions.iter().all(|tx| tx.description.as_deref() == Some("Cosmos 1")));
+    }
+}
@@ -5,7 +5,7 @@ use chrono::NaiveDateTime;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Deserializer};
 
-use crate::{base::{Transaction, Amount, deserialize_amount}, CsvSpec, TransactionSource};
+use crate::{base::{Transaction, Amount, deserialize_amount}, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 // function for reading NaiveDateTime in the format "07/04/2019 07:48:17"
@@ -160,7 +160,7 @@ impl<'a> From<LiquidSpotTrade<'a>> for Transaction {
     }
 }
 
-fn load_liquid_deposits_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_liquid_deposits_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -172,7 +172,7 @@ fn load_liquid_deposits_csv(input_path: &Path) -> Result<Vec<Transaction>> {
     Ok(transactions)
 }
 
-fn load_liquid_withdrawals_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_liquid_withdrawals_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -196,9 +196,8 @@ static LIQUID_DEPOSITS_CSV: TransactionSource = TransactionSource {
         "Created (YY/MM/DD)",
         "Hash",
     ])],
-    detect: None,
     load_sync: Some(load_liquid_deposits_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -224,9 +223,8 @@ static LIQUID_TRADES_CSV: TransactionSource = TransactionSource {
         skip_lines: 2,
         trim: csv::Trim::None,
     }],
-    detect: None,
     load_sync: Some(load_liquid_trades_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -246,12 +244,11 @@ static LIQUID_WITHDRAWALS_CSV: TransactionSource = TransactionSource {
         "Broadcasted At",
         "Hash",
     ])],
-    detect: None,
     load_sync: Some(load_liquid_withdrawals_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
-fn load_liquid_trades_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_liquid_trades_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new()
         .has_headers(false)
         .flexible(true)
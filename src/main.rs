@@ -2,44 +2,72 @@
 
 mod alby;
 mod alby_hub;
+mod backend;
 mod base;
+mod bip329;
 mod binance;
 mod bison;
 mod blink;
+mod bitcoin_cache;
 mod bitcoin_core;
+mod bitcoin_core_rpc;
 mod bitcoin_de;
 mod bitonic;
+mod bitpanda;
 mod bitstamp;
 mod bittrex;
+mod bitvavo;
+mod bybit;
+mod coinbase;
 mod coinmarketcap;
 mod coinpanda;
+mod cointracking;
 mod ctc;
+mod currency_aliases;
+mod custom_csv;
+mod duplicates;
 mod electrum;
+mod electrum_server;
 mod esplora;
 mod etherscan;
 mod fifo;
 mod ftx;
 mod horizon;
+mod koinly;
 mod kraken;
+mod kucoin;
+mod ledger_live;
 mod liquid;
 mod mycelium;
+mod okx;
 mod phoenix;
 mod poloniex;
 mod price_history;
+mod query;
+mod rules;
+mod solana;
+mod sparrow;
 mod time;
 mod trezor;
 mod wallet_of_satoshi;
 mod wave_space;
+mod xrpl;
 
 use anyhow::{anyhow, Context, Result};
+use backend::{BackendSettings, Backends};
 use coinmarketcap::CmcInterval;
-use base::{cmc_id, Amount, Operation, Transaction};
-use chrono::{Datelike, Duration, Local, TimeZone, Utc};
+use currency_aliases::{CurrencyAlias, apply_currency_aliases};
+use custom_csv::CustomCsvMapping;
+use base::{cmc_id, Amount, Operation, OperationType, Transaction, TransactionRef};
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
 use directories::ProjectDirs;
+use duplicates::DuplicateReason;
 use fifo::{CapitalGain, CostBasisTracking, FIFO};
 use raccoin_ui::*;
 use price_history::{PriceHistory, PriceRequirements, split_ranges};
-use regex::{Regex, RegexBuilder};
+use query::{parse_query, replace_term, Query, Term};
+use rules::{CompiledRule, Rule, RuleAction, RuleConditions};
 use rust_decimal::{Decimal, RoundingStrategy};
 use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
@@ -49,7 +77,7 @@ use linkme::distributed_slice;
 use std::{
     cell::RefCell,
     cmp::{Eq, Ordering},
-    collections::HashMap,
+    collections::{BTreeMap, HashMap, HashSet},
     default::Default,
     env,
     ffi::OsString,
@@ -60,13 +88,16 @@ use std::{
     path::{Path, PathBuf},
     pin::Pin,
     rc::Rc,
+    str::FromStr,
 };
+use strum::VariantArray;
 
 fn rounded_to_cent(amount: Decimal) -> Decimal {
     amount.round_dp_with_strategy(2, RoundingStrategy::MidpointAwayFromZero)
 }
 
 pub(crate) type LoadFuture = Pin<Box<dyn Future<Output = Result<Vec<Transaction>>> + Send>>;
+pub(crate) type ListAccounts = fn(&Path) -> Result<Vec<String>>;
 
 pub(crate) struct CsvSpec {
     pub(crate) headers: &'static [&'static str],
@@ -91,11 +122,68 @@ pub(crate) struct TransactionSource {
     pub(crate) label: &'static str,
     pub(crate) csv: &'static [CsvSpec],
     pub(crate) detect: Option<fn(&Path) -> Result<bool>>,
-    pub(crate) load_sync: Option<fn(&Path) -> Result<Vec<Transaction>>>,
-    pub(crate) load_async: Option<fn(String) -> LoadFuture>,
+    pub(crate) load_sync: Option<fn(&Path, &LoadOptions) -> Result<Vec<Transaction>>>,
+    pub(crate) load_async: Option<fn(String, SyncOptions) -> LoadFuture>,
+    /// Whether the timestamps in the source are in local time, which is
+    /// interpreted in the time zone configured for the wallet source.
+    pub(crate) local_time: bool,
+    /// Lists the accounts in a file that exports several accounts, which
+    /// allows splitting it into one wallet per account.
+    pub(crate) accounts: Option<ListAccounts>,
+    /// The backends this source can be synced with, the first one being
+    /// the default.
+    pub(crate) backends: &'static [&'static str],
+    /// For sources deriving addresses from keys, the default number of
+    /// consecutive unused addresses after which scanning stops.
+    pub(crate) gap_limit: Option<u32>,
+    /// The chains this source can be synced from, of which any can be
+    /// selected. The first one is selected by default.
+    pub(crate) chains: &'static [&'static str],
+}
+
+/// Settings of a wallet source that affect how it is loaded.
+pub(crate) struct LoadOptions {
+    /// The time zone of local timestamps in the source.
+    pub(crate) time_zone: Tz,
+    /// For sources exporting several accounts, the only account to load.
+    pub(crate) account: Option<String>,
+    /// For custom CSV sources, how to read the columns.
+    pub(crate) custom_csv: Option<CustomCsvMapping>,
+}
+
+/// Settings of a portfolio that affect how wallet sources are synchronized.
+#[derive(Clone, Default)]
+pub(crate) struct SyncOptions {
+    /// The blockchain APIs to use.
+    pub(crate) backends: Backends,
+    /// For sources supporting several backends, the one chosen for the source.
+    pub(crate) backend: Option<String>,
+    /// For sources deriving addresses from keys, the gap limit chosen for
+    /// the source.
+    pub(crate) gap_limit: Option<u32>,
+    /// Where sources may cache data between syncs.
+    pub(crate) cache_dir: Option<PathBuf>,
+    /// For sources available on several chains, the chains to sync from.
+    pub(crate) chains: Vec<String>,
 }
 
 impl TransactionSource {
+    /// A source without any capabilities, for filling in the fields a
+    /// source doesn't use.
+    pub(crate) const DEFAULT: TransactionSource = TransactionSource {
+        id: "",
+        label: "",
+        csv: &[],
+        detect: None,
+        load_sync: None,
+        load_async: None,
+        local_time: false,
+        accounts: None,
+        backends: &[],
+        gap_limit: None,
+        chains: &[],
+    };
+
     pub(crate) fn detect_from_file(&self, path: &Path) -> Result<bool> {
         if let Some(detect) = self.detect {
             return detect(path);
@@ -176,6 +264,60 @@ struct WalletSource {
     /// Transactions from this source. Only used for on-demand synchronized sources.
     #[serde(skip_serializing_if = "Vec::is_empty", default)]
     transactions: Vec<Transaction>,
+    /// The IANA time zone of local timestamps in this source, for sources
+    /// using local time. Defaults to [`DEFAULT_TIME_ZONE`].
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    time_zone: Option<String>,
+    /// For sources exporting several accounts, the only account loaded
+    /// from the source.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    account: Option<String>,
+    /// For custom CSV sources, the name of the column mapping in the portfolio.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    custom_csv: Option<String>,
+    /// For sources supporting several backends, the one used for syncing.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    backend: Option<String>,
+    /// For sources deriving addresses from keys, overrides the default gap
+    /// limit of the source type.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    gap_limit: Option<u32>,
+    /// For sources available on several chains, the selected chains. When
+    /// empty, the default chain of the source type is used.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    chains: Vec<String>,
+}
+
+/// The time zone assumed for sources using local time, when none is set.
+const DEFAULT_TIME_ZONE: Tz = Tz::Europe__Berlin;
+
+impl WalletSource {
+    fn load_options(&self, custom_csv_mappings: &[CustomCsvMapping]) -> LoadOptions {
+        let time_zone = match self.time_zone.as_deref().map(Tz::from_str) {
+            Some(Ok(time_zone)) => time_zone,
+            Some(Err(e)) => {
+                println!("warning: invalid time zone for source {}: {}", self.path, e);
+                DEFAULT_TIME_ZONE
+            }
+            None => DEFAULT_TIME_ZONE,
+        };
+        let custom_csv = self.custom_csv.as_ref().and_then(|name| {
+            custom_csv_mappings.iter().find(|mapping| &mapping.name == name).cloned()
+        });
+        LoadOptions { time_zone, account: self.account.clone(), custom_csv }
+    }
+
+    /// Returns the chains to sync from, defaulting to the first chain of the
+    /// source type.
+    fn selected_chains(&self) -> Vec<String> {
+        if !self.chains.is_empty() {
+            return self.chains.clone();
+        }
+        transaction_source_by_id(&self.source_type)
+            .and_then(|definition| definition.chains.first())
+            .map(|&chain| vec![chain.to_owned()])
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -216,6 +358,47 @@ struct AppState {
     last_export_directory: Option<PathBuf>,
 }
 
+/// A send and a receive transaction that were manually marked as being (or
+/// not being) two sides of the same transfer.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct TransferLink {
+    send: TransactionRef,
+    receive: TransactionRef,
+}
+
+/// Which of two duplicate transactions should be kept.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+enum DuplicateResolution {
+    First,
+    Second,
+    Both,
+}
+
+impl DuplicateResolution {
+    fn swapped(self) -> Self {
+        match self {
+            DuplicateResolution::First => DuplicateResolution::Second,
+            DuplicateResolution::Second => DuplicateResolution::First,
+            DuplicateResolution::Both => DuplicateResolution::Both,
+        }
+    }
+}
+
+/// The choice made for a pair of duplicate transactions.
+#[derive(Serialize, Deserialize, Clone)]
+struct ResolvedDuplicate {
+    first: TransactionRef,
+    second: TransactionRef,
+    resolution: DuplicateResolution,
+}
+
+/// A named transaction query.
+#[derive(Serialize, Deserialize, Clone)]
+struct SavedSearch {
+    name: String,
+    query: String,
+}
+
 #[derive(Serialize, Deserialize, Default)]
 struct Portfolio {
     #[serde(default)]
@@ -226,6 +409,160 @@ struct Portfolio {
     merge_consecutive_trades: bool,
     #[serde(default)]
     cost_basis_tracking: CostBasisTracking,
+    /// Send/receive pairs that are always matched as a transfer.
+    #[serde(default)]
+    linked_transfers: Vec<TransferLink>,
+    /// Send/receive pairs that are never matched as a transfer.
+    #[serde(default)]
+    unlinked_transfers: Vec<TransferLink>,
+    /// Choices made for duplicate transactions found between sources.
+    #[serde(default)]
+    resolved_duplicates: Vec<ResolvedDuplicate>,
+    /// Currency aliases applied to the transactions of all sources.
+    #[serde(default)]
+    currency_aliases: Vec<CurrencyAlias>,
+    /// Rules applied, in order, to the transactions of all sources.
+    #[serde(default)]
+    rules: Vec<Rule>,
+    #[serde(default)]
+    saved_searches: Vec<SavedSearch>,
+    /// Transaction labels imported from BIP-329 files, by transaction ID.
+    #[serde(default)]
+    transaction_labels: BTreeMap<String, String>,
+    /// Column mappings used by custom CSV sources.
+    #[serde(default)]
+    custom_csv_mappings: Vec<CustomCsvMapping>,
+    /// Settings for the blockchain APIs used to sync sources.
+    #[serde(default)]
+    backends: Backends,
+}
+
+impl Portfolio {
+    fn duplicate_resolution(&self, first: &TransactionRef, second: &TransactionRef) -> Option<DuplicateResolution> {
+        self.resolved_duplicates.iter().find_map(|resolved| {
+            if &resolved.first == first && &resolved.second == second {
+                Some(resolved.resolution)
+            } else if &resolved.first == second && &resolved.second == first {
+                Some(resolved.resolution.swapped())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the saved references to transactions, which refer to the
+    /// wallet a transaction was loaded from by name.
+    fn transaction_refs_mut(&mut self) -> impl Iterator<Item = &mut TransactionRef> {
+        self.linked_transfers.iter_mut()
+            .chain(self.unlinked_transfers.iter_mut())
+            .flat_map(|link| [&mut link.send, &mut link.receive])
+            .chain(self.resolved_duplicates.iter_mut().flat_map(|resolved| [&mut resolved.first, &mut resolved.second]))
+    }
+
+    /// Returns the given wallet name, with a number appended when another
+    /// wallet already uses it.
+    fn unique_wallet_name(&self, name: &str) -> String {
+        let is_used = |name: &str| self.wallets.iter().any(|wallet| wallet.name == name);
+        if !is_used(name) {
+            return name.to_owned();
+        }
+        (2..).map(|number| format!("{} ({})", name, number)).find(|name| !is_used(name)).unwrap()
+    }
+
+    /// Replaces a source by a new wallet for each of the given accounts. The
+    /// wallet of the source is removed when it has no other sources left.
+    ///
+    /// Saved links and duplicate resolutions are moved to the wallet of the
+    /// account their transaction was loaded from.
+    fn split_source_accounts(&mut self, wallet_index: usize, source_index: usize, accounts: Vec<String>) -> Result<()> {
+        let source = &self.wallets[wallet_index].sources[source_index];
+        let load_sync = transaction_source_by_id(&source.source_type)
+            .and_then(|definition| definition.load_sync)
+            .context("source does not support accounts")?;
+
+        // load the transactions of each account, to find out which account the
+        // saved references belong to
+        let mut account_transactions = Vec::new();
+        for account in &accounts {
+            let mut options = source.load_options(&self.custom_csv_mappings);
+            options.account = Some(account.clone());
+            let mut transactions = load_sync(&source.full_path, &options)?;
+            apply_currency_aliases(&self.currency_aliases, &mut transactions);
+            account_transactions.push(transactions);
+        }
+
+        let wallet = &mut self.wallets[wallet_index];
+        let wallet_name = wallet.name.clone();
+        let source = wallet.sources.remove(source_index);
+        if wallet.sources.is_empty() {
+            self.wallets.remove(wallet_index);
+        }
+
+        let mut wallet_names = Vec::new();
+        for account in accounts {
+            let mut wallet = Wallet::new(self.unique_wallet_name(&account));
+            wallet.sources.push(WalletSource {
+                source_type: source.source_type.clone(),
+                path: source.path.clone(),
+                name: source.name.clone(),
+                enabled: source.enabled,
+                full_path: source.full_path.clone(),
+                transaction_count: 0,
+                transactions: Vec::new(),
+                time_zone: source.time_zone.clone(),
+                account: Some(account),
+                custom_csv: source.custom_csv.clone(),
+                backend: source.backend.clone(),
+                gap_limit: source.gap_limit,
+                chains: source.chains.clone(),
+            });
+            wallet_names.push(wallet.name.clone());
+            self.wallets.push(wallet);
+        }
+
+        // The operation of a reference may have been changed since loading,
+        // for example by merging trades, so only its kind is compared.
+        let is_same_tx = |tx_ref: &TransactionRef, tx: &Transaction| {
+            tx_ref.timestamp == tx.timestamp &&
+                tx_ref.tx_hash == tx.tx_hash &&
+                std::mem::discriminant(&tx_ref.operation) == std::mem::discriminant(&tx.operation)
+        };
+        for tx_ref in self.transaction_refs_mut().filter(|tx_ref| tx_ref.wallet == wallet_name) {
+            let account_index = account_transactions.iter()
+                .position(|transactions| transactions.iter().any(|tx| is_same_tx(tx_ref, tx)));
+            if let Some(account_index) = account_index {
+                tx_ref.wallet = wallet_names[account_index].clone();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Two transactions from different sources of the same wallet, which appear
+/// to be the same transaction.
+struct Duplicate {
+    transactions: [Transaction; 2],
+    refs: [TransactionRef; 2],
+    sources: [String; 2],
+    reason: DuplicateReason,
+    resolution: Option<DuplicateResolution>,
+}
+
+#[derive(Default)]
+struct LoadedTransactions {
+    transactions: Vec<Transaction>,
+    /// Stable references to the transactions, by transaction index.
+    transaction_refs: Vec<TransactionRef>,
+    /// Whether the transactions were a send or receive after applying the
+    /// rules, which can be linked to another one, by transaction index.
+    transfer_candidates: Vec<bool>,
+    duplicates: Vec<Duplicate>,
+    /// For each rule, the transactions it affected.
+    rule_matches: Vec<Vec<Transaction>>,
+    /// The transactions after applying the rules, with the type of their
+    /// source, for previewing the matches of a new rule.
+    rule_candidates: Vec<(String, Transaction)>,
 }
 
 #[derive(Default, Clone)]
@@ -295,49 +632,29 @@ impl TaxReport {
     }
 }
 
-enum TransactionFilter {
-    WalletIndex(usize),
-    Currency(String),
-    Text(Regex),
-    HasGainError,
-}
-
-impl TransactionFilter {
-    fn matches(&self, tx: &Transaction) -> bool {
-        match self {
-            TransactionFilter::WalletIndex(index) => tx.wallet_index == *index,
-            TransactionFilter::Currency(currency) => match tx.incoming_outgoing() {
-                (None, None) => false,
-                (None, Some(amount)) |
-                (Some(amount), None) => {
-                    amount.effective_currency().as_ref() == currency
-                }
-                (Some(incoming), Some(outgoing)) => {
-                    incoming.effective_currency().as_ref() == currency ||
-                    outgoing.effective_currency().as_ref() == currency
-                }
-            }
-            TransactionFilter::Text(text) => {
-                tx.description.as_deref().is_some_and(|description| text.is_match(description)) ||
-                    tx.tx_hash.as_deref().is_some_and(|tx_hash| text.is_match(tx_hash))
-            }
-            TransactionFilter::HasGainError => {
-                tx.gain.as_ref().is_some_and(|gain| gain.is_err())
-            }
-        }
-    }
-}
-
 struct App {
     project_dirs: Option<ProjectDirs>,
     state: AppState,
     portfolio: Portfolio,
     transactions: Vec<Transaction>,
+    /// Stable references to the transactions, by transaction index.
+    transaction_refs: Vec<TransactionRef>,
+    /// Whether the transactions can be linked as part of a transfer, by
+    /// transaction index.
+    transfer_candidates: Vec<bool>,
+    duplicates: Vec<Duplicate>,
+    /// For each rule, the transactions it affected.
+    rule_matches: Vec<Vec<Transaction>>,
+    /// The transactions a new rule would be applied to, with their source type.
+    rule_candidates: Vec<(String, Transaction)>,
     reports: Vec<TaxReport>,
     price_history: PriceHistory,
+    /// The wallet and file for which a custom CSV source is being set up,
+    /// and the name of the saved mapping it started from, which is edited.
+    pending_custom_csv: Option<(usize, PathBuf, Option<String>)>,
     stop_update_price_history: bool,
 
-    transaction_filters: Vec<TransactionFilter>,
+    transaction_query: Query,
 
     ui_weak: slint::Weak<AppWindow>,
     ui_wallets: Rc<VecModel<UiWallet>>,
@@ -369,11 +686,17 @@ impl App {
             state,
             portfolio: Portfolio::default(),
             transactions: Vec::new(),
+            transaction_refs: Vec::new(),
+            transfer_candidates: Vec::new(),
+            duplicates: Vec::new(),
+            rule_matches: Vec::new(),
+            rule_candidates: Vec::new(),
             reports: Vec::new(),
             price_history,
+            pending_custom_csv: None,
             stop_update_price_history: false,
 
-            transaction_filters: Vec::default(),
+            transaction_query: Query::default(),
 
             ui_weak: slint::Weak::default(),
             ui_wallets: Rc::new(Default::default()),
@@ -442,7 +765,13 @@ impl App {
     }
 
     fn refresh_transactions(&mut self) {
-        self.transactions = load_transactions(&mut self.portfolio).unwrap_or_default();
+        let loaded = load_transactions(&mut self.portfolio).unwrap_or_default();
+        self.transactions = loaded.transactions;
+        self.transaction_refs = loaded.transaction_refs;
+        self.transfer_candidates = loaded.transfer_candidates;
+        self.duplicates = loaded.duplicates;
+        self.rule_matches = loaded.rule_matches;
+        self.rule_candidates = loaded.rule_candidates;
         estimate_transaction_values(&mut self.transactions, &self.price_history);
         self.reports = calculate_tax_reports(&mut self.transactions, self.portfolio.cost_basis_tracking);
     }
@@ -463,6 +792,63 @@ impl App {
         }
     }
 
+    /// Marks the given send and receive transactions (in any order) as being
+    /// two sides of the same transfer.
+    fn link_transfer(&mut self, first_index: usize, second_index: usize) -> Result<()> {
+        let first = self.transaction_refs.get(first_index).context("invalid transaction")?;
+        let second = self.transaction_refs.get(second_index).context("invalid transaction")?;
+        if !self.transfer_candidates[first_index] || !self.transfer_candidates[second_index] {
+            return Err(anyhow!("a transfer consists of a send and a receive transaction"));
+        }
+
+        // The operations after applying the rules decide whether the
+        // transactions form a transfer, where unmatched sends and receives
+        // have become sells and buys.
+        let link = match (&self.transactions[first_index].operation, &self.transactions[second_index].operation) {
+            (Operation::Send(sent) | Operation::Sell(sent), Operation::Receive(received) | Operation::Buy(received)) |
+            (Operation::Receive(received) | Operation::Buy(received), Operation::Send(sent) | Operation::Sell(sent)) => {
+                if sent.effective_currency() != received.effective_currency() {
+                    return Err(anyhow!("currencies don't match ({} and {})", sent.effective_currency(), received.effective_currency()));
+                }
+                if matches!(self.transactions[first_index].operation, Operation::Send(_) | Operation::Sell(_)) {
+                    TransferLink { send: first.clone(), receive: second.clone() }
+                } else {
+                    TransferLink { send: second.clone(), receive: first.clone() }
+                }
+            }
+            _ => return Err(anyhow!("a transfer consists of a send and a receive transaction")),
+        };
+
+        // drop any previous links involving either transaction
+        self.portfolio.linked_transfers.retain(|l| l.send != link.send && l.receive != link.receive);
+
+        self.portfolio.unlinked_transfers.retain(|l| l != &link);
+        self.portfolio.linked_transfers.push(link);
+        Ok(())
+    }
+
+    /// Makes sure the transfer the given transaction is part of is no longer
+    /// matched, including by the automatic matching.
+    fn unlink_transfer(&mut self, index: usize) -> Result<()> {
+        let tx = self.transactions.get(index).context("invalid transaction")?;
+        let matching_index = tx.matching_tx.context("transaction is not part of a transfer")?;
+        let (send_index, receive_index) = if tx.operation.is_send() {
+            (index, matching_index)
+        } else {
+            (matching_index, index)
+        };
+        let link = TransferLink {
+            send: self.transaction_refs[send_index].clone(),
+            receive: self.transaction_refs[receive_index].clone(),
+        };
+
+        self.portfolio.linked_transfers.retain(|l| l != &link);
+        if !self.portfolio.unlinked_transfers.contains(&link) {
+            self.portfolio.unlinked_transfers.push(link);
+        }
+        Ok(())
+    }
+
     fn report_info(&self, message: &str) {
         self.push_notification(UiNotificationType::Info, message);
     }
@@ -484,8 +870,13 @@ impl App {
     fn refresh_ui(&self) {
         ui_set_wallets(self);
         ui_set_transactions(self);
+        ui_set_duplicates(self);
         ui_set_reports(self);
         ui_set_portfolio(self);
+        ui_set_currency_aliases(self);
+        ui_set_backends(self);
+        ui_set_rules(self);
+        ui_set_saved_searches(self);
     }
 
     fn save_state(&self) -> Result<()> {
@@ -601,14 +992,20 @@ pub(crate) fn export_all_to(app: &App, output_path: &Path) -> Result<()> {
     Ok(())
 }
 
-fn load_transactions(portfolio: &mut Portfolio) -> Result<Vec<Transaction>> {
-    let (wallets, ignored_currencies) = (&mut portfolio.wallets, &portfolio.ignored_currencies);
+fn load_transactions(portfolio: &mut Portfolio) -> Result<LoadedTransactions> {
     let mut transactions = Vec::new();
-
-    for (wallet_index, wallet) in wallets.iter_mut().enumerate() {
+    let mut transaction_refs = Vec::new();
+    let mut duplicates = Vec::new();
+    let rules: Vec<CompiledRule> = portfolio.rules.iter().map(CompiledRule::new).collect();
+    let mut rule_matches = vec![Vec::new(); rules.len()];
+    let mut rule_candidates = Vec::new();
+
+    for wallet_index in 0..portfolio.wallets.len() {
+        let (wallet, ignored_currencies, currency_aliases, transaction_labels, custom_csv_mappings) = (&mut portfolio.wallets[wallet_index], &portfolio.ignored_currencies, &portfolio.currency_aliases, &portfolio.transaction_labels, &portfolio.custom_csv_mappings);
         let mut wallet_transactions = Vec::new();
+        let mut wallet_transaction_sources = Vec::new();
 
-        for source in wallet.sources.iter_mut() {
+        for (source_index, source) in wallet.sources.iter_mut().enumerate() {
             if !source.enabled || !wallet.enabled {
                 source.transaction_count = 0;
                 continue
@@ -624,13 +1021,16 @@ fn load_transactions(portfolio: &mut Portfolio) -> Result<Vec<Transaction>> {
             };
 
             let source_txs = if let Some(load_sync) = source_definition.load_sync {
-                load_sync(&source.full_path)
+                load_sync(&source.full_path, &source.load_options(custom_csv_mappings))
             } else {
                 anyhow::Ok(source.transactions.clone())
             };
 
             match source_txs {
                 Ok(mut source_transactions) => {
+                    apply_currency_aliases(currency_aliases, &mut source_transactions);
+                    bip329::apply_labels(transaction_labels, &mut source_transactions);
+
                     // sort transactions
                     source_transactions.sort_by(|a, b| a.cmp(b));
 
@@ -660,7 +1060,7 @@ fn load_transactions(portfolio: &mut Portfolio) -> Result<Vec<Transaction>> {
                         };
 
                         retain_tx || tx.fee.take().is_some_and(|fee| {
-                            if !is_ignored(&fee.currency) {
+                            if !is_ignored(&fee.effective_currency()) {
                                 // We can't ignore the fee, so keep the transaction just for the fee
                                 tx.operation = Operation::Fee(fee);
                                 tx.value = tx.fee_value.take();
@@ -672,7 +1072,11 @@ fn load_transactions(portfolio: &mut Portfolio) -> Result<Vec<Transaction>> {
                         })
                     });
 
-                    source.transaction_count = source_transactions.len();
+                    for tx in &mut source_transactions {
+                        tx.wallet_index = wallet_index;
+                    }
+
+                    wallet_transaction_sources.extend(std::iter::repeat_n(source_index, source_transactions.len()));
                     wallet_transactions.extend(source_transactions);
                 }
                 // todo: provide this feedback to the UI
@@ -683,16 +1087,71 @@ fn load_transactions(portfolio: &mut Portfolio) -> Result<Vec<Transaction>> {
             }
         }
 
-        for tx in &mut wallet_transactions {
-            tx.wallet_index = wallet_index;
+        // detect transactions that were loaded from multiple sources
+        let mut dropped = vec![false; wallet_transactions.len()];
+        let wallet = &portfolio.wallets[wallet_index];
+        for (a, b, reason) in duplicates::find_duplicates(&wallet_transactions, &wallet_transaction_sources) {
+            let refs = [
+                TransactionRef::new(&wallet_transactions[a], &wallet.name),
+                TransactionRef::new(&wallet_transactions[b], &wallet.name),
+            ];
+            let resolution = portfolio.duplicate_resolution(&refs[0], &refs[1]);
+            match resolution {
+                Some(DuplicateResolution::First) => dropped[b] = true,
+                Some(DuplicateResolution::Second) => dropped[a] = true,
+                Some(DuplicateResolution::Both) => {}
+                None => println!("Duplicate transaction detected: {:?}", wallet_transactions[b]),
+            }
+
+            let source_name = |index: usize| {
+                let source = &wallet.sources[wallet_transaction_sources[index]];
+                if source.name.is_empty() { source.path.clone() } else { source.name.clone() }
+            };
+            duplicates.push(Duplicate {
+                transactions: [wallet_transactions[a].clone(), wallet_transactions[b].clone()],
+                refs,
+                sources: [source_name(a), source_name(b)],
+                reason,
+                resolution,
+            });
         }
 
+        // Apply the rules to the remaining transactions. The references are
+        // taken before, since a rule may change the operation while saved
+        // links and duplicate resolutions refer to the loaded one.
+        let mut index = 0;
+        let mut source_transaction_counts = vec![0; wallet.sources.len()];
+        wallet_transactions.retain_mut(|tx| {
+            let (source_index, is_dropped) = (wallet_transaction_sources[index], dropped[index]);
+            index += 1;
+            if is_dropped {
+                return false;
+            }
+
+            let tx_ref = TransactionRef::new(tx, &wallet.name);
+            let source_type = &wallet.sources[source_index].source_type;
+            let retain_tx = rules::apply_rules(&rules, source_type, &wallet.name, tx, &mut rule_matches);
+            if retain_tx {
+                transaction_refs.push(tx_ref);
+                rule_candidates.push((source_type.clone(), tx.clone()));
+                source_transaction_counts[source_index] += 1;
+            }
+            retain_tx
+        });
+
+        // the counts only include the transactions that remain
+        let wallet = &mut portfolio.wallets[wallet_index];
+        for (source, count) in wallet.sources.iter_mut().zip(source_transaction_counts) {
+            source.transaction_count = count;
+        }
         wallet.balances = calculate_balances(&wallet_transactions);
         transactions.extend(wallet_transactions);
     }
 
-    // sort transactions
-    transactions.sort_by(|a, b| a.cmp(b));
+    // sort transactions, keeping their references in the same order
+    let mut loaded: Vec<(Transaction, TransactionRef)> = transactions.into_iter().zip(transaction_refs).collect();
+    loaded.sort_by(|a, b| a.0.cmp(&b.0));
+    let (mut transactions, transaction_refs): (Vec<Transaction>, Vec<TransactionRef>) = loaded.into_iter().unzip();
 
     // assign transaction indices
     for (index, tx) in transactions.iter_mut().enumerate() {
@@ -708,9 +1167,28 @@ fn load_transactions(portfolio: &mut Portfolio) -> Result<Vec<Transaction>> {
         last = Some(tx);
     }
 
-    match_send_receive(&mut transactions);
+    let find_pair = |link: &TransferLink| {
+        let send_index = transaction_refs.iter().position(|tx_ref| tx_ref == &link.send);
+        let receive_index = transaction_refs.iter().position(|tx_ref| tx_ref == &link.receive);
+        match (send_index, receive_index) {
+            (Some(send_index), Some(receive_index)) => Some((send_index, receive_index)),
+            _ => {
+                println!("warning: transfer link refers to a missing transaction: {:?} -> {:?}", link.send, link.receive);
+                None
+            }
+        }
+    };
+    let linked_pairs: Vec<(usize, usize)> = portfolio.linked_transfers.iter().filter_map(find_pair).collect();
+    let unlinked_pairs: HashSet<(usize, usize)> = portfolio.unlinked_transfers.iter().filter_map(find_pair).collect();
+
+    // unmatched sends and receives are turned into sells and buys below
+    let transfer_candidates = transactions.iter()
+        .map(|tx| matches!(tx.operation, Operation::Send(_) | Operation::Receive(_)))
+        .collect();
+
+    match_send_receive(&mut transactions, &linked_pairs, &unlinked_pairs);
 
-    Ok(transactions)
+    Ok(LoadedTransactions { transactions, transaction_refs, transfer_candidates, duplicates, rule_matches, rule_candidates })
 }
 
 fn merge_consecutive_trades(transactions: &mut Vec<Transaction>) {
@@ -751,17 +1229,17 @@ fn calculate_balances(transactions: &[Transaction]) -> HashMap<String, Decimal>
         let (incoming, outgoing) = tx.incoming_outgoing();
         if let Some(incoming) = incoming {
             if !incoming.is_fiat() {
-                *get_or_default(&mut balances, &incoming.currency) += incoming.quantity;
+                *get_or_default(&mut balances, &incoming.effective_currency().into_owned()) += incoming.quantity;
             }
         }
         if let Some(outgoing) = outgoing {
             if !outgoing.is_fiat() {
-                *get_or_default(&mut balances, &outgoing.currency) -= outgoing.quantity;
+                *get_or_default(&mut balances, &outgoing.effective_currency().into_owned()) -= outgoing.quantity;
             }
         }
         if let Some(fee) = &tx.fee {
             if !fee.is_fiat() {
-                *get_or_default(&mut balances, &fee.currency) -= fee.quantity;
+                *get_or_default(&mut balances, &fee.effective_currency().into_owned()) -= fee.quantity;
             }
         }
     }
@@ -769,14 +1247,66 @@ fn calculate_balances(transactions: &[Transaction]) -> HashMap<String, Decimal>
     balances
 }
 
-fn match_send_receive(transactions: &mut Vec<Transaction>) {
+fn match_send_receive(transactions: &mut Vec<Transaction>, linked_pairs: &[(usize, usize)], unlinked_pairs: &HashSet<(usize, usize)>) {
     // before applying FIFO, turn any unmatched Send transactions into Sell transactions
     // and unmatched Receive transactions into Buy transactions
     let mut unmatched_sends_receives = Vec::new();
     let mut matching_pairs = Vec::new();
+    let mut linked_indices = HashSet::new();
+
+    // manually linked transfers take precedence over automatic matching
+    for &(send_index, receive_index) in linked_pairs {
+        let send_tx = &transactions[send_index];
+        let receive_tx = &transactions[receive_index];
+        match (&send_tx.operation, &receive_tx.operation) {
+            (Operation::Send(sent), Operation::Receive(received)) if sent.effective_currency() == received.effective_currency() => {
+                if linked_indices.contains(&send_index) || linked_indices.contains(&receive_index) {
+                    println!("warning: ignoring transfer link, since one of its transactions is already linked: {:?} -> {:?}", send_tx, receive_tx);
+                    continue;
+                }
+                linked_indices.insert(send_index);
+                linked_indices.insert(receive_index);
+                matching_pairs.push((send_index, receive_index));
+            }
+            _ => {
+                println!("warning: ignoring invalid transfer link: {:?} -> {:?}", send_tx, receive_tx);
+            }
+        }
+    }
+
+    // a Stellar claimable balance is matched to its claim by ID, since they
+    // have different transaction hashes and may be far apart in time
+    let mut claimable_balance_sends = HashMap::new();
+    for (index, tx) in transactions.iter().enumerate() {
+        if tx.operation.is_send() && !linked_indices.contains(&index) {
+            if let Some(balance_id) = horizon::claimable_balance_id(tx) {
+                claimable_balance_sends.insert(balance_id, index);
+            }
+        }
+    }
+    for (receive_index, receive_tx) in transactions.iter().enumerate() {
+        let (Operation::Receive(received), Some(balance_id)) = (&receive_tx.operation, horizon::claimable_balance_id(receive_tx)) else {
+            continue;
+        };
+        let Some(&send_index) = claimable_balance_sends.get(balance_id) else {
+            continue;
+        };
+        let Operation::Send(sent) = &transactions[send_index].operation else {
+            continue;
+        };
+        if linked_indices.contains(&send_index) || linked_indices.contains(&receive_index) ||
+            unlinked_pairs.contains(&(send_index, receive_index)) ||
+            sent.effective_currency() != received.effective_currency() {
+            continue;
+        }
+        linked_indices.insert(send_index);
+        linked_indices.insert(receive_index);
+        matching_pairs.push((send_index, receive_index));
+    }
 
     for (index, tx) in transactions.iter().enumerate() {
         match &tx.operation {
+            Operation::Send(_) | Operation::Receive(_) if linked_indices.contains(&index) => {}
             Operation::Send(_) | Operation::Receive(_) => {
                 // try to find a matching transaction, by reverse iterating, but no further than one day ago (for receive) or one hour ago (for send)
                 let oldest_match_time = tx.timestamp - if tx.operation.is_send() {
@@ -794,11 +1324,17 @@ fn match_send_receive(transactions: &mut Vec<Transaction>) {
                 }) {
                     let candidate_tx: &Transaction = &transactions[*tx_index];
 
+                    // skip pairs that were manually unlinked
+                    let pair = if tx.operation.is_send() { (index, *tx_index) } else { (*tx_index, index) };
+                    if unlinked_pairs.contains(&pair) {
+                        continue;
+                    }
+
                     match (&candidate_tx.operation, &tx.operation) {
                         (Operation::Send(send_amount), Operation::Receive(receive_amount)) |
                         (Operation::Receive(receive_amount), Operation::Send(send_amount)) => {
                             // the send and receive transactions must have the same currency
-                            if receive_amount.currency != send_amount.currency {
+                            if receive_amount.effective_currency() != send_amount.effective_currency() {
                                 continue;
                             }
 
@@ -860,13 +1396,13 @@ fn match_send_receive(transactions: &mut Vec<Transaction>) {
 
         let match_result = match (&transactions[send_index].operation, &transactions[receive_index].operation) {
             (Operation::Send(sent), Operation::Receive(received)) if received.quantity < sent.quantity => {
-                assert!(sent.currency == received.currency);
+                assert!(sent.effective_currency() == received.effective_currency());
 
-                let implied_fee = Amount::new(sent.quantity - received.quantity, sent.currency.clone());
+                let implied_fee = Amount { quantity: sent.quantity - received.quantity, ..sent.clone() };
 
                 match &transactions[send_index].fee {
                     Some(existing_fee) => {
-                        if existing_fee.currency != implied_fee.currency {
+                        if existing_fee.effective_currency() != implied_fee.effective_currency() {
                             println!("warning: send/receive amounts imply fee, but there's already a fee in a different currency ({}) for transaction {:?}", existing_fee.currency, transactions[send_index]);
                             MatchResult::AbortMatch
                         } else if existing_fee.quantity != implied_fee.quantity {
@@ -1090,15 +1626,15 @@ fn collect_price_requirements(transactions: &[Transaction]) -> PriceRequirements
             (None, Some(amount)) |
             (Some(amount), None) => {
                 if !amount.is_fiat() {
-                    requirements.add(&amount.currency, tx.timestamp);
+                    requirements.add(&amount.effective_currency(), tx.timestamp);
                 }
             }
             (Some(incoming), Some(outgoing)) => {
                 if !incoming.is_fiat() && !outgoing.is_fiat() {
                     // In case neither side is fiat, we want to know the price of both
                     // currencies, since it can give a better value estimate.
-                    requirements.add(&incoming.currency, tx.timestamp);
-                    requirements.add(&outgoing.currency, tx.timestamp);
+                    requirements.add(&incoming.effective_currency(), tx.timestamp);
+                    requirements.add(&outgoing.effective_currency(), tx.timestamp);
                 }
             }
         }
@@ -1107,7 +1643,7 @@ fn collect_price_requirements(transactions: &[Transaction]) -> PriceRequirements
         match &tx.fee {
             Some(amount) => {
                 if !amount.is_fiat() {
-                    requirements.add(&amount.currency, tx.timestamp);
+                    requirements.add(&amount.effective_currency(), tx.timestamp);
                 }
             }
             None => {}
@@ -1192,7 +1728,7 @@ fn calculate_tax_reports(transactions: &mut Vec<Transaction>, tracking: CostBasi
                 short_term_proceeds += gain.proceeds;
             }
 
-            let summary = summary_for(&mut currencies, &gain.amount.currency);
+            let summary = summary_for(&mut currencies, &gain.amount.effective_currency());
             summary.quantity_disposed += gain.amount.quantity;
             // summary.quantity_income += // todo: sum up all income quantities
             summary.cost += gain.cost;
@@ -1206,7 +1742,7 @@ fn calculate_tax_reports(transactions: &mut Vec<Transaction>, tracking: CostBasi
             match (&tx.operation, &tx.fee, &tx.fee_value) {
                 (Operation::Trade { incoming: _, outgoing }, Some(fee), Some(fee_value)) => {
                     if outgoing.try_add(fee).is_none() {
-                        let summary = summary_for(&mut currencies, &fee.currency);
+                        let summary = summary_for(&mut currencies, &fee.effective_currency());
                         summary.fees += fee_value.quantity;
                     }
                 }
@@ -1300,7 +1836,15 @@ fn initialize_ui(app: &mut App) -> Result<AppWindow, slint::PlatformError> {
         .map(|source| SharedString::from(source.label))
         .collect();
     source_types.sort();
-    facade.set_source_types(Rc::new(VecModel::from(source_types)).into());
+    facade.set_source_types(Rc::new(VecModel::from(source_types.clone())).into());
+
+    let any = SharedString::from("Any");
+    let rule_source_types: Vec<SharedString> = std::iter::once(any.clone()).chain(source_types).collect();
+    facade.set_rule_source_types(Rc::new(VecModel::from(rule_source_types)).into());
+    let rule_operation_types: Vec<SharedString> = std::iter::once(any)
+        .chain(OperationType::VARIANTS.iter().map(|operation| operation.to_string().into()))
+        .collect();
+    facade.set_rule_operation_types(Rc::new(VecModel::from(rule_operation_types)).into());
 
     facade.set_wallets(app.ui_wallets.clone().into());
     facade.set_transactions(app.ui_transactions.clone().into());
@@ -1316,10 +1860,17 @@ fn initialize_ui(app: &mut App) -> Result<AppWindow, slint::PlatformError> {
             // or "https://btc.com/tx/{}"
             // or "https://live.blockcypher.com/btc/tx/{}"
             "DASH" => open::that(format!("https://live.blockcypher.com/dash/tx/{}", tx_hash)),
+            "ARB" => open::that(format!("https://arbiscan.io/tx/{}", tx_hash)),
+            "BASE" => open::that(format!("https://basescan.org/tx/{}", tx_hash)),
+            "BSC" => open::that(format!("https://bscscan.com/tx/{}", tx_hash)),
             "ETH" => open::that(format!("https://etherscan.io/tx/{}", tx_hash)),
             "LTC" => open::that(format!("https://blockchair.com/litecoin/transaction/{}", tx_hash)),
+            "OP" => open::that(format!("https://optimistic.etherscan.io/tx/{}", tx_hash)),
+            "POL" => open::that(format!("https://polygonscan.com/tx/{}", tx_hash)),
             "PPC" => open::that(format!("https://explorer.peercoin.net/tx/{}", tx_hash)),
             "RDD" => open::that(format!("https://rddblockexplorer.com/tx/{}", tx_hash)),
+            "SOL" => open::that(format!("https://solscan.io/tx/{}", tx_hash)),
+            "TRX" => open::that(format!("https://tronscan.org/#/transaction/{}", tx_hash)),
             "XLM" => open::that(format!("https://stellar.expert/explorer/public/tx/{}", tx_hash)),
             "XMR" => open::that(format!("https://blockchair.com/monero/transaction/{}", tx_hash)),
             "XRP" => open::that(format!("https://xrpscan.com/tx/{}", tx_hash)),
@@ -1342,6 +1893,17 @@ fn ui_set_wallets(app: &App) {
                 .map(|definition| definition.label)
                 .unwrap_or(source.source_type.as_str());
             let can_sync = source_definition.map(|definition| definition.can_sync()).unwrap_or(false);
+            let local_time = source_definition.is_some_and(|definition| definition.local_time);
+            let can_split = source.account.is_none() && source_definition.is_some_and(|definition| definition.accounts.is_some());
+            let backends = source_definition.map(|definition| definition.backends).unwrap_or_default();
+            let backend = source.backend.as_deref().or(backends.first().copied()).unwrap_or_default();
+            let selected_chains = source.selected_chains();
+            let chains: Vec<UiSourceChain> = source_definition.map(|definition| definition.chains).unwrap_or_default().iter().map(|&chain| {
+                UiSourceChain {
+                    name: chain.into(),
+                    selected: selected_chains.iter().any(|selected| selected == chain),
+                }
+            }).collect();
 
             UiWalletSource {
                 source_type: label.into(),
@@ -1350,6 +1912,15 @@ fn ui_set_wallets(app: &App) {
                 enabled: source.enabled,
                 can_sync,
                 transaction_count: source.transaction_count as i32,
+                local_time,
+                time_zone: source.time_zone.clone().unwrap_or_default().into(),
+                account: source.account.clone().unwrap_or_default().into(),
+                can_split,
+                backends: Rc::new(VecModel::from(backends.iter().map(|&backend| backend.into()).collect::<Vec<SharedString>>())).into(),
+                backend: backend.into(),
+                default_gap_limit: source_definition.and_then(|definition| definition.gap_limit).unwrap_or_default() as i32,
+                gap_limit: source.gap_limit.map(|gap_limit| gap_limit.to_string()).unwrap_or_default().into(),
+                chains: Rc::new(VecModel::from(chains)).into(),
             }
         }).collect();
 
@@ -1369,18 +1940,14 @@ fn ui_set_wallets(app: &App) {
 fn ui_set_transactions(app: &App) {
     let wallets = &app.portfolio.wallets;
     let transactions = &app.transactions;
-    let filters = &app.transaction_filters;
+    let wallet_names: Vec<&str> = wallets.iter().map(|wallet| wallet.name.as_str()).collect();
     let mut transaction_warning_count = 0;
 
     let mut ui_transactions = Vec::new();
 
     for transaction in transactions {
         let matching_tx = transaction.matching_tx.map(|index| &transactions[index]);
-        let filter_matches = |filter: &TransactionFilter| {
-            filter.matches(transaction) || matching_tx.is_some_and(|tx| filter.matches(tx))
-        };
-
-        if !(filters.iter().all(filter_matches)) {
+        if !app.transaction_query.matches(transaction, matching_tx, &wallet_names) {
             continue;
         }
 
@@ -1533,6 +2100,7 @@ fn ui_set_transactions(app: &App) {
             description: description.unwrap_or_default().into(),
             tx_hash: tx_hash.map(|s| s.to_owned()).unwrap_or_default().into(),
             blockchain: blockchain.map(|s| s.to_owned()).unwrap_or_default().into(),
+            linkable: transaction.matching_tx.is_none() && app.transfer_candidates.get(transaction.index).copied().unwrap_or(false),
         });
     }
 
@@ -1540,6 +2108,50 @@ fn ui_set_transactions(app: &App) {
     app.ui().global::<Facade>().set_transaction_warning_count(transaction_warning_count);
 }
 
+/// Summarizes the amounts of a transaction, like "-1 BTC +20000 EUR".
+fn amounts_summary(tx: &Transaction) -> String {
+    match tx.incoming_outgoing() {
+        (Some(incoming), Some(outgoing)) => format!("-{} +{}", outgoing, incoming),
+        (Some(incoming), None) => format!("+{}", incoming),
+        (None, Some(outgoing)) => format!("-{}", outgoing),
+        (None, None) => String::new(),
+    }
+}
+
+fn ui_set_duplicates(app: &App) {
+    let ui_duplicate_transaction = |tx: &Transaction, source: &str| {
+        let summary = amounts_summary(tx);
+        let timestamp = Local.from_utc_datetime(&tx.timestamp).naive_local();
+
+        UiDuplicateTransaction {
+            date: timestamp.date().to_string().into(),
+            time: timestamp.time().format("%H:%M:%S").to_string().into(),
+            source: source.into(),
+            summary: summary.into(),
+            tx_hash: tx.tx_hash.clone().unwrap_or_default().into(),
+        }
+    };
+
+    let ui_duplicates: Vec<UiDuplicate> = app.duplicates.iter().map(|duplicate| {
+        UiDuplicate {
+            first: ui_duplicate_transaction(&duplicate.transactions[0], &duplicate.sources[0]),
+            second: ui_duplicate_transaction(&duplicate.transactions[1], &duplicate.sources[1]),
+            reason: match duplicate.reason {
+                DuplicateReason::SameTxHash => "Same transaction hash",
+                DuplicateReason::SimilarTimeAndAmount => "Similar time and amount",
+            }.into(),
+            resolution: match duplicate.resolution {
+                None => UiDuplicateResolution::Unresolved,
+                Some(DuplicateResolution::First) => UiDuplicateResolution::First,
+                Some(DuplicateResolution::Second) => UiDuplicateResolution::Second,
+                Some(DuplicateResolution::Both) => UiDuplicateResolution::Both,
+            },
+        }
+    }).collect();
+
+    app.ui().global::<Facade>().set_duplicates(Rc::new(VecModel::from(ui_duplicates)).into());
+}
+
 fn ui_set_reports(app: &App) {
     let report_years: Vec<StandardListViewItem> = app.reports.iter().map(|report| {
         if report.year == 0 {
@@ -1666,6 +2278,291 @@ fn ui_set_portfolio(app: &App) {
     }
 }
 
+fn ui_set_currency_aliases(app: &App) {
+    let format_date = |date: Option<NaiveDate>| date.map(|date| date.to_string()).unwrap_or_default();
+    let ui_aliases: Vec<UiCurrencyAlias> = app.portfolio.currency_aliases.iter().map(|alias| {
+        UiCurrencyAlias {
+            from: alias.from.clone().into(),
+            to: alias.to.clone().into(),
+            since: format_date(alias.since).into(),
+            until: format_date(alias.until).into(),
+        }
+    }).collect();
+
+    app.ui().global::<Facade>().set_currency_aliases(Rc::new(VecModel::from(ui_aliases)).into());
+}
+
+fn ui_set_backends(app: &App) {
+    let ui_backends: Vec<UiBackend> = app.portfolio.backends.list().into_iter().map(|(id, name, default_url, settings)| {
+        UiBackend {
+            id: id.into(),
+            name: name.into(),
+            default_url: default_url.into(),
+            url: settings.url.clone().unwrap_or_default().into(),
+            api_key: settings.api_key.clone().unwrap_or_default().into(),
+            proxy: settings.proxy.clone().unwrap_or_default().into(),
+            min_interval: settings.min_interval_ms.map(|ms| ms.to_string()).unwrap_or_default().into(),
+        }
+    }).collect();
+
+    app.ui().global::<Facade>().set_backends(Rc::new(VecModel::from(ui_backends)).into());
+}
+
+fn ui_set_rules(app: &App) {
+    let ui_rules: Vec<UiRule> = app.portfolio.rules.iter().enumerate().map(|(index, rule)| {
+        let conditions = &rule.conditions;
+        let mut parts = Vec::new();
+        if let Some(source_type) = &conditions.source_type {
+            let label = transaction_source_by_id(source_type).map_or(source_type.as_str(), |source| source.label);
+            parts.push(format!("source: {}", label));
+        }
+        if let Some(wallet) = &conditions.wallet {
+            parts.push(format!("wallet: {}", wallet));
+        }
+        if let Some(operation) = &conditions.operation {
+            parts.push(format!("operation: {}", operation));
+        }
+        if let Some(currency) = &conditions.currency {
+            parts.push(format!("currency: {}", currency));
+        }
+        if let Some(min_amount) = &conditions.min_amount {
+            parts.push(format!("amount ≥ {}", min_amount));
+        }
+        if let Some(max_amount) = &conditions.max_amount {
+            parts.push(format!("amount ≤ {}", max_amount));
+        }
+        if let Some(description) = &conditions.description {
+            parts.push(format!("description: /{}/", description));
+        }
+        if let Some(counterparty) = &conditions.counterparty {
+            parts.push(format!("counterparty: {}", counterparty));
+        }
+        let conditions = if parts.is_empty() { "All transactions".to_owned() } else { parts.join(", ") };
+
+        let action = match &rule.action {
+            RuleAction::Reclassify { operation } => format!("Reclassify as {}", operation),
+            RuleAction::SetValue { value } => format!("Set value to {} EUR", value),
+            RuleAction::SetDescription { description } => format!("Set description to \"{}\"", description),
+            RuleAction::Ignore => "Ignore".to_owned(),
+        };
+
+        let matches: Vec<UiRuleMatch> = app.rule_matches.get(index).map(|matches| {
+            matches.iter().map(|tx| ui_rule_match(app, tx)).collect()
+        }).unwrap_or_default();
+
+        UiRule {
+            name: rule.name.clone().into(),
+            conditions: conditions.into(),
+            action: action.into(),
+            matches: Rc::new(VecModel::from(matches)).into(),
+        }
+    }).collect();
+
+    app.ui().global::<Facade>().set_rules(Rc::new(VecModel::from(ui_rules)).into());
+}
+
+fn ui_rule_match(app: &App, tx: &Transaction) -> UiRuleMatch {
+    let timestamp = Local.from_utc_datetime(&tx.timestamp).naive_local();
+    UiRuleMatch {
+        date: timestamp.date().to_string().into(),
+        time: timestamp.time().format("%H:%M:%S").to_string().into(),
+        wallet: app.portfolio.wallets[tx.wallet_index].name.clone().into(),
+        summary: amounts_summary(tx).into(),
+        description: tx.description.clone().unwrap_or_default().into(),
+    }
+}
+
+fn ui_set_saved_searches(app: &App) {
+    let ui_saved_searches: Vec<UiSavedSearch> = app.portfolio.saved_searches.iter().map(|search| {
+        UiSavedSearch {
+            name: search.name.clone().into(),
+            query: search.query.clone().into(),
+        }
+    }).collect();
+
+    app.ui().global::<Facade>().set_saved_searches(Rc::new(VecModel::from(ui_saved_searches)).into());
+}
+
+/// Creates a rule from the values entered in the UI. The choice "Any" means
+/// the condition is not used.
+fn rule_from_ui(new_rule: &UiNewRule) -> Result<Rule> {
+    let text = |value: &SharedString| {
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_owned())
+    };
+    fn choice(value: &SharedString) -> Option<&str> {
+        (value.as_str() != "Any").then_some(value.as_str())
+    }
+    let parse_amount = |value: &SharedString| {
+        text(value).map(|value| Decimal::from_str(&value).with_context(|| format!("invalid amount '{}'", value))).transpose()
+    };
+    let parse_operation = |value: &str| OperationType::from_str(value).with_context(|| format!("unknown operation '{}'", value));
+
+    let source_type = choice(&new_rule.source_type).map(|label| {
+        TRANSACTION_SOURCES.iter().find(|source| source.label == label).map(|source| source.id.to_owned())
+            .with_context(|| format!("unknown source type '{}'", label))
+    }).transpose()?;
+
+    let description = text(&new_rule.description);
+    if let Some(description) = &description {
+        rules::description_regex(description).context("invalid description pattern")?;
+    }
+
+    let action = match new_rule.action.as_str() {
+        "Reclassify" => {
+            let operation = choice(&new_rule.argument).context("choose the operation to reclassify as")?;
+            RuleAction::Reclassify { operation: parse_operation(operation)? }
+        }
+        "Set Value" => RuleAction::SetValue { value: parse_amount(&new_rule.argument)?.context("missing value")? },
+        "Set Description" => RuleAction::SetDescription { description: new_rule.argument.trim().to_owned() },
+        "Ignore" => RuleAction::Ignore,
+        action => return Err(anyhow!("unknown action '{}'", action)),
+    };
+
+    Ok(Rule {
+        name: new_rule.name.trim().to_owned(),
+        conditions: RuleConditions {
+            source_type,
+            wallet: text(&new_rule.wallet),
+            operation: choice(&new_rule.operation).map(parse_operation).transpose()?,
+            currency: text(&new_rule.currency),
+            min_amount: parse_amount(&new_rule.min_amount)?,
+            max_amount: parse_amount(&new_rule.max_amount)?,
+            description,
+            counterparty: text(&new_rule.counterparty),
+        },
+        action,
+    })
+}
+
+fn custom_csv_mapping_from_ui(mapping: &UiCustomCsvMapping) -> Result<CustomCsvMapping> {
+    let column = |value: &SharedString| {
+        let value = value.trim();
+        (!value.is_empty()).then(|| value.to_owned())
+    };
+    let required_column = |value: &SharedString, field: &str| {
+        column(value).with_context(|| format!("choose the {} column", field))
+    };
+
+    let name = mapping.name.trim();
+    if name.is_empty() {
+        return Err(anyhow!("enter a name for the mapping"));
+    }
+
+    let delimiter = match mapping.delimiter.as_str() {
+        "tab" | "\\t" => '\t',
+        delimiter => {
+            let mut chars = delimiter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(delimiter), None) => delimiter,
+                _ => return Err(anyhow!("the delimiter must be a single character")),
+            }
+        }
+    };
+
+    let decimal_separator = match mapping.decimal_separator.as_str() {
+        "," => ',',
+        _ => '.',
+    };
+
+    Ok(CustomCsvMapping {
+        name: name.to_owned(),
+        delimiter,
+        decimal_separator,
+        date_format: mapping.date_format.trim().to_owned(),
+        columns: custom_csv::CustomCsvColumns {
+            timestamp: required_column(&mapping.timestamp_column, "timestamp")?,
+            type_: column(&mapping.type_column),
+            amount: required_column(&mapping.amount_column, "amount")?,
+            currency: required_column(&mapping.currency_column, "currency")?,
+            counter_amount: column(&mapping.counter_amount_column),
+            counter_currency: column(&mapping.counter_currency_column),
+            fee: column(&mapping.fee_column),
+            fee_currency: column(&mapping.fee_currency_column),
+            tx_hash: column(&mapping.tx_hash_column),
+            description: column(&mapping.description_column),
+        },
+        types: custom_csv::parse_type_mapping(&mapping.types)?,
+    })
+}
+
+fn custom_csv_mapping_to_ui(mapping: &CustomCsvMapping) -> UiCustomCsvMapping {
+    let column = |value: &Option<String>| value.clone().unwrap_or_default().into();
+    let columns = &mapping.columns;
+
+    UiCustomCsvMapping {
+        name: mapping.name.clone().into(),
+        delimiter: if mapping.delimiter == '\t' { "tab".into() } else { mapping.delimiter.to_string().into() },
+        decimal_separator: mapping.decimal_separator.to_string().into(),
+        date_format: mapping.date_format.clone().into(),
+        timestamp_column: columns.timestamp.clone().into(),
+        type_column: column(&columns.type_),
+        amount_column: columns.amount.clone().into(),
+        currency_column: columns.currency.clone().into(),
+        counter_amount_column: column(&columns.counter_amount),
+        counter_currency_column: column(&columns.counter_currency),
+        fee_column: column(&columns.fee),
+        fee_currency_column: column(&columns.fee_currency),
+        tx_hash_column: column(&columns.tx_hash),
+        description_column: column(&columns.description),
+        types: custom_csv::format_type_mapping(&mapping.types).into(),
+    }
+}
+
+fn ui_set_custom_csv_columns(app: &App, headers: &[String]) {
+    let ui = app.ui();
+    let facade = ui.global::<Facade>();
+    let columns: Vec<SharedString> = headers.iter().map(|header| header.into()).collect();
+    let optional_columns: Vec<SharedString> = std::iter::once(SharedString::new()).chain(columns.iter().cloned()).collect();
+    facade.set_custom_csv_columns(Rc::new(VecModel::from(columns)).into());
+    facade.set_custom_csv_optional_columns(Rc::new(VecModel::from(optional_columns)).into());
+}
+
+/// Shows the first rows of the given file as parsed using the mapping.
+fn ui_preview_custom_csv(app: &App, path: &Path, mapping: &CustomCsvMapping) -> Result<()> {
+    const PREVIEW_ROWS: usize = 100;
+
+    let rows = custom_csv::parse_rows(path, mapping, DEFAULT_TIME_ZONE)?;
+    let loaded = rows.iter().filter(|(_, result)| matches!(result, Ok(Some(_)))).count();
+    let skipped = rows.iter().filter(|(_, result)| matches!(result, Ok(None))).count();
+    let errors = rows.len() - loaded - skipped;
+
+    let ui_rows: Vec<UiCustomCsvRow> = rows.into_iter().take(PREVIEW_ROWS).map(|(line, result)| {
+        match result {
+            Ok(Some(tx)) => {
+                let timestamp = Local.from_utc_datetime(&tx.timestamp).naive_local();
+                let operation: OperationType = (&tx.operation).into();
+                UiCustomCsvRow {
+                    line: line as i32,
+                    date: timestamp.date().to_string().into(),
+                    time: timestamp.time().format("%H:%M:%S").to_string().into(),
+                    summary: format!("{} {}", operation, amounts_summary(&tx)).into(),
+                    description: tx.description.clone().unwrap_or_default().into(),
+                    error: false,
+                }
+            }
+            Ok(None) => UiCustomCsvRow {
+                line: line as i32,
+                description: "Skipped, the type is not mapped".into(),
+                ..Default::default()
+            },
+            Err(e) => UiCustomCsvRow {
+                line: line as i32,
+                description: e.to_string().into(),
+                error: true,
+                ..Default::default()
+            },
+        }
+    }).collect();
+
+    let ui = app.ui();
+
+    let facade = ui.global::<Facade>();
+    facade.set_custom_csv_preview(Rc::new(VecModel::from(ui_rows)).into());
+    facade.set_custom_csv_status(format!("{} transactions, {} skipped, {} errors", loaded, skipped, errors).into());
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let mut app = App::new();
@@ -1882,6 +2779,12 @@ async fn main() -> Result<()> {
                             full_path: file_name,
                             transaction_count: 0,
                             transactions: Vec::new(),
+                            time_zone: None,
+                            account: None,
+                            custom_csv: None,
+                            backend: None,
+                            gap_limit: None,
+                            chains: Vec::new(),
                         });
                         app.state.last_source_directory = Some(source_directory);
 
@@ -1896,19 +2799,212 @@ async fn main() -> Result<()> {
         }
     });
 
-    facade.on_add_source_address({
+    facade.on_pick_custom_csv({
         let app = app.clone();
 
-        move |wallet_index, source_type, source_path, source_name| {
+        move |wallet_index| {
             let mut app = app.borrow_mut();
-            let source_type = source_type.to_string();
-            let source_path = source_path.trim();
-            let source_name = source_name.trim();
+            let mut dialog = rfd::FileDialog::new()
+                .set_title("Add Custom CSV Source")
+                .add_filter("CSV", &["csv", "txt"]);
 
-            if source_path.is_empty() {
-                app.report_error("Source address cannot be empty.");
-                return;
-            }
+            if let Some(last_source_directory) = &app.state.last_source_directory {
+                dialog = dialog.set_directory(last_source_directory);
+            }
+
+            let Some(path) = dialog.pick_file() else {
+                return false;
+            };
+
+            // start from a saved mapping that fits the file, if any
+            let saved_mapping = app.portfolio.custom_csv_mappings.iter().find(|mapping| {
+                custom_csv::read_headers(&path, mapping.delimiter).is_ok_and(|headers| {
+                    let columns = &mapping.columns;
+                    [&columns.timestamp, &columns.amount, &columns.currency].iter().all(|column| headers.contains(column))
+                })
+            }).cloned();
+            let saved_name = saved_mapping.as_ref().map(|mapping| mapping.name.clone());
+            let mapping = saved_mapping.unwrap_or_else(|| CustomCsvMapping {
+                name: path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default(),
+                delimiter: custom_csv::guess_delimiter(&path),
+                ..Default::default()
+            });
+
+            let headers = match custom_csv::read_headers(&path, mapping.delimiter) {
+                Ok(headers) => headers,
+                Err(e) => {
+                    app.report_error(&format!("Error reading {}: {}", path.display(), e));
+                    return false;
+                }
+            };
+
+            ui_set_custom_csv_columns(&app, &headers);
+            let ui = app.ui();
+            let facade = ui.global::<Facade>();
+            facade.set_custom_csv_file_name(path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default().into());
+            facade.set_custom_csv_mapping(custom_csv_mapping_to_ui(&mapping));
+            facade.set_custom_csv_preview(Rc::new(VecModel::<UiCustomCsvRow>::default()).into());
+            facade.set_custom_csv_status("Map the columns and preview the result".into());
+
+            if app.portfolio.custom_csv_mappings.contains(&mapping) {
+                if let Err(e) = ui_preview_custom_csv(&app, &path, &mapping) {
+                    app.report_error(&format!("Error reading {}: {}", path.display(), e));
+                }
+            }
+
+            app.pending_custom_csv = Some((wallet_index as usize, path, saved_name));
+            true
+        }
+    });
+
+    facade.on_preview_custom_csv({
+        let app = app.clone();
+
+        move |ui_mapping| {
+            let app = app.borrow();
+            let Some((_, path, _)) = &app.pending_custom_csv else {
+                return;
+            };
+
+            let ui = app.ui();
+
+            let facade = ui.global::<Facade>();
+            facade.set_custom_csv_preview(Rc::new(VecModel::<UiCustomCsvRow>::default()).into());
+
+            // the columns depend on the delimiter, which may have changed
+            let result = custom_csv_mapping_from_ui(&ui_mapping).and_then(|mapping| {
+                ui_set_custom_csv_columns(&app, &custom_csv::read_headers(path, mapping.delimiter)?);
+                ui_preview_custom_csv(&app, path, &mapping)
+            });
+            if let Err(e) = result {
+                facade.set_custom_csv_status(e.to_string().into());
+            }
+        }
+    });
+
+    facade.on_add_source_custom_csv({
+        let app = app.clone();
+
+        move |ui_mapping| {
+            let mut app = app.borrow_mut();
+            let mapping = match custom_csv_mapping_from_ui(&ui_mapping) {
+                Ok(mapping) => mapping,
+                Err(e) => {
+                    app.report_error(&format!("Invalid column mapping: {}", e));
+                    return false;
+                }
+            };
+            let Some((_, _, saved_name)) = &app.pending_custom_csv else {
+                return false;
+            };
+
+            // Only the saved mapping the setup started from is replaced, since
+            // other sources may use a mapping with the same name.
+            let name_taken = app.portfolio.custom_csv_mappings.iter().any(|existing| existing.name == mapping.name);
+            if name_taken && saved_name.as_ref() != Some(&mapping.name) {
+                app.report_error(&format!("A column mapping named \"{}\" already exists, please choose another name.", mapping.name));
+                return false;
+            }
+
+            let Some((wallet_index, path, _)) = app.pending_custom_csv.take() else {
+                return false;
+            };
+            if wallet_index >= app.portfolio.wallets.len() {
+                return false;
+            }
+
+            let mappings = &mut app.portfolio.custom_csv_mappings;
+            match mappings.iter_mut().find(|existing| existing.name == mapping.name) {
+                Some(existing) => *existing = mapping.clone(),
+                None => mappings.push(mapping.clone()),
+            }
+
+            app.state.last_source_directory = path.parent().map(Path::to_path_buf);
+            app.portfolio.wallets[wallet_index].sources.push(WalletSource {
+                source_type: custom_csv::CUSTOM_CSV.id.to_owned(),
+                path: path.to_str().unwrap_or_default().to_owned(),
+                name: mapping.name.clone(),
+                enabled: true,
+                full_path: path,
+                transaction_count: 0,
+                transactions: Vec::new(),
+                time_zone: None,
+                account: None,
+                custom_csv: Some(mapping.name),
+                backend: None,
+                gap_limit: None,
+                chains: Vec::new(),
+            });
+
+            app.refresh_transactions();
+            app.refresh_ui();
+            app.save_portfolio(None);
+            true
+        }
+    });
+
+    facade.on_import_custom_csv_mapping({
+        let app = app.clone();
+
+        move || {
+            let dialog = rfd::FileDialog::new()
+                .set_title("Import Column Mapping")
+                .add_filter("JSON", &["json"]);
+
+            if let Some(path) = dialog.pick_file() {
+                let app = app.borrow();
+                match custom_csv::load_mapping(&path) {
+                    Ok(mapping) => {
+                        app.ui().global::<Facade>().set_custom_csv_mapping(custom_csv_mapping_to_ui(&mapping));
+                    }
+                    Err(e) => {
+                        app.report_error(&format!("Error importing column mapping from {}: {}", path.display(), e));
+                    }
+                }
+            }
+        }
+    });
+
+    facade.on_export_custom_csv_mapping({
+        let app = app.clone();
+
+        move |ui_mapping| {
+            let app = app.borrow();
+            let mapping = match custom_csv_mapping_from_ui(&ui_mapping) {
+                Ok(mapping) => mapping,
+                Err(e) => {
+                    app.report_error(&format!("Invalid column mapping: {}", e));
+                    return;
+                }
+            };
+
+            let dialog = rfd::FileDialog::new()
+                .set_title("Export Column Mapping")
+                .set_file_name(format!("{}.json", mapping.name))
+                .add_filter("JSON", &["json"]);
+
+            if let Some(path) = dialog.save_file() {
+                match custom_csv::save_mapping(&mapping, &path) {
+                    Ok(_) => app.report_info(&format!("Exported column mapping to {}", path.display())),
+                    Err(e) => app.report_error(&format!("Error exporting column mapping to {}: {}", path.display(), e)),
+                }
+            }
+        }
+    });
+
+    facade.on_add_source_address({
+        let app = app.clone();
+
+        move |wallet_index, source_type, source_path, source_name| {
+            let mut app = app.borrow_mut();
+            let source_type = source_type.to_string();
+            let source_path = source_path.trim();
+            let source_name = source_name.trim();
+
+            if source_path.is_empty() {
+                app.report_error("Source address cannot be empty.");
+                return;
+            }
 
             let source_definition = transaction_source_by_id(&source_type);
             if source_definition.is_none() {
@@ -1925,6 +3021,12 @@ async fn main() -> Result<()> {
                     full_path: PathBuf::new(),
                     transaction_count: 0,
                     transactions: Vec::new(),
+                    time_zone: None,
+                    account: None,
+                    custom_csv: None,
+                    backend: None,
+                    gap_limit: None,
+                    chains: Vec::new(),
                 });
 
                 app.refresh_transactions();
@@ -1965,6 +3067,180 @@ async fn main() -> Result<()> {
         }
     });
 
+    facade.on_add_currency_alias({
+        let app = app.clone();
+
+        move |from, to, since, until| {
+            let mut app = app.borrow_mut();
+
+            let parse_date = |date: &str| -> Result<Option<NaiveDate>> {
+                let date = date.trim();
+                if date.is_empty() {
+                    Ok(None)
+                } else {
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d").map(Some).with_context(|| format!("invalid date '{}'", date))
+                }
+            };
+
+            let alias = || -> Result<CurrencyAlias> {
+                let (from, to) = (from.trim(), to.trim());
+                if from.is_empty() || to.is_empty() {
+                    return Err(anyhow!("both currencies are required"));
+                }
+                if from == to {
+                    return Err(anyhow!("'{}' can't be an alias of itself", from));
+                }
+                Ok(CurrencyAlias {
+                    from: from.to_owned(),
+                    to: to.to_owned(),
+                    since: parse_date(&since)?,
+                    until: parse_date(&until)?,
+                })
+            };
+
+            match alias() {
+                Ok(alias) => {
+                    app.portfolio.currency_aliases.push(alias);
+                    app.refresh_transactions();
+                    app.refresh_ui();
+                    app.save_portfolio(None);
+                }
+                Err(e) => {
+                    app.report_error(&format!("Failed to add currency alias: {}", e));
+                }
+            }
+        }
+    });
+
+    facade.on_remove_currency_alias({
+        let app = app.clone();
+
+        move |index| {
+            let mut app = app.borrow_mut();
+            if (index as usize) < app.portfolio.currency_aliases.len() {
+                app.portfolio.currency_aliases.remove(index as usize);
+                app.refresh_transactions();
+                app.refresh_ui();
+                app.save_portfolio(None);
+            }
+        }
+    });
+
+    facade.on_set_backend({
+        let app = app.clone();
+
+        move |ui_backend| {
+            let non_empty = |value: SharedString| Some(value.trim().to_owned()).filter(|value| !value.is_empty());
+            let min_interval_ms = match non_empty(ui_backend.min_interval).map(|ms| ms.parse::<u64>()).transpose() {
+                Ok(min_interval_ms) => min_interval_ms,
+                Err(_) => {
+                    app.borrow().report_error("The interval must be a number of milliseconds");
+                    return;
+                }
+            };
+
+            let mut app = app.borrow_mut();
+            if let Some(settings) = app.portfolio.backends.get_mut(&ui_backend.id) {
+                *settings = BackendSettings {
+                    url: non_empty(ui_backend.url),
+                    api_key: non_empty(ui_backend.api_key),
+                    proxy: non_empty(ui_backend.proxy),
+                    min_interval_ms,
+                };
+                ui_set_backends(&app);
+                app.save_portfolio(None);
+                app.report_info(&format!("Updated the settings for {}", ui_backend.name));
+            }
+        }
+    });
+
+    facade.on_add_rule({
+        let app = app.clone();
+
+        move |new_rule| {
+            let mut app = app.borrow_mut();
+            match rule_from_ui(&new_rule) {
+                Ok(rule) => {
+                    app.portfolio.rules.push(rule);
+                    app.refresh_transactions();
+                    app.refresh_ui();
+                    app.save_portfolio(None);
+                }
+                Err(e) => {
+                    app.report_error(&format!("Failed to add rule: {}", e));
+                }
+            }
+        }
+    });
+
+    facade.on_preview_rule({
+        let app = app.clone();
+
+        move |new_rule| {
+            let app = app.borrow();
+            match rule_from_ui(&new_rule) {
+                Ok(rule) => {
+                    // the new rule would be applied after the existing ones
+                    let rule = CompiledRule::new(&rule);
+                    let matches: Vec<UiRuleMatch> = app.rule_candidates.iter().filter(|(source_type, tx)| {
+                        rule.matches(tx, source_type, &app.portfolio.wallets[tx.wallet_index].name)
+                    }).map(|(_, tx)| ui_rule_match(&app, tx)).collect();
+                    app.ui().global::<Facade>().set_rule_preview(Rc::new(VecModel::from(matches)).into());
+                }
+                Err(e) => {
+                    app.report_error(&format!("Failed to preview rule: {}", e));
+                }
+            }
+        }
+    });
+
+    facade.on_remove_rule({
+        let app = app.clone();
+
+        move |index| {
+            let mut app = app.borrow_mut();
+            if (index as usize) < app.portfolio.rules.len() {
+                app.portfolio.rules.remove(index as usize);
+                app.refresh_transactions();
+                app.refresh_ui();
+                app.save_portfolio(None);
+            }
+        }
+    });
+
+    facade.on_save_search({
+        let app = app.clone();
+
+        move |name, query| {
+            let mut app = app.borrow_mut();
+            if let Err(e) = parse_query(query.as_str()) {
+                app.report_error(&format!("Failed to save search: {}", e));
+                return;
+            }
+
+            let name = name.trim().to_owned();
+            match app.portfolio.saved_searches.iter_mut().find(|search| search.name == name) {
+                Some(search) => search.query = query.into(),
+                None => app.portfolio.saved_searches.push(SavedSearch { name, query: query.into() }),
+            }
+            ui_set_saved_searches(&app);
+            app.save_portfolio(None);
+        }
+    });
+
+    facade.on_remove_saved_search({
+        let app = app.clone();
+
+        move |index| {
+            let mut app = app.borrow_mut();
+            if (index as usize) < app.portfolio.saved_searches.len() {
+                app.portfolio.saved_searches.remove(index as usize);
+                ui_set_saved_searches(&app);
+                app.save_portfolio(None);
+            }
+        }
+    });
+
     facade.on_set_wallet_enabled({
         let app = app.clone();
 
@@ -2008,12 +3284,197 @@ async fn main() -> Result<()> {
         }
     });
 
+    facade.on_set_source_time_zone({
+        let app = app.clone();
+
+        move |wallet_index, source_index, time_zone| {
+            let mut app = app.borrow_mut();
+            let time_zone = time_zone.trim();
+            if !time_zone.is_empty() {
+                if let Err(e) = Tz::from_str(time_zone) {
+                    app.report_error(&format!("Invalid time zone: {}", e));
+                    return;
+                }
+            }
+
+            if let Some(wallet) = app.portfolio.wallets.get_mut(wallet_index as usize) {
+                if let Some(source) = wallet.sources.get_mut(source_index as usize) {
+                    source.time_zone = (!time_zone.is_empty()).then(|| time_zone.to_owned());
+
+                    app.refresh_transactions();
+                    app.refresh_ui();
+                    app.save_portfolio(None);
+                }
+            }
+        }
+    });
+
+    facade.on_set_source_backend({
+        let app = app.clone();
+
+        move |wallet_index, source_index, backend| {
+            let mut app = app.borrow_mut();
+            if let Some(source) = app.portfolio.wallets.get_mut(wallet_index as usize)
+                .and_then(|wallet| wallet.sources.get_mut(source_index as usize)) {
+                source.backend = Some(backend.into());
+
+                ui_set_wallets(&app);
+                app.save_portfolio(None);
+            }
+        }
+    });
+
+    facade.on_set_source_gap_limit({
+        let app = app.clone();
+
+        move |wallet_index, source_index, gap_limit| {
+            let mut app = app.borrow_mut();
+            let gap_limit = gap_limit.trim();
+            let gap_limit = match (!gap_limit.is_empty()).then(|| gap_limit.parse::<u32>()).transpose() {
+                Ok(Some(0)) | Err(_) => {
+                    app.report_error("The gap limit must be a positive number.");
+                    return;
+                }
+                Ok(gap_limit) => gap_limit,
+            };
+
+            if let Some(source) = app.portfolio.wallets.get_mut(wallet_index as usize)
+                .and_then(|wallet| wallet.sources.get_mut(source_index as usize)) {
+                source.gap_limit = gap_limit;
+
+                ui_set_wallets(&app);
+                app.save_portfolio(None);
+            }
+        }
+    });
+
+    facade.on_set_source_chain({
+        let app = app.clone();
+
+        move |wallet_index, source_index, chain, selected| {
+            let mut app = app.borrow_mut();
+            if let Some(source) = app.portfolio.wallets.get_mut(wallet_index as usize)
+                .and_then(|wallet| wallet.sources.get_mut(source_index as usize)) {
+                let mut chains = source.selected_chains();
+                chains.retain(|selected_chain| selected_chain != chain.as_str());
+                if selected {
+                    chains.push(chain.into());
+                }
+                source.chains = chains;
+
+                // the UI already reflects the change, and refreshing it would
+                // close the chain selector
+                app.save_portfolio(None);
+            }
+        }
+    });
+
+    facade.on_split_source_accounts({
+        let app = app.clone();
+
+        move |wallet_index, source_index| {
+            let mut app = app.borrow_mut();
+            let Some(source) = app.portfolio.wallets.get(wallet_index as usize)
+                .and_then(|wallet| wallet.sources.get(source_index as usize)) else {
+                return;
+            };
+            let Some(list_accounts) = transaction_source_by_id(&source.source_type).and_then(|definition| definition.accounts) else {
+                return;
+            };
+
+            let accounts = match list_accounts(&source.full_path) {
+                Ok(accounts) => accounts,
+                Err(e) => {
+                    app.report_error(&format!("Failed to read the accounts of {}: {}", source.path, e));
+                    return;
+                }
+            };
+            if accounts.len() < 2 {
+                app.report_error("This source contains only a single account.");
+                return;
+            }
+
+            // the source is replaced by a wallet for each of its accounts
+            let path = source.path.clone();
+            if let Err(e) = app.portfolio.split_source_accounts(wallet_index as usize, source_index as usize, accounts) {
+                app.report_error(&format!("Failed to split the accounts of {}: {}", path, e));
+                return;
+            }
+
+            app.refresh_transactions();
+            app.refresh_ui();
+            app.save_portfolio(None);
+        }
+    });
+
+    facade.on_link_transfer({
+        let app = app.clone();
+
+        move |first_id, second_id| {
+            let mut app = app.borrow_mut();
+            match app.link_transfer(first_id as usize, second_id as usize) {
+                Ok(()) => {
+                    app.refresh_transactions();
+                    app.refresh_ui();
+                    app.save_portfolio(None);
+                }
+                Err(e) => app.report_error(&format!("Failed to link transfer: {}", e)),
+            }
+        }
+    });
+
+    facade.on_unlink_transfer({
+        let app = app.clone();
+
+        move |id| {
+            let mut app = app.borrow_mut();
+            match app.unlink_transfer(id as usize) {
+                Ok(()) => {
+                    app.refresh_transactions();
+                    app.refresh_ui();
+                    app.save_portfolio(None);
+                }
+                Err(e) => app.report_error(&format!("Failed to unlink transfer: {}", e)),
+            }
+        }
+    });
+
+    facade.on_resolve_duplicate({
+        let app = app.clone();
+
+        move |index, resolution| {
+            let mut app = app.borrow_mut();
+            let Some(duplicate) = app.duplicates.get(index as usize) else { return };
+            let [first, second] = duplicate.refs.clone();
+
+            let resolution = match resolution {
+                UiDuplicateResolution::Unresolved => None,
+                UiDuplicateResolution::First => Some(DuplicateResolution::First),
+                UiDuplicateResolution::Second => Some(DuplicateResolution::Second),
+                UiDuplicateResolution::Both => Some(DuplicateResolution::Both),
+            };
+
+            app.portfolio.resolved_duplicates.retain(|resolved| {
+                let same_pair = (resolved.first == first && resolved.second == second) ||
+                    (resolved.first == second && resolved.second == first);
+                !same_pair
+            });
+            if let Some(resolution) = resolution {
+                app.portfolio.resolved_duplicates.push(ResolvedDuplicate { first, second, resolution });
+            }
+
+            app.refresh_transactions();
+            app.refresh_ui();
+            app.save_portfolio(None);
+        }
+    });
+
     facade.on_sync_source({
         let app = app.clone();
 
         move |wallet_index, source_index| {
             let app_for_future = app.clone();
-            let (source_type, source_path) = {
+            let (source_type, source_path, sync_options) = {
                 let app_borrow = app.borrow();
                 let source = app_borrow.portfolio.wallets.get(wallet_index as usize)
                     .and_then(|wallet| wallet.sources.get(source_index as usize));
@@ -2023,7 +3484,13 @@ async fn main() -> Result<()> {
                 }
                 let source = source.unwrap();
 
-                (source.source_type.clone(), source.path.clone())
+                (source.source_type.clone(), source.path.clone(), SyncOptions {
+                    backends: app_borrow.portfolio.backends.clone(),
+                    backend: source.backend.clone(),
+                    gap_limit: source.gap_limit,
+                    cache_dir: app_borrow.project_dirs.as_ref().map(|dirs| dirs.cache_dir().to_owned()),
+                    chains: source.selected_chains(),
+                })
             };
 
             slint::spawn_local(async move {
@@ -2034,7 +3501,7 @@ async fn main() -> Result<()> {
                         .load_async
                         .ok_or_else(|| anyhow!("Sync not supported for this source type"))?;
 
-                    let mut transactions = load_async(source_path).await;
+                    let mut transactions = load_async(source_path, sync_options).await;
 
                     let _ = transactions.as_mut().map(|transactions| {
                         transactions.sort_by(|a, b| a.cmp(b) );
@@ -2219,6 +3686,111 @@ async fn main() -> Result<()> {
         }
     });
 
+    facade.on_export_transactions_koinly({
+        let app = app.clone();
+
+        move || {
+            if let Some(path) = save_csv_file("Export Transactions (Koinly)", "transactions_koinly.csv") {
+                let app = app.borrow();
+                match koinly::save_transactions_to_koinly_csv(&app.transactions, &path) {
+                    Ok(_) => {
+                        app.report_info(&format!("Exported {} transactions to {}", app.transactions.len(), path.display()));
+                    }
+                    Err(e) => {
+                        app.report_error(&format!("Error exporting transactions to {}: {}", path.display(), e));
+                    }
+                }
+            }
+        }
+    });
+
+    facade.on_export_transactions_cointracking({
+        let app = app.clone();
+
+        move || {
+            if let Some(path) = save_csv_file("Export Transactions (CoinTracking)", "transactions_cointracking.csv") {
+                let app = app.borrow();
+                match cointracking::save_transactions_to_cointracking_csv(&app.transactions, &path) {
+                    Ok(count) => {
+                        app.report_info(&format!("Exported {} transactions to {} (dates in UTC)", count, path.display()));
+                    }
+                    Err(e) => {
+                        app.report_error(&format!("Error exporting transactions to {}: {}", path.display(), e));
+                    }
+                }
+            }
+        }
+    });
+
+    facade.on_export_transactions_coinpanda({
+        let app = app.clone();
+
+        move || {
+            if let Some(path) = save_csv_file("Export Transactions (Coinpanda)", "transactions_coinpanda.csv") {
+                let app = app.borrow();
+                match coinpanda::save_transactions_to_coinpanda_csv(&app.transactions, &path) {
+                    Ok(_) => {
+                        app.report_info(&format!("Exported {} transactions to {}", app.transactions.len(), path.display()));
+                    }
+                    Err(e) => {
+                        app.report_error(&format!("Error exporting transactions to {}: {}", path.display(), e));
+                    }
+                }
+            }
+        }
+    });
+
+    facade.on_import_labels({
+        let app = app.clone();
+
+        move || {
+            let dialog = rfd::FileDialog::new()
+                .set_title("Import Labels (BIP-329)")
+                .add_filter("JSON Lines", &["jsonl", "json"]);
+
+            if let Some(path) = dialog.pick_file() {
+                let mut app = app.borrow_mut();
+                match bip329::load_bip329_labels(&path) {
+                    Ok(labels) => {
+                        let count = labels.len();
+                        app.portfolio.transaction_labels.extend(labels);
+
+                        app.refresh_transactions();
+                        app.refresh_ui();
+                        app.save_portfolio(None);
+                        app.report_info(&format!("Imported {} transaction labels", count));
+                    }
+                    Err(e) => {
+                        app.report_error(&format!("Error importing labels from {}: {}", path.display(), e));
+                    }
+                }
+            }
+        }
+    });
+
+    facade.on_export_labels({
+        let app = app.clone();
+
+        move || {
+            let dialog = rfd::FileDialog::new()
+                .set_title("Export Labels (BIP-329)")
+                .set_file_name("labels.jsonl")
+                .add_filter("JSON Lines", &["jsonl"]);
+
+            if let Some(path) = dialog.save_file() {
+                let app = app.borrow();
+                match bip329::save_bip329_labels(&app.transactions, &path) {
+                    Ok(count) => {
+                        app.report_info(&format!("Exported {} transaction labels", count));
+                    }
+                    Err(e) => {
+                        app.report_error(&format!("Error exporting labels to {}: {}", path.display(), e));
+                    }
+                }
+            }
+        }
+    });
+
     facade.on_remove_notification({
         let app = app.clone();
 
@@ -2228,6 +3800,10 @@ async fn main() -> Result<()> {
         }
     });
 
+    facade.on_query_with_term(|query, field, value| {
+        replace_term(query.as_str(), field.as_str(), value.as_str()).into()
+    });
+
     facade.on_transaction_filter_changed({
         let app = app.clone();
 
@@ -2236,25 +3812,19 @@ async fn main() -> Result<()> {
             let ui = app.ui();
             let facade = ui.global::<Facade>();
 
-            app.transaction_filters.clear();
-
-            if facade.get_wallet_filter() >= 0 {
-                app.transaction_filters.push(TransactionFilter::WalletIndex(facade.get_wallet_filter() as usize));
-            }
-
-            if !facade.get_currency_filter().is_empty() {
-                app.transaction_filters.push(TransactionFilter::Currency(facade.get_currency_filter().into()));
-            }
-
-            if facade.get_text_filter().len() > 0 {
-                let re = RegexBuilder::new(&regex::escape(facade.get_text_filter().as_str()))
-                    .case_insensitive(true)
-                    .build().unwrap();
-                app.transaction_filters.push(TransactionFilter::Text(re));
-            }
-
-            if facade.get_warnings_filter() {
-                app.transaction_filters.push(TransactionFilter::HasGainError);
+            // keep the last valid query while the query is being edited
+            match parse_query(facade.get_query().as_str()) {
+                Ok(query) => {
+                    facade.set_query_error(SharedString::default());
+                    app.transaction_query = if facade.get_warnings_filter() {
+                        query.and(Term::HasError)
+                    } else {
+                        query
+                    };
+                }
+                Err(e) => {
+                    facade.set_query_error(e.to_string().into());
+                }
             }
 
             ui_set_transactions(&app);
@@ -2272,3 +3842,138 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::parse_date_time;
+
+    fn transfer_candidates() -> Vec<Transaction> {
+        vec![
+            Transaction::send(parse_date_time("2023-01-01 10:00:00").unwrap(), Amount::new(dec!(1.0), "BTC".to_owned())),
+            Transaction::receive(parse_date_time("2023-01-01 10:30:00").unwrap(), Amount::new(dec!(1.0), "BTC".to_owned())),
+            Transaction::receive(parse_date_time("2023-01-05 10:00:00").unwrap(), Amount::new(dec!(0.5), "BTC".to_owned())),
+        ]
+    }
+
+    #[test]
+    fn match_send_receive_automatically() {
+        let mut transactions = transfer_candidates();
+        match_send_receive(&mut transactions, &[], &HashSet::new());
+
+        assert_eq!(transactions[0].matching_tx, Some(1));
+        assert_eq!(transactions[1].matching_tx, Some(0));
+        assert!(matches!(transactions[2].operation, Operation::Buy(_)));
+    }
+
+    #[test]
+    fn match_send_receive_linked() {
+        let mut transactions = transfer_candidates();
+        match_send_receive(&mut transactions, &[(0, 2)], &HashSet::new());
+
+        assert_eq!(transactions[0].matching_tx, Some(2));
+        assert_eq!(transactions[2].matching_tx, Some(0));
+        assert!(matches!(transactions[1].operation, Operation::Buy(_)));
+    }
+
+    #[test]
+    fn match_send_receive_claimable_balance() {
+        // a balance created by one own Stellar account and claimed days later by another
+        let usdc = || Amount::new(dec!(25), "USDC".to_owned());
+        let stellar_tx = |mut tx: Transaction, tx_hash: &str, counterparty: &str, wallet_index: usize| {
+            tx.tx_hash = Some(tx_hash.to_owned());
+            tx.blockchain = Some("XLM".to_owned());
+            tx.counterparty = Some(counterparty.to_owned());
+            tx.description = Some("Claimable Balance ID 00000000abc".to_owned());
+            tx.wallet_index = wallet_index;
+            tx
+        };
+        let mut transactions = vec![
+            stellar_tx(Transaction::send(parse_date_time("2024-01-01 12:00:00").unwrap(), usdc()), "create", "GB", 0),
+            Transaction::receive(parse_date_time("2024-01-04 11:00:00").unwrap(), usdc()),
+            stellar_tx(Transaction::receive(parse_date_time("2024-01-04 12:00:00").unwrap(), usdc()), "claim", "GA", 1),
+        ];
+        match_send_receive(&mut transactions, &[], &HashSet::new());
+
+        assert_eq!(transactions[0].matching_tx, Some(2));
+        assert_eq!(transactions[2].matching_tx, Some(0));
+        assert!(matches!(transactions[1].operation, Operation::Buy(_)));
+    }
+
+    #[test]
+    fn load_transactions_after_rules() {
+        let mut portfolio: Portfolio = serde_json::from_value(serde_json::json!({
+            "wallets": [
+                { "name": "Ledger", "enabled": true, "sources": [
+                    { "source_type": "LedgerLiveCsv", "path": "ledger_live.csv", "enabled": true },
+                ] },
+            ],
+            "rules": [
+                { "name": "Cosmos", "conditions": { "description": "^Cosmos" }, "action": { "type": "Ignore" } },
+                { "name": "Mining", "conditions": { "operation": "Receive", "currency": "BTC" }, "action": { "type": "Reclassify", "operation": "Income" } },
+            ],
+        })).unwrap();
+        portfolio.wallets[0].sources[0].full_path = PathBuf::from("tests/data/ledger_live.csv");
+
+        // the ignored transactions are not counted
+        let loaded = load_transactions(&mut portfolio).unwrap();
+        assert_eq!(loaded.transactions.len(), 5);
+        assert_eq!(portfolio.wallets[0].sources[0].transaction_count, 5);
+
+        // the reclassified receive can no longer be linked
+        let candidates: Vec<(&Operation, bool)> = loaded.transactions.iter()
+            .map(|tx| &tx.operation)
+            .zip(loaded.transfer_candidates.iter().copied())
+            .collect();
+        assert_eq!(candidates[0], (&Operation::Income(Amount::new(dec!(0.05), "BTC".to_owned())), false));
+        assert_eq!(candidates[1], (&Operation::Buy(Amount::new(dec!(100), "USDT".to_owned())), true));
+        assert_eq!(candidates[2], (&Operation::Sell(Amount::new(dec!(0.01), "BTC".to_owned())), true));
+    }
+
+    #[test]
+    fn split_source_accounts() {
+        let send = TransactionRef {
+            wallet: "Ledger".to_owned(),
+            timestamp: parse_date_time("2024-01-12 16:20:00").unwrap(),
+            operation: Operation::Send(Amount::new(dec!(0.01), "BTC".to_owned())),
+            tx_hash: Some("a1075db55d416d3ca199f55b6084e2115b9345e16c5cf302fc80e9d5fbf5d48d".to_owned()),
+        };
+        let receive = TransactionRef {
+            wallet: "Bitcoin 1".to_owned(),
+            ..send.clone()
+        };
+        let mut portfolio: Portfolio = serde_json::from_value(serde_json::json!({
+            "wallets": [
+                { "name": "Bitcoin 1", "enabled": true, "sources": [] },
+                { "name": "Ledger", "enabled": true, "sources": [
+                    { "source_type": "LedgerLiveCsv", "path": "ledger_live.csv", "enabled": true },
+                ] },
+            ],
+        })).unwrap();
+        portfolio.wallets[1].sources[0].full_path = PathBuf::from("tests/data/ledger_live.csv");
+        portfolio.linked_transfers.push(TransferLink { send: send.clone(), receive: receive.clone() });
+
+        let list_accounts = transaction_source_by_id("LedgerLiveCsv").unwrap().accounts.unwrap();
+        let accounts = list_accounts(Path::new("tests/data/ledger_live.csv")).unwrap();
+        portfolio.split_source_accounts(1, 0, accounts).unwrap();
+
+        // the emptied wallet is removed and the new wallets have unique names
+        let names: Vec<&str> = portfolio.wallets.iter().map(|wallet| wallet.name.as_str()).collect();
+        assert_eq!(names, vec!["Bitcoin 1", "Ethereum 1", "Cosmos 1", "Polygon 1", "Bitcoin 1 (2)"]);
+        assert_eq!(portfolio.wallets[4].sources[0].account.as_deref(), Some("Bitcoin 1"));
+
+        // the link now refers to the wallet of the account
+        assert_eq!(portfolio.linked_transfers[0].send.wallet, "Bitcoin 1 (2)");
+        assert_eq!(portfolio.linked_transfers[0].receive, receive);
+    }
+
+    #[test]
+    fn match_send_receive_unlinked() {
+        let mut transactions = transfer_candidates();
+        match_send_receive(&mut transactions, &[], &HashSet::from([(0, 1)]));
+
+        assert!(transactions.iter().all(|tx| tx.matching_tx.is_none()));
+        assert!(matches!(transactions[0].operation, Operation::Sell(_)));
+        assert!(matches!(transactions[1].operation, Operation::Buy(_)));
+    }
+}
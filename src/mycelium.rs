@@ -1,13 +1,13 @@
 use std::path::Path;
 
 use anyhow::Result;
-use chrono::{NaiveDateTime, TimeZone};
-use chrono_tz::Europe::Berlin;
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
 use csv::Trim;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Deserializer};
 
-use crate::{base::{Transaction, Amount}, CsvSpec, TransactionSource};
+use crate::{base::{Transaction, Amount}, time::local_to_utc, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 // serialize function for reading NaiveDateTime
@@ -36,24 +36,24 @@ struct MyceliumTransaction {
     label: String,
 }
 
-impl From<MyceliumTransaction> for Transaction {
+impl MyceliumTransaction {
     // todo: translate address?
-    fn from(item: MyceliumTransaction) -> Self {
-        let utc_time = Berlin.from_local_datetime(&item.timestamp).unwrap().naive_utc();
-        let mut tx = if item.value < Decimal::ZERO {
-            Transaction::send(utc_time, Amount::new(-item.value, "BTC".to_owned()))
+    fn into_transaction(self, time_zone: Tz) -> Transaction {
+        let utc_time = local_to_utc(self.timestamp, time_zone);
+        let mut tx = if self.value < Decimal::ZERO {
+            Transaction::send(utc_time, Amount::new(-self.value, "BTC".to_owned()))
         } else {
-            Transaction::receive(utc_time, Amount::new(item.value, "BTC".to_owned()))
+            Transaction::receive(utc_time, Amount::new(self.value, "BTC".to_owned()))
         };
-        tx.description = if item.label.is_empty() { None } else { Some(item.label) };
-        tx.tx_hash = Some(item.id);
+        tx.description = if self.label.is_empty() { None } else { Some(self.label) };
+        tx.tx_hash = Some(self.id);
         tx.blockchain = Some("BTC".to_owned());
         tx
     }
 }
 
 // loads a Mycelium CSV file into a list of unified transactions
-fn load_mycelium_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_mycelium_csv(input_path: &Path, options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let mut rdr = csv::ReaderBuilder::new()
@@ -62,7 +62,7 @@ fn load_mycelium_csv(input_path: &Path) -> Result<Vec<Transaction>> {
 
     for result in rdr.deserialize() {
         let record: MyceliumTransaction = result?;
-        transactions.push(record.into());
+        transactions.push(record.into_transaction(options.time_zone));
     }
 
     Ok(transactions)
@@ -81,7 +81,7 @@ static MYCELIUM_CSV: TransactionSource = TransactionSource {
         "Currency",
         "Transaction Label",
     ])],
-    detect: None,
     load_sync: Some(load_mycelium_csv),
-    load_async: None,
+    local_time: true,
+    ..TransactionSource::DEFAULT
 };
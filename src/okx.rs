@@ -0,0 +1,336 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    base::{blockchain_from_network, Amount, Operation, Transaction},
+    time::deserialize_date_time,
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+#[derive(Debug, Deserialize)]
+enum Action {
+    Buy,
+    Sell,
+}
+
+// struct for storing the following CSV columns of the trading history:
+// id,Order id,Time,Trade Type,Symbol,Action,Amount,Trading Unit,Filled Price,PnL,Fee,Fee Unit,Position Change,Position Balance,Balance Change,Balance,Balance Unit
+#[derive(Debug, Deserialize)]
+struct OkxTrade {
+    #[serde(rename = "id")]
+    id: String,
+    #[serde(rename = "Time", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+    #[serde(rename = "Trade Type")]
+    trade_type: String,
+    /// Base and quote currency, like "BTC-USDT".
+    #[serde(rename = "Symbol")]
+    symbol: String,
+    #[serde(rename = "Action")]
+    action: Action,
+    /// Quantity in the trading unit.
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+    /// The base or quote currency, or "Cont" for contracts.
+    #[serde(rename = "Trading Unit")]
+    trading_unit: String,
+    #[serde(rename = "Filled Price")]
+    filled_price: Decimal,
+    /// Negative, since fees reduce the balance.
+    #[serde(rename = "Fee")]
+    fee: Decimal,
+    #[serde(rename = "Fee Unit")]
+    fee_unit: String,
+}
+
+impl TryFrom<OkxTrade> for Transaction {
+    type Error = anyhow::Error;
+
+    fn try_from(item: OkxTrade) -> Result<Self> {
+        let (base, quote) = item.symbol.split_once('-')
+            .with_context(|| format!("unrecognized symbol '{}'", item.symbol))?;
+        // market orders can be placed for an amount of the quote currency
+        let (base_amount, quote_amount) = if item.trading_unit == base {
+            (item.amount, item.amount * item.filled_price)
+        } else if item.trading_unit == quote {
+            if item.filled_price.is_zero() {
+                return Err(anyhow!("trade {} has no filled price", item.id));
+            }
+            (item.amount / item.filled_price, item.amount)
+        } else {
+            return Err(anyhow!("unsupported trading unit '{}' of trade {}", item.trading_unit, item.id));
+        };
+        let quote = Amount::new(quote_amount, quote.to_owned());
+        let base = Amount::new(base_amount, base.to_owned());
+
+        let mut tx = match item.action {
+            Action::Buy => Transaction::trade(item.timestamp, base, quote),
+            Action::Sell => Transaction::trade(item.timestamp, quote, base),
+        };
+        if !item.fee.is_zero() {
+            tx.fee = Some(Amount::new(item.fee.abs(), item.fee_unit));
+        }
+        tx.description = Some(format!("Trade {}", item.id));
+        Ok(tx)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+enum TransferType {
+    Deposit,
+    Withdrawal,
+}
+
+// struct for storing the following CSV columns of the deposit and withdrawal history:
+// Time,Type,Coin,Chain,Amount,Fee,Address,TxID,Status
+#[derive(Debug, Deserialize)]
+struct OkxTransfer {
+    #[serde(rename = "Time", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+    #[serde(rename = "Type")]
+    type_: TransferType,
+    #[serde(rename = "Coin")]
+    coin: String,
+    /// The coin and its network, like "USDT-TRC20".
+    #[serde(rename = "Chain")]
+    chain: String,
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+    #[serde(rename = "Fee")]
+    fee: Option<Decimal>,
+    #[serde(rename = "Address")]
+    address: String,
+    #[serde(rename = "TxID")]
+    tx_id: String,
+    #[serde(rename = "Status")]
+    status: String,
+}
+
+impl From<OkxTransfer> for Transaction {
+    fn from(item: OkxTransfer) -> Self {
+        let amount = Amount::new(item.amount, item.coin.clone());
+        let mut tx = match item.type_ {
+            TransferType::Deposit => Transaction::receive(item.timestamp, amount),
+            TransferType::Withdrawal => {
+                let mut tx = Transaction::send(item.timestamp, amount);
+                // the deposit address is our own
+                if !item.address.is_empty() {
+                    tx.counterparty = Some(item.address);
+                }
+                tx
+            }
+        };
+        tx.fee = item.fee.filter(|fee| !fee.is_zero()).map(|fee| Amount::new(fee.abs(), item.coin.clone()));
+        if !item.tx_id.is_empty() {
+            tx.tx_hash = Some(item.tx_id);
+            let network = item.chain.split_once('-').map_or(item.chain.as_str(), |(_, network)| network);
+            tx.blockchain = Some(blockchain_from_network(network, &item.coin));
+        }
+        tx
+    }
+}
+
+// struct for storing the following CSV columns of the earn history:
+// Time,Product,Coin,Type,Amount
+#[derive(Debug, Deserialize)]
+struct OkxEarnRecord {
+    #[serde(rename = "Time", deserialize_with = "deserialize_date_time")]
+    timestamp: NaiveDateTime,
+    #[serde(rename = "Product")]
+    product: String,
+    #[serde(rename = "Coin")]
+    coin: String,
+    #[serde(rename = "Type")]
+    type_: String,
+    #[serde(rename = "Amount")]
+    amount: Decimal,
+}
+
+impl OkxEarnRecord {
+    /// Subscriptions and redemptions only move funds between the trading and
+    /// earn accounts, so only the earnings are loaded.
+    fn into_transaction(self) -> Option<Transaction> {
+        match self.type_.as_str() {
+            "Earnings" | "Interest" | "Staking Reward" => {
+                let mut tx = Transaction::new(self.timestamp, Operation::Staking(Amount::new(self.amount, self.coin)));
+                tx.description = Some(self.product);
+                Some(tx)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn load_okx_trades_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for result in rdr.deserialize() {
+        let record: OkxTrade = result?;
+        // derivatives don't change the spot holdings
+        if record.trade_type != "Spot" {
+            continue;
+        }
+        transactions.push(record.try_into()?);
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+fn load_okx_deposits_withdrawals_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for result in rdr.deserialize() {
+        let record: OkxTransfer = result?;
+        match record.status.as_str() {
+            "Completed" | "Deposit successful" | "Withdrawal complete" => transactions.push(record.into()),
+            "Failed" | "Canceled" | "Cancelled" => {}
+            status => return Err(anyhow!("unexpected status '{}' of transfer at {}", status, record.timestamp)),
+        }
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+fn load_okx_earn_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .from_path(input_path)?;
+
+    let mut transactions = Vec::new();
+    for result in rdr.deserialize() {
+        let record: OkxEarnRecord = result?;
+        transactions.extend(record.into_transaction());
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static OKX_TRADES_CSV: TransactionSource = TransactionSource {
+    id: "OkxTradesCsv",
+    label: "OKX Trading History (CSV)",
+    csv: &[CsvSpec::new(&[
+        "id",
+        "Order id",
+        "Time",
+        "Trade Type",
+        "Symbol",
+        "Action",
+        "Amount",
+        "Trading Unit",
+        "Filled Price",
+        "PnL",
+        "Fee",
+        "Fee Unit",
+        "Position Change",
+        "Position Balance",
+        "Balance Change",
+        "Balance",
+        "Balance Unit",
+    ])],
+    load_sync: Some(load_okx_trades_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static OKX_DEPOSITS_WITHDRAWALS_CSV: TransactionSource = TransactionSource {
+    id: "OkxDepositsWithdrawalsCsv",
+    label: "OKX Deposits/Withdrawals (CSV)",
+    csv: &[CsvSpec::new(&[
+        "Time",
+        "Type",
+        "Coin",
+        "Chain",
+        "Amount",
+        "Fee",
+        "Address",
+        "TxID",
+        "Status",
+    ])],
+    load_sync: Some(load_okx_deposits_withdrawals_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static OKX_EARN_CSV: TransactionSource = TransactionSource {
+    id: "OkxEarnCsv",
+    label: "OKX Earn (CSV)",
+    csv: &[CsvSpec::new(&[
+        "Time",
+        "Product",
+        "Coin",
+        "Type",
+        "Amount",
+    ])],
+    load_sync: Some(load_okx_earn_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const OPTIONS: LoadOptions = LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None };
+
+    #[test]
+    fn load_spot_trades() {
+        let path = Path::new("tests/data/okx_trades.csv");
+        assert!(OKX_TRADES_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_okx_trades_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 3);
+        assert_eq!(transactions[0].operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.02), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(1300), "USDT".to_owned()),
+        });
+        assert_eq!(transactions[0].fee, Some(Amount::new(dec!(0.00002), "BTC".to_owned())));
+        assert_eq!(transactions[1].operation, Operation::Trade {
+            incoming: Amount::new(dec!(660), "USDT".to_owned()),
+            outgoing: Amount::new(dec!(0.01), "BTC".to_owned()),
+        });
+        assert_eq!(transactions[1].fee, Some(Amount::new(dec!(0.66), "USDT".to_owned())));
+        // a market buy for an amount of the quote currency
+        assert_eq!(transactions[2].operation, Operation::Trade {
+            incoming: Amount::new(dec!(0.01), "BTC".to_owned()),
+            outgoing: Amount::new(dec!(650), "USDT".to_owned()),
+        });
+    }
+
+    #[test]
+    fn load_deposits_withdrawals() {
+        let path = Path::new("tests/data/okx_deposits_withdrawals.csv");
+        assert!(OKX_DEPOSITS_WITHDRAWALS_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_okx_deposits_withdrawals_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert!(transactions[0].operation.is_receive());
+        assert_eq!(transactions[0].blockchain.as_deref(), Some("TRX"));
+        let withdrawal = &transactions[1];
+        assert_eq!(withdrawal.operation, Operation::Send(Amount::new(dec!(0.0099), "BTC".to_owned())));
+        assert_eq!(withdrawal.fee, Some(Amount::new(dec!(0.0001), "BTC".to_owned())));
+        assert_eq!(withdrawal.tx_hash.as_deref(), Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"));
+        assert_eq!(withdrawal.blockchain.as_deref(), Some("BTC"));
+    }
+
+    #[test]
+    fn load_earnings() {
+        let path = Path::new("tests/data/okx_earn.csv");
+        assert!(OKX_EARN_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_okx_earn_csv(path, &OPTIONS).unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].operation, Operation::Staking(Amount::new(dec!(0.0042), "ETH".to_owned())));
+    }
+}
@@ -9,7 +9,7 @@ use serde::Deserialize;
 
 use crate::{
     base::{Amount, Transaction},
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -141,7 +141,7 @@ impl From<PhoenixRecord> for Transaction {
     }
 }
 
-fn load_phoenix_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_phoenix_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut reader = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -159,9 +159,8 @@ static PHOENIX_CSV: TransactionSource = TransactionSource {
     id: "PhoenixCsv",
     label: "Phoenix (CSV)",
     csv: &[CsvSpec::new(&PHOENIX_HEADERS)],
-    detect: None,
     load_sync: Some(load_phoenix_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 /// Helpers
@@ -8,7 +8,7 @@ use serde::{Deserialize, Deserializer};
 use crate::{
     base::{Amount, Transaction},
     time::{deserialize_date_time, parse_date_time},
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -281,7 +281,7 @@ impl TryFrom<PoloniexTrade> for Transaction {
 }
 
 // loads a Poloniex Deposits CSV file into a list of unified transactions
-fn load_poloniex_deposits_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_poloniex_deposits_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -294,7 +294,7 @@ fn load_poloniex_deposits_csv(input_path: &Path) -> Result<Vec<Transaction>> {
 }
 
 // loads a Poloniex Withdrawals CSV file into a list of unified transactions
-fn load_poloniex_withdrawals_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_poloniex_withdrawals_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -307,7 +307,7 @@ fn load_poloniex_withdrawals_csv(input_path: &Path) -> Result<Vec<Transaction>>
 }
 
 // loads a Poloniex Trades CSV file into a list of unified transactions
-fn load_poloniex_trades_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_poloniex_trades_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -325,7 +325,7 @@ fn load_poloniex_trades_csv(input_path: &Path) -> Result<Vec<Transaction>> {
     Ok(transactions)
 }
 
-fn load_poloniex_trades_before_august_2022_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_poloniex_trades_before_august_2022_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut rdr = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -416,9 +416,8 @@ static POLONIEX_DEPOSITS_CSV: TransactionSource = TransactionSource {
             "f_status",
         ]),
     ],
-    detect: None,
     load_sync: Some(load_poloniex_deposits_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -443,9 +442,8 @@ static POLONIEX_TRADES_BEFORE_AUGUST_2022_CSV: TransactionSource = TransactionSo
         "sellerordernumber",
         "date",
     ])],
-    detect: None,
     load_sync: Some(load_poloniex_trades_before_august_2022_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -495,9 +493,8 @@ static POLONIEX_TRADES_CSV: TransactionSource = TransactionSource {
             "utc_time",
         ]),
     ],
-    detect: None,
     load_sync: Some(load_poloniex_trades_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
@@ -517,7 +514,6 @@ static POLONIEX_WITHDRAWALS_CSV: TransactionSource = TransactionSource {
         CsvSpec::new(&["", "timestamp", "currency", "amount", "fee_deducted", "status"]),
         CsvSpec::new(&["f_date", "currency", "f_amount", "f_feededucted", "f_status"]),
     ],
-    detect: None,
     load_sync: Some(load_poloniex_withdrawals_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
@@ -305,7 +305,7 @@ impl PriceHistory {
 
     /// Estimate the value of an amount at a given timestamp.
     pub fn estimate_value(&self, timestamp: NaiveDateTime, amount: &Amount) -> Option<Amount> {
-        self.estimate_price(timestamp, &amount.currency)
+        self.estimate_price(timestamp, &amount.effective_currency())
             .map(|price| Amount::new(price * amount.quantity, "EUR".to_owned()))
     }
 
@@ -0,0 +1,588 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use strum::VariantArray;
+
+use crate::base::{Operation, OperationType, Transaction};
+
+/// How a number in a query is compared.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn holds(self, a: Decimal, b: Decimal) -> bool {
+        match self {
+            Comparison::Less => a < b,
+            Comparison::LessOrEqual => a <= b,
+            Comparison::Equal => a == b,
+            Comparison::GreaterOrEqual => a >= b,
+            Comparison::Greater => a > b,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TypeFilter {
+    /// A send and receive matched with each other.
+    Transfer,
+    Operation(OperationType),
+}
+
+/// A single condition of a query, like `currency:BTC` or `value>1000`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Term {
+    Type(TypeFilter),
+    Currency(String),
+    Wallet(String),
+    /// Transactions on or after `from` and before `until`, in local time.
+    Date { from: Option<NaiveDate>, until: Option<NaiveDate> },
+    /// The value of the transaction, in EUR.
+    Value(Comparison, Decimal),
+    /// The quantity of the incoming or outgoing amount.
+    Amount(Comparison, Decimal),
+    Gain(Comparison, Decimal),
+    Description(String),
+    Hash(String),
+    Counterparty(String),
+    /// A `#tag` in the description.
+    Tag(String),
+    /// Text found in the description or transaction hash.
+    Text(String),
+    HasError,
+    HasDescription,
+    HasHash,
+    HasValue,
+}
+
+/// A parsed transaction query.
+///
+/// Terms separated by whitespace (or `AND`) must all match, `OR` matches
+/// either side and `NOT` or a `-` prefix negates a term. Parentheses can be
+/// used for grouping, for example:
+///
+/// `type:trade currency:BTC date:2023-01..2023-06 (value>1000 OR -wallet:Kraken) -tag:reviewed`
+///
+/// Values containing spaces can be quoted, using `\"` for a quote inside.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) enum Query {
+    #[default]
+    All,
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+fn contains_ignore_case(haystack: Option<&str>, needle: &str) -> bool {
+    haystack.is_some_and(|haystack| haystack.to_lowercase().contains(needle))
+}
+
+/// Returns whether the description contains the given tag as `#tag` word.
+fn has_tag(description: Option<&str>, tag: &str) -> bool {
+    description.is_some_and(|description| {
+        description.split_whitespace().any(|word| {
+            word.trim_end_matches([',', '.', ';', ':', '!', '?'])
+                .strip_prefix('#')
+                .is_some_and(|word| word.eq_ignore_ascii_case(tag))
+        })
+    })
+}
+
+impl Term {
+    fn matches(&self, tx: &Transaction, wallet_names: &[&str]) -> bool {
+        let (incoming, outgoing) = tx.incoming_outgoing();
+        let mut amounts = [incoming, outgoing].into_iter().flatten();
+
+        match self {
+            Term::Type(TypeFilter::Transfer) => {
+                tx.matching_tx.is_some() && matches!(tx.operation, Operation::Send(_) | Operation::Receive(_))
+            }
+            Term::Type(TypeFilter::Operation(operation_type)) => OperationType::from(&tx.operation) == *operation_type,
+            Term::Currency(currency) => amounts.any(|amount| {
+                amount.currency.eq_ignore_ascii_case(currency) || amount.effective_currency().eq_ignore_ascii_case(currency)
+            }),
+            Term::Wallet(wallet) => wallet_names.get(tx.wallet_index).is_some_and(|name| name.eq_ignore_ascii_case(wallet)),
+            Term::Date { from, until } => {
+                let date = Local.from_utc_datetime(&tx.timestamp).date_naive();
+                from.is_none_or(|from| date >= from) && until.is_none_or(|until| date < until)
+            }
+            Term::Value(comparison, value) => tx.value.as_ref().is_some_and(|v| comparison.holds(v.quantity, *value)),
+            Term::Amount(comparison, quantity) => amounts.any(|amount| comparison.holds(amount.quantity, *quantity)),
+            Term::Gain(comparison, gain) => matches!(tx.gain, Some(Ok(g)) if comparison.holds(g, *gain)),
+            Term::Description(text) => contains_ignore_case(tx.description.as_deref(), text),
+            Term::Hash(text) => contains_ignore_case(tx.tx_hash.as_deref(), text),
+            Term::Counterparty(text) => contains_ignore_case(tx.counterparty.as_deref(), text),
+            Term::Tag(tag) => has_tag(tx.description.as_deref(), tag),
+            Term::Text(text) => {
+                contains_ignore_case(tx.description.as_deref(), text) || contains_ignore_case(tx.tx_hash.as_deref(), text)
+            }
+            Term::HasError => tx.gain.as_ref().is_some_and(|gain| gain.is_err()),
+            Term::HasDescription => tx.description.is_some(),
+            Term::HasHash => tx.tx_hash.is_some(),
+            Term::HasValue => tx.value.is_some(),
+        }
+    }
+}
+
+impl Query {
+    /// Returns whether the query matches the given transaction. For
+    /// transfers, each term may match either side of the transfer.
+    pub(crate) fn matches(&self, tx: &Transaction, matching_tx: Option<&Transaction>, wallet_names: &[&str]) -> bool {
+        match self {
+            Query::All => true,
+            Query::And(queries) => queries.iter().all(|query| query.matches(tx, matching_tx, wallet_names)),
+            Query::Or(queries) => queries.iter().any(|query| query.matches(tx, matching_tx, wallet_names)),
+            Query::Not(query) => !query.matches(tx, matching_tx, wallet_names),
+            Query::Term(term) => {
+                term.matches(tx, wallet_names) || matching_tx.is_some_and(|tx| term.matches(tx, wallet_names))
+            }
+        }
+    }
+
+    /// Combines this query with another term, which both need to match.
+    pub(crate) fn and(self, term: Term) -> Query {
+        match self {
+            Query::All => Query::Term(term),
+            Query::And(mut queries) => {
+                queries.push(Query::Term(term));
+                Query::And(queries)
+            }
+            query => Query::And(vec![query, Query::Term(term)]),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => { chars.next(); }
+            '(' => { chars.next(); tokens.push(Token::Open); }
+            ')' => { chars.next(); tokens.push(Token::Close); }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let mut word = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if c == '"' {
+                        quoted = true;
+                        chars.next();
+                        loop {
+                            match chars.next() {
+                                Some('"') => break,
+                                Some('\\') => match chars.next() {
+                                    Some(c) => word.push(c),
+                                    None => bail!("missing closing quote"),
+                                },
+                                Some(c) => word.push(c),
+                                None => bail!("missing closing quote"),
+                            }
+                        }
+                    } else if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    } else {
+                        word.push(c);
+                        chars.next();
+                    }
+                }
+                tokens.push(match word.as_str() {
+                    "AND" if !quoted => Token::And,
+                    "OR" if !quoted => Token::Or,
+                    "NOT" if !quoted => Token::Not,
+                    _ => Token::Word(word),
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses a date of the form `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into the
+/// first day of that period and the first day after it.
+fn parse_period(text: &str) -> Result<(NaiveDate, NaiveDate)> {
+    let invalid = || anyhow!("invalid date '{}', expected YYYY, YYYY-MM or YYYY-MM-DD", text);
+    let parts: Vec<&str> = text.split('-').collect();
+    let numbers: Vec<u32> = parts.iter().map(|part| part.parse::<u32>()).collect::<Result<_, _>>().map_err(|_| invalid())?;
+
+    match numbers[..] {
+        [year] => {
+            let start = NaiveDate::from_ymd_opt(year as i32, 1, 1).ok_or_else(invalid)?;
+            Ok((start, start.with_year(start.year() + 1).ok_or_else(invalid)?))
+        }
+        [year, month] => {
+            let start = NaiveDate::from_ymd_opt(year as i32, month, 1).ok_or_else(invalid)?;
+            Ok((start, start.checked_add_months(chrono::Months::new(1)).ok_or_else(invalid)?))
+        }
+        [year, month, day] => {
+            let start = NaiveDate::from_ymd_opt(year as i32, month, day).ok_or_else(invalid)?;
+            Ok((start, start.succ_opt().ok_or_else(invalid)?))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_date_term(operator: &str, value: &str) -> Result<Term> {
+    let (from, until) = match operator {
+        ":" | "=" => match value.split_once("..") {
+            Some((from, until)) => {
+                let from = (!from.is_empty()).then(|| parse_period(from)).transpose()?;
+                let until = (!until.is_empty()).then(|| parse_period(until)).transpose()?;
+                (from.map(|(start, _)| start), until.map(|(_, end)| end))
+            }
+            None => {
+                let (start, end) = parse_period(value)?;
+                (Some(start), Some(end))
+            }
+        },
+        ">" => (Some(parse_period(value)?.1), None),
+        ">=" => (Some(parse_period(value)?.0), None),
+        "<" => (None, Some(parse_period(value)?.0)),
+        "<=" => (None, Some(parse_period(value)?.1)),
+        _ => unreachable!(),
+    };
+    Ok(Term::Date { from, until })
+}
+
+fn parse_type(value: &str) -> Result<TypeFilter> {
+    match value.to_lowercase().as_str() {
+        "transfer" => Ok(TypeFilter::Transfer),
+        "deposit" => Ok(TypeFilter::Operation(OperationType::FiatDeposit)),
+        "withdrawal" => Ok(TypeFilter::Operation(OperationType::FiatWithdrawal)),
+        _ => OperationType::VARIANTS.iter()
+            .find(|operation_type| operation_type.to_string().eq_ignore_ascii_case(value))
+            .map(|operation_type| TypeFilter::Operation(*operation_type))
+            .with_context(|| format!("unknown type '{}'", value)),
+    }
+}
+
+fn parse_term(word: &str) -> Result<Term> {
+    let Some(position) = word.find([':', '<', '>', '=']) else {
+        return Ok(Term::Text(word.to_lowercase()));
+    };
+
+    let field = word[..position].to_lowercase();
+    let rest = &word[position..];
+    let operator = [">=", "<=", ":", "<", ">", "="].into_iter().find(|operator| rest.starts_with(operator)).unwrap();
+    let value = &rest[operator.len()..];
+    if value.is_empty() {
+        bail!("missing value for '{}'", field);
+    }
+
+    let comparison = || match operator {
+        "<" => Comparison::Less,
+        "<=" => Comparison::LessOrEqual,
+        ">=" => Comparison::GreaterOrEqual,
+        ">" => Comparison::Greater,
+        _ => Comparison::Equal,
+    };
+    let number = || Decimal::from_str(value).with_context(|| format!("invalid number '{}'", value));
+    let text_operator = || {
+        if operator == ":" || operator == "=" {
+            Ok(())
+        } else {
+            Err(anyhow!("'{}' can't be compared with '{}'", field, operator))
+        }
+    };
+
+    Ok(match field.as_str() {
+        "date" => parse_date_term(operator, value)?,
+        "value" => Term::Value(comparison(), number()?),
+        "amount" => Term::Amount(comparison(), number()?),
+        "gain" => Term::Gain(comparison(), number()?),
+        "type" => { text_operator()?; Term::Type(parse_type(value)?) }
+        "currency" => { text_operator()?; Term::Currency(value.to_owned()) }
+        "wallet" => { text_operator()?; Term::Wallet(value.to_owned()) }
+        "description" => { text_operator()?; Term::Description(value.to_lowercase()) }
+        "hash" => { text_operator()?; Term::Hash(value.to_lowercase()) }
+        "counterparty" => { text_operator()?; Term::Counterparty(value.to_lowercase()) }
+        "tag" => { text_operator()?; Term::Tag(value.trim_start_matches('#').to_owned()) }
+        "has" | "is" => {
+            text_operator()?;
+            match value.to_lowercase().as_str() {
+                "error" | "warning" => Term::HasError,
+                "description" => Term::HasDescription,
+                "hash" => Term::HasHash,
+                "value" => Term::HasValue,
+                _ => bail!("unknown property '{}'", value),
+            }
+        }
+        _ => bail!("unknown field '{}'", field),
+    })
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        self.position += 1;
+        self.tokens.get(self.position - 1)
+    }
+
+    fn parse_or(&mut self) -> Result<Query> {
+        let mut queries = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            queries.push(self.parse_and()?);
+        }
+        Ok(if queries.len() == 1 { queries.pop().unwrap() } else { Query::Or(queries) })
+    }
+
+    fn parse_and(&mut self) -> Result<Query> {
+        let mut queries = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                Some(Token::And) => { self.next(); }
+                _ => {}
+            }
+            queries.push(self.parse_unary()?);
+        }
+        Ok(if queries.len() == 1 { queries.pop().unwrap() } else { Query::And(queries) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Query> {
+        match self.next() {
+            Some(Token::Not) => Ok(Query::Not(Box::new(self.parse_unary()?))),
+            Some(Token::Open) => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    _ => Err(anyhow!("missing closing parenthesis")),
+                }
+            }
+            Some(Token::Word(word)) => {
+                let word = word.clone();
+                Ok(Query::Term(parse_term(&word)?))
+            }
+            Some(Token::Close) => Err(anyhow!("unexpected closing parenthesis")),
+            Some(Token::And) | Some(Token::Or) => Err(anyhow!("missing term before AND/OR")),
+            None => Err(anyhow!("unexpected end of query")),
+        }
+    }
+}
+
+/// Parses a query. An empty query matches all transactions.
+pub(crate) fn parse_query(input: &str) -> Result<Query> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Ok(Query::All);
+    }
+
+    let mut parser = Parser { tokens, position: 0 };
+    let query = parser.parse_or()?;
+    if parser.peek().is_some() {
+        bail!("unexpected closing parenthesis");
+    }
+    Ok(query)
+}
+
+/// Quotes a value for use in a query, when needed.
+pub(crate) fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(|c: char| c.is_whitespace() || "\"\\()".contains(c)) {
+        return value.to_owned();
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Returns the query with its terms for the given field replaced by one
+/// matching the given value, keeping the other terms. Only terms outside of
+/// parentheses that are not negated are replaced. When a term is removed
+/// from alternatives or the remaining query would no longer be valid, only
+/// the new term is returned.
+pub(crate) fn replace_term(query: &str, field: &str, value: &str) -> String {
+    let term = format!("{}:{}", field, quote(value));
+    let prefix = format!("{}:", field);
+
+    // split into words, keeping quoted parts together
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (index, c) in query.char_indices() {
+        if in_quotes {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_quotes = false,
+                _ => {}
+            }
+        } else if c.is_whitespace() {
+            if let Some(start) = start.take() {
+                words.push(&query[start..index]);
+            }
+            continue;
+        } else if c == '"' {
+            in_quotes = true;
+        }
+        start.get_or_insert(index);
+    }
+    if let Some(start) = start {
+        words.push(&query[start..]);
+    }
+
+    let mut depth = 0;
+    let mut kept: Vec<&str> = Vec::new();
+    let mut removed = false;
+    let mut has_or = false;
+    for word in words {
+        let is_field = depth == 0 && word.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(&prefix));
+        has_or |= depth == 0 && word == "OR";
+        let mut in_quotes = false;
+        let mut escaped = false;
+        for c in word.chars() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_quotes => escaped = true,
+                '"' => in_quotes = !in_quotes,
+                '(' if !in_quotes => depth += 1,
+                ')' if !in_quotes => depth -= 1,
+                _ => {}
+            }
+        }
+        if is_field {
+            removed = true;
+        } else {
+            kept.push(word);
+        }
+    }
+
+    let replaced = match (kept.is_empty(), has_or) {
+        (true, _) => return term,
+        (false, true) if removed => return term,
+        (false, true) => format!("({}) {}", kept.join(" "), term),
+        (false, false) => format!("{} {}", kept.join(" "), term),
+    };
+    // an AND right before a removed term is left dangling
+    match parse_query(&replaced) {
+        Ok(_) => replaced,
+        Err(_) => term,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Amount;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    fn trade(date_time: &str, wallet_index: usize, value: Decimal) -> Transaction {
+        let mut tx = Transaction::trade(
+            parse_date_time(date_time).unwrap(),
+            Amount::new(dec!(0.1), "BTC".to_owned()),
+            Amount::new(value, "EUR".to_owned()),
+        );
+        tx.wallet_index = wallet_index;
+        tx.value = Some(Amount::from_fiat(value));
+        tx
+    }
+
+    #[test]
+    fn parse_example_query() {
+        let query = parse_query(r#"type:trade currency:BTC wallet:"My Kraken" date:2023-01..2023-06 value>1000 -hash:abc"#).unwrap();
+        assert_eq!(query, Query::And(vec![
+            Query::Term(Term::Type(TypeFilter::Operation(OperationType::Trade))),
+            Query::Term(Term::Currency("BTC".to_owned())),
+            Query::Term(Term::Wallet("My Kraken".to_owned())),
+            Query::Term(Term::Date {
+                from: NaiveDate::from_ymd_opt(2023, 1, 1),
+                until: NaiveDate::from_ymd_opt(2023, 7, 1),
+            }),
+            Query::Term(Term::Value(Comparison::Greater, dec!(1000))),
+            Query::Not(Box::new(Query::Term(Term::Hash("abc".to_owned())))),
+        ]));
+
+        let query = parse_query(r#"-tag:reviewed wallet:"Bob's \"Cold\" Storage""#).unwrap();
+        assert_eq!(query, Query::And(vec![
+            Query::Not(Box::new(Query::Term(Term::Tag("reviewed".to_owned())))),
+            Query::Term(Term::Wallet(r#"Bob's "Cold" Storage"#.to_owned())),
+        ]));
+    }
+
+    #[test]
+    fn replace_terms() {
+        assert_eq!(replace_term("", "wallet", "Kraken"), "wallet:Kraken");
+        assert_eq!(replace_term("currency:BTC wallet:Ledger", "wallet", "My Kraken"), r#"currency:BTC wallet:"My Kraken""#);
+        assert_eq!(replace_term(r#"wallet:"My Kraken" currency:BTC"#, "currency", "ETH"), r#"wallet:"My Kraken" currency:ETH"#);
+        assert_eq!(replace_term("-wallet:Ledger (wallet:A OR wallet:B)", "wallet", "C"), "-wallet:Ledger (wallet:A OR wallet:B) wallet:C");
+        assert_eq!(replace_term("value>10 OR wallet:A", "wallet", "B"), "wallet:B");
+        assert_eq!(replace_term("value>10 OR value<1", "wallet", "B"), "(value>10 OR value<1) wallet:B");
+
+        let term = replace_term("currency:BTC", "wallet", r#"Bob's "Cold" \ Storage"#);
+        assert_eq!(parse_query(&term).unwrap(), Query::And(vec![
+            Query::Term(Term::Currency("BTC".to_owned())),
+            Query::Term(Term::Wallet(r#"Bob's "Cold" \ Storage"#.to_owned())),
+        ]));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse_query("label:reviewed").is_err());
+        assert!(parse_query("tag<reviewed").is_err());
+        assert!(parse_query("type:unknown").is_err());
+        assert!(parse_query("value>abc").is_err());
+        assert!(parse_query("(currency:BTC").is_err());
+        assert!(parse_query("currency:BTC)").is_err());
+        assert!(parse_query("date:2023-13").is_err());
+        assert_eq!(parse_query("  ").unwrap(), Query::All);
+    }
+
+    #[test]
+    fn match_transactions() {
+        let wallets = ["Kraken", "Ledger"];
+        let transactions = [
+            trade("2023-01-15 12:00:00", 0, dec!(500)),
+            {
+                let mut tx = trade("2023-03-15 12:00:00", 0, dec!(2000));
+                tx.description = Some("Checked #Reviewed, all fine".to_owned());
+                tx
+            },
+            trade("2023-08-15 12:00:00", 1, dec!(3000)),
+        ];
+        let matching = |query: &str| -> Vec<usize> {
+            let query = parse_query(query).unwrap();
+            transactions.iter().enumerate()
+                .filter(|(_, tx)| query.matches(tx, None, &wallets))
+                .map(|(index, _)| index)
+                .collect()
+        };
+
+        assert_eq!(matching("wallet:kraken date:2023-01..2023-06 value>1000"), vec![1]);
+        assert_eq!(matching("value<1000 OR wallet:Ledger"), vec![0, 2]);
+        assert_eq!(matching("NOT (date:2023-03 OR currency:ETH)"), vec![0, 2]);
+        assert_eq!(matching("date>=2023-03 -wallet:Ledger"), vec![1]);
+        assert_eq!(matching("type:swap"), Vec::<usize>::new());
+        assert_eq!(matching("tag:reviewed"), vec![1]);
+        assert_eq!(matching("-tag:reviewed wallet:Kraken"), vec![0]);
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::base::{Amount, OperationType, Transaction};
+
+/// The conditions a transaction needs to meet for a rule to apply. Conditions
+/// that are not set match any transaction.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct RuleConditions {
+    /// The ID of the source type the transaction was loaded from.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub source_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub wallet: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub operation: Option<OperationType>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub currency: Option<String>,
+    /// The minimum quantity of the incoming or outgoing amount (inclusive).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min_amount: Option<Decimal>,
+    /// The maximum quantity of the incoming or outgoing amount (inclusive).
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max_amount: Option<Decimal>,
+    /// A case-insensitive regular expression matched against the description.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub counterparty: Option<String>,
+}
+
+/// What happens to the transactions matched by a rule.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type")]
+pub(crate) enum RuleAction {
+    /// Changes the type of operation, keeping its amounts.
+    Reclassify { operation: OperationType },
+    /// Sets the value of the transaction, in EUR.
+    SetValue { value: Decimal },
+    SetDescription { description: String },
+    /// Removes the transaction.
+    Ignore,
+}
+
+/// A user-defined rule, which modifies all loaded transactions that meet its
+/// conditions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct Rule {
+    pub name: String,
+    #[serde(default)]
+    pub conditions: RuleConditions,
+    pub action: RuleAction,
+}
+
+/// Compiles the description condition of a rule.
+pub(crate) fn description_regex(pattern: &str) -> Result<Regex> {
+    Ok(RegexBuilder::new(pattern).case_insensitive(true).build()?)
+}
+
+/// A rule prepared for matching against transactions.
+pub(crate) struct CompiledRule<'a> {
+    rule: &'a Rule,
+    description: Option<Regex>,
+    /// Set when the rule could not be compiled, in which case it matches
+    /// nothing.
+    invalid: bool,
+}
+
+impl<'a> CompiledRule<'a> {
+    pub(crate) fn new(rule: &'a Rule) -> Self {
+        match rule.conditions.description.as_deref().map(description_regex).transpose() {
+            Ok(description) => Self { rule, description, invalid: false },
+            Err(e) => {
+                println!("warning: rule '{}' has an invalid description pattern: {}", rule.name, e);
+                Self { rule, description: None, invalid: true }
+            }
+        }
+    }
+
+    fn matches_amount(&self, amount: &Amount) -> bool {
+        let conditions = &self.rule.conditions;
+        conditions.currency.as_ref().is_none_or(|currency| {
+            &amount.currency == currency || amount.effective_currency() == currency.as_str()
+        }) &&
+            conditions.min_amount.is_none_or(|min| amount.quantity >= min) &&
+            conditions.max_amount.is_none_or(|max| amount.quantity <= max)
+    }
+
+    pub(crate) fn matches(&self, tx: &Transaction, source_type: &str, wallet: &str) -> bool {
+        let conditions = &self.rule.conditions;
+        let amount_condition = conditions.currency.is_some() || conditions.min_amount.is_some() || conditions.max_amount.is_some();
+        let (incoming, outgoing) = tx.incoming_outgoing();
+
+        !self.invalid &&
+            conditions.source_type.as_ref().is_none_or(|s| s == source_type) &&
+            conditions.wallet.as_ref().is_none_or(|w| w == wallet) &&
+            conditions.operation.is_none_or(|operation| OperationType::from(&tx.operation) == operation) &&
+            (!amount_condition || [incoming, outgoing].into_iter().flatten().any(|amount| self.matches_amount(amount))) &&
+            self.description.as_ref().is_none_or(|re| tx.description.as_deref().is_some_and(|d| re.is_match(d))) &&
+            conditions.counterparty.as_ref().is_none_or(|counterparty| {
+                tx.counterparty.as_ref().is_some_and(|c| c.eq_ignore_ascii_case(counterparty))
+            })
+    }
+}
+
+/// Applies the rules, in order, to a transaction loaded from a source of the
+/// given type. Returns `false` when the transaction is to be ignored.
+///
+/// For each rule, the transaction is added to `affected` when it matched, as
+/// it was before the rule was applied.
+pub(crate) fn apply_rules(rules: &[CompiledRule], source_type: &str, wallet: &str, tx: &mut Transaction, affected: &mut [Vec<Transaction>]) -> bool {
+    for (rule_index, rule) in rules.iter().enumerate() {
+        if !rule.matches(tx, source_type, wallet) {
+            continue;
+        }
+
+        affected[rule_index].push(tx.clone());
+
+        match &rule.rule.action {
+            RuleAction::Reclassify { operation } => {
+                match tx.operation.reclassified(*operation) {
+                    Some(reclassified) => tx.operation = reclassified,
+                    None => println!("warning: rule '{}' can't change {:?} to {}", rule.rule.name, tx.operation, operation),
+                }
+            }
+            RuleAction::SetValue { value } => {
+                tx.value = Some(Amount::from_fiat(*value));
+            }
+            RuleAction::SetDescription { description } => {
+                tx.description = Some(description.clone());
+            }
+            RuleAction::Ignore => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Operation;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    fn send(quantity: Decimal, counterparty: &str) -> Transaction {
+        let mut tx = Transaction::send(parse_date_time("2023-01-01 10:00:00").unwrap(), Amount::new(quantity, "BTC".to_owned()));
+        tx.counterparty = Some(counterparty.to_owned());
+        tx
+    }
+
+    #[test]
+    fn reclassify_and_ignore() {
+        let rules = [
+            Rule {
+                name: "Donations".to_owned(),
+                conditions: RuleConditions {
+                    operation: Some(OperationType::Send),
+                    counterparty: Some("BC1QDONATE".to_owned()),
+                    ..Default::default()
+                },
+                action: RuleAction::Reclassify { operation: OperationType::OutgoingGift },
+            },
+            Rule {
+                name: "Dust".to_owned(),
+                conditions: RuleConditions {
+                    currency: Some("BTC".to_owned()),
+                    max_amount: Some(dec!(0.0001)),
+                    ..Default::default()
+                },
+                action: RuleAction::Ignore,
+            },
+        ];
+        let compiled: Vec<CompiledRule> = rules.iter().map(CompiledRule::new).collect();
+
+        let mut transactions = vec![
+            send(dec!(0.5), "bc1qdonate"),
+            send(dec!(0.00001), "bc1qother"),
+            send(dec!(0.5), "bc1qother"),
+        ];
+        let mut affected = vec![Vec::new(); rules.len()];
+        transactions.retain_mut(|tx| apply_rules(&compiled, "BitcoinAddress", "Wallet", tx, &mut affected));
+
+        assert_eq!(transactions.len(), 2);
+        assert!(matches!(transactions[0].operation, Operation::OutgoingGift(_)));
+        assert!(transactions[1].operation.is_send());
+        assert_eq!(affected[0].len(), 1);
+        assert!(affected[0][0].operation.is_send());
+        assert_eq!(affected[1].len(), 1);
+    }
+
+    #[test]
+    fn match_description_and_source() {
+        let rule = Rule {
+            name: "Rewards".to_owned(),
+            conditions: RuleConditions {
+                source_type: Some("BitcoinDeCsv".to_owned()),
+                description: Some("^reward".to_owned()),
+                ..Default::default()
+            },
+            action: RuleAction::SetDescription { description: "Bonus".to_owned() },
+        };
+        let compiled = [CompiledRule::new(&rule)];
+
+        let mut reward = send(dec!(1), "");
+        reward.description = Some("Reward for referral".to_owned());
+        let mut transactions = vec![reward.clone(), send(dec!(1), "")];
+        let mut affected = vec![Vec::new()];
+
+        transactions.retain_mut(|tx| apply_rules(&compiled, "BitcoinDeCsv", "Wallet", tx, &mut affected));
+        assert_eq!(transactions[0].description.as_deref(), Some("Bonus"));
+        assert_eq!(transactions[1].description, None);
+        assert_eq!(affected[0].len(), 1);
+
+        let mut tx = reward;
+        assert!(apply_rules(&compiled, "BitstampCsv", "Wallet", &mut tx, &mut affected));
+        assert_eq!(tx.description.as_deref(), Some("Reward for referral"));
+    }
+}
@@ -0,0 +1,942 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use bitcoin::base58;
+use bitcoin::hashes::{sha256, Hash, HashEngine};
+use chrono::{DateTime, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{
+    backend::{BackendSettings, RateLimiter},
+    base::{Amount, AssetId, Operation, Transaction},
+    LoadFuture, SyncOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+pub(crate) const DEFAULT_SOLANA_URL: &str = "https://api.mainnet-beta.solana.com";
+
+const SIGNATURES_LIMIT: usize = 1000;
+const REWARD_EPOCHS_PER_REQUEST: usize = 10;
+const SOL_SCALE: u32 = 9;
+const STAKE_PROGRAM: &str = "Stake11111111111111111111111111111111111111";
+const WRAPPED_SOL: &str = "So11111111111111111111111111111111111111112";
+const TOKEN_PROGRAMS: &[&str] = &[
+    "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA",
+    "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb",  // Token-2022
+];
+const METADATA_PROGRAM: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s";
+// The highest bump seeds tried when deriving the Metaplex metadata address.
+// Each is off the curve with a chance of about one half.
+const METADATA_BUMPS: std::ops::RangeInclusive<u8> = 248..=255;
+// The name and symbol of Metaplex metadata, which follow its key, update
+// authority and mint. Both are stored as padded strings of at most 32 and 10
+// bytes, each preceded by its length.
+const METADATA_NAME_OFFSET: usize = 1 + 32 + 32;
+const METADATA_NAME_AND_SYMBOL_LENGTH: usize = 4 + 32 + 4 + 10;
+
+// Programs through which swaps are made, with the protocol they belong to.
+// Aggregators come first, since they route swaps through the others.
+const SWAP_PROGRAMS: &[(&str, &str)] = &[
+    ("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", "Jupiter"),
+    ("JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB", "Jupiter"),
+    ("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8", "Raydium"),    // AMM v4
+    ("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK", "Raydium"),    // Concentrated liquidity
+    ("CPMMoo8L3F4NbTegBCKVNunggL7H1ZpdTHKxQB5qKP1C", "Raydium"),    // Constant product
+];
+
+/// A Solana JSON-RPC client which spreads out its requests as configured.
+struct SolanaRpc {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+    rate_limiter: RateLimiter,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    id: usize,
+    #[serde(default)]
+    result: Value,
+    error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    fn into_result<T: DeserializeOwned>(self, method: &str) -> Result<T> {
+        if let Some(error) = self.error {
+            return Err(anyhow!("{} failed: {}", method, error.message));
+        }
+        Ok(serde_json::from_value(self.result)?)
+    }
+}
+
+impl SolanaRpc {
+    fn new(settings: &BackendSettings) -> Result<Self> {
+        Ok(Self {
+            client: settings.http_client()?,
+            url: settings.url_or(DEFAULT_SOLANA_URL).to_owned(),
+            api_key: settings.api_key.clone(),
+            rate_limiter: settings.rate_limiter(100),
+        })
+    }
+
+    fn request(&self, body: &Value) -> reqwest::RequestBuilder {
+        let request = self.client.post(&self.url).json(body);
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        self.rate_limiter.wait().await;
+
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response: RpcResponse = self.request(&body).send().await?.error_for_status()?.json().await?;
+        response.into_result(method)
+    }
+
+    /// Calls the method once for each of the parameters, in a single batch
+    /// request. The results are returned in the order of the parameters.
+    async fn call_batch<T: DeserializeOwned>(&self, method: &str, params: Vec<Value>) -> Result<Vec<T>> {
+        if params.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.rate_limiter.wait().await;
+
+        let count = params.len();
+        let body: Vec<Value> = params.into_iter().enumerate()
+            .map(|(id, params)| json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .collect();
+        let mut responses: Vec<RpcResponse> = self.request(&json!(body)).send().await?.error_for_status()?.json().await?;
+        if responses.len() != count {
+            return Err(anyhow!("{} returned {} of {} results", method, responses.len(), count));
+        }
+
+        // responses to a batch may arrive in any order
+        responses.sort_by_key(|response| response.id);
+        responses.into_iter().map(|response| response.into_result(method)).collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignatureInfo {
+    signature: String,
+    slot: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcList<T> {
+    value: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyedAccount {
+    pubkey: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionResponse {
+    slot: u64,
+    block_time: Option<i64>,
+    meta: TransactionMeta,
+    transaction: EncodedTransaction,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransactionMeta {
+    err: Option<Value>,
+    fee: u64,
+    pre_balances: Vec<u64>,
+    post_balances: Vec<u64>,
+    #[serde(default)]
+    pre_token_balances: Vec<TokenBalance>,
+    #[serde(default)]
+    post_token_balances: Vec<TokenBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenBalance {
+    account_index: usize,
+    mint: String,
+    owner: Option<String>,
+    ui_token_amount: TokenAmount,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenAmount {
+    amount: String,
+    decimals: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct EncodedTransaction {
+    message: Message,
+    signatures: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Message {
+    account_keys: Vec<AccountKey>,
+    instructions: Vec<Instruction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountKey {
+    pubkey: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Instruction {
+    program_id: String,
+    parsed: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EpochInfo {
+    epoch: u64,
+    slots_in_epoch: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InflationReward {
+    amount: u64,
+    effective_slot: u64,
+}
+
+/// The change in SOL of an account, or in a token of an owner.
+struct BalanceChange<'a> {
+    account: &'a str,
+    mint: Option<&'a str>,
+    change: i128,
+    decimals: u32,
+}
+
+fn lamports_to_amount(lamports: i128) -> Result<Amount> {
+    let lamports = i64::try_from(lamports).context("amount of lamports out of range")?;
+    Ok(Amount::new(Decimal::new(lamports, SOL_SCALE), "SOL".to_owned()))
+}
+
+fn token_amount(amount: i128, decimals: u32, mint: &str, symbols: &HashMap<String, String>) -> Result<Amount> {
+    let mut quantity = Decimal::from_i128_with_scale(amount, 0);
+    quantity.set_scale(decimals)?;
+    let symbol = symbols.get(mint).cloned().unwrap_or_else(|| short_address(mint));
+    Ok(Amount::new_asset(quantity, symbol, AssetId::new("solana", mint)))
+}
+
+/// Shortens an address to its first and last four characters, for naming
+/// tokens of which no symbol is known.
+fn short_address(address: &str) -> String {
+    match (address.get(..4), address.get(address.len().saturating_sub(4)..)) {
+        (Some(start), Some(end)) if address.len() > 8 => format!("{}...{}", start, end),
+        _ => address.to_owned(),
+    }
+}
+
+fn parse_symbol(symbol: &str) -> Option<String> {
+    let symbol = symbol.trim_end_matches('\0').trim();
+    (!symbol.is_empty()).then(|| symbol.to_owned())
+}
+
+/// Returns the symbol from the metadata the Token-2022 program stores in the
+/// mint account.
+async fn token_2022_symbol(rpc: &SolanaRpc, mint: &str) -> Result<Option<String>> {
+    let account: Value = rpc.call("getAccountInfo", json!([mint, { "encoding": "jsonParsed" }])).await?;
+    Ok(account.pointer("/value/data/parsed/info/extensions")
+        .and_then(Value::as_array)
+        .and_then(|extensions| extensions.iter().find(|extension| extension["extension"] == "tokenMetadata"))
+        .and_then(|metadata| metadata.pointer("/state/symbol")?.as_str())
+        .and_then(parse_symbol))
+}
+
+/// Returns the candidates for the address of the Metaplex metadata account of
+/// the mint, starting with the highest bump seed. This program derived address
+/// is the first candidate that is off the ed25519 curve.
+fn metadata_address_candidates(mint: &str) -> Result<Vec<String>> {
+    let program = base58::decode(METADATA_PROGRAM)?;
+    let mint = base58::decode(mint)?;
+    Ok(METADATA_BUMPS.rev().map(|bump| {
+        let mut engine = sha256::Hash::engine();
+        for seed in [b"metadata".as_slice(), &program, &mint, &[bump], &program, b"ProgramDerivedAddress"] {
+            engine.input(seed);
+        }
+        base58::encode(sha256::Hash::from_engine(engine).as_byte_array())
+    }).collect())
+}
+
+/// Returns the symbol from the Metaplex metadata of the mint, which most
+/// tokens of the original token program use.
+async fn metaplex_symbol(rpc: &SolanaRpc, mint: &str) -> Result<Option<String>> {
+    // Rather than checking which candidate is off the curve, look up all of
+    // them. Only the metadata account can be owned by the metadata program.
+    let candidates = metadata_address_candidates(mint)?;
+    let accounts: RpcList<Option<Value>> = rpc.call("getMultipleAccounts", json!([candidates, {
+        "encoding": "base58",
+        "dataSlice": { "offset": METADATA_NAME_OFFSET, "length": METADATA_NAME_AND_SYMBOL_LENGTH },
+    }])).await?;
+    let Some(data) = accounts.value.iter().flatten()
+        .find(|account| account["owner"] == METADATA_PROGRAM)
+        .and_then(|account| account.pointer("/data/0")?.as_str()) else {
+        return Ok(None);
+    };
+
+    let data = base58::decode(data)?;
+    let string_at = |offset: usize| -> Option<&[u8]> {
+        let length = u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize;
+        data.get(offset + 4..offset + 4 + length)
+    };
+    let symbol = string_at(0)
+        .and_then(|name| string_at(4 + name.len()))
+        .context("invalid Metaplex metadata")?;
+    Ok(parse_symbol(&String::from_utf8_lossy(symbol)))
+}
+
+/// Looks up the symbols of the given token mints. Well-known tokens use their
+/// usual currency, others the symbol from their Token-2022 or Metaplex
+/// metadata. Tokens without a symbol are left out.
+async fn token_symbols(rpc: &SolanaRpc, mints: HashSet<&str>) -> HashMap<String, String> {
+    let mut symbols = HashMap::new();
+
+    for mint in mints {
+        if let Some(currency) = AssetId::new("solana", mint).known_currency() {
+            symbols.insert(mint.to_owned(), currency.to_owned());
+            continue;
+        }
+
+        let symbol = match token_2022_symbol(rpc, mint).await {
+            Ok(None) => metaplex_symbol(rpc, mint).await,
+            result => result,
+        };
+        match symbol {
+            Ok(Some(symbol)) => { symbols.insert(mint.to_owned(), symbol); }
+            Ok(None) => {}
+            Err(err) => println!("Solana: failed to load token metadata of {}: {}", mint, err),
+        }
+    }
+
+    symbols
+}
+
+fn block_time(timestamp: i64) -> Result<NaiveDateTime> {
+    DateTime::from_timestamp(timestamp, 0).map(|dt| dt.naive_utc()).context("invalid block time")
+}
+
+// Returns whether any of the values, also in nested objects, is the address
+fn mentions(value: &Value, address: &str) -> bool {
+    match value {
+        Value::String(string) => string == address,
+        Value::Object(object) => object.values().any(|value| mentions(value, address)),
+        _ => false,
+    }
+}
+
+impl TransactionResponse {
+    fn account_keys(&self) -> impl Iterator<Item = &str> {
+        self.transaction.message.account_keys.iter().map(|key| key.pubkey.as_str())
+    }
+
+    /// Returns the stake accounts for which the address is an authority.
+    fn stake_accounts(&self, address: &str) -> Vec<String> {
+        self.transaction.message.instructions.iter()
+            .filter(|instruction| instruction.program_id == STAKE_PROGRAM)
+            .filter_map(|instruction| instruction.parsed.as_ref()?.get("info"))
+            .filter(|info| mentions(info, address))
+            .filter_map(|info| info.get("stakeAccount")?.as_str().map(str::to_owned))
+            .collect()
+    }
+
+    /// Returns the given accounts together with the token accounts they own.
+    fn own_accounts<'a>(&'a self, owned: &'a HashSet<String>) -> HashSet<&'a str> {
+        let account_keys: Vec<&str> = self.account_keys().collect();
+        let token_accounts = self.meta.pre_token_balances.iter()
+            .chain(self.meta.post_token_balances.iter())
+            .filter(|balance| balance.owner.as_ref().is_some_and(|owner| owned.contains(owner)))
+            .filter_map(|balance| account_keys.get(balance.account_index).copied());
+        owned.iter().map(String::as_str).chain(token_accounts).collect()
+    }
+
+    /// Returns the change in SOL for each account and the change in each
+    /// token for each owner.
+    ///
+    /// Wrapped SOL is left out, since it is already included in the balance
+    /// of its token account.
+    fn balance_changes(&self) -> Result<Vec<BalanceChange<'_>>> {
+        let meta = &self.meta;
+        let mut changes = Vec::new();
+
+        for (index, account) in self.account_keys().enumerate() {
+            let pre = *meta.pre_balances.get(index).context("missing balance")? as i128;
+            let post = *meta.post_balances.get(index).context("missing balance")? as i128;
+            if pre != post {
+                changes.push(BalanceChange { account, mint: None, change: post - pre, decimals: SOL_SCALE });
+            }
+        }
+
+        let token_balances = meta.pre_token_balances.iter().map(|balance| (balance, -1))
+            .chain(meta.post_token_balances.iter().map(|balance| (balance, 1)));
+        for (balance, sign) in token_balances {
+            let Some(owner) = balance.owner.as_deref() else { continue };
+            if balance.mint == WRAPPED_SOL {
+                continue;
+            }
+            let amount = sign * i128::from_str(&balance.ui_token_amount.amount)?;
+            match changes.iter_mut().find(|change| change.account == owner && change.mint == Some(balance.mint.as_str())) {
+                Some(change) => change.change += amount,
+                None => changes.push(BalanceChange {
+                    account: owner,
+                    mint: Some(&balance.mint),
+                    change: amount,
+                    decimals: balance.ui_token_amount.decimals,
+                }),
+            }
+        }
+
+        changes.retain(|change| change.change != 0);
+        Ok(changes)
+    }
+
+    /// Returns the mints of the tokens of which balances changed.
+    fn mints(&self) -> impl Iterator<Item = &str> {
+        self.meta.pre_token_balances.iter()
+            .chain(self.meta.post_token_balances.iter())
+            .map(|balance| balance.mint.as_str())
+            .filter(|mint| *mint != WRAPPED_SOL)
+    }
+
+    fn to_transactions(&self, address: &str, owned: &HashSet<String>, symbols: &HashMap<String, String>) -> Result<Vec<Transaction>> {
+        let timestamp = block_time(self.block_time.context("missing block time")?)?;
+        let fee_payer = self.account_keys().next().context("missing fee payer")?;
+        let fee = lamports_to_amount(self.meta.fee as i128)?;
+        let paid_by_us = fee_payer == address;
+
+        let mut operations: Vec<(Operation, Option<String>)> = Vec::new();
+
+        // Failed transactions only charge the fee
+        if self.meta.err.is_none() {
+            let changes = self.balance_changes()?;
+            let owned = self.own_accounts(owned);
+
+            // Sum up the changes to our own accounts, including our stake
+            // accounts, per currency
+            let mut own_changes: Vec<(Option<&str>, i128, u32)> = Vec::new();
+            for change in changes.iter().filter(|change| owned.contains(change.account)) {
+                match own_changes.iter_mut().find(|(mint, _, _)| *mint == change.mint) {
+                    Some((_, own_change, _)) => *own_change += change.change,
+                    None => own_changes.push((change.mint, change.change, change.decimals)),
+                }
+            }
+            if paid_by_us {
+                // the fee is recorded separately
+                match own_changes.iter_mut().find(|(mint, _, _)| mint.is_none()) {
+                    Some((_, change, _)) => *change += self.meta.fee as i128,
+                    None => own_changes.push((None, self.meta.fee as i128, SOL_SCALE)),
+                }
+            }
+            own_changes.retain(|(_, change, _)| *change != 0);
+
+            // The counterparty is the account with the largest opposite change
+            let counterparty = |mint: Option<&str>, change: i128| {
+                changes.iter()
+                    .filter(|other| !owned.contains(other.account) && other.mint == mint && other.change.signum() == -change.signum())
+                    .max_by_key(|other| other.change.abs())
+                    .map(|other| other.account.to_owned())
+            };
+
+            let mut incoming = Vec::new();
+            let mut outgoing = Vec::new();
+            for (mint, change, decimals) in own_changes {
+                let amount = match mint {
+                    Some(mint) => token_amount(change.abs(), decimals, mint, symbols)?,
+                    None => lamports_to_amount(change.abs())?,
+                };
+                if change > 0 {
+                    incoming.push((amount, counterparty(mint, change)));
+                } else {
+                    outgoing.push((amount, counterparty(mint, change)));
+                }
+            }
+
+            if incoming.len() == 1 && outgoing.len() == 1 {
+                operations.push((Operation::Trade { incoming: incoming.remove(0).0, outgoing: outgoing.remove(0).0 }, None));
+            } else {
+                operations.extend(incoming.into_iter().map(|(amount, counterparty)| (Operation::Receive(amount), counterparty)));
+                operations.extend(outgoing.into_iter().map(|(amount, counterparty)| (Operation::Send(amount), counterparty)));
+            }
+        }
+
+        let protocol = SWAP_PROGRAMS.iter()
+            .find(|(program, _)| self.account_keys().any(|key| key == *program))
+            .map(|(_, protocol)| *protocol);
+
+        let mut transactions: Vec<Transaction> = operations.into_iter().map(|(operation, counterparty)| {
+            let mut transaction = Transaction::new(timestamp, operation);
+            transaction.counterparty = counterparty;
+            if let (Operation::Trade { .. }, Some(protocol)) = (&transaction.operation, protocol) {
+                transaction.description = Some(format!("{} swap", protocol));
+            }
+            transaction
+        }).collect();
+
+        // The fee is attached to the first operation, or recorded on its own
+        if paid_by_us && !fee.is_zero() {
+            match transactions.first_mut() {
+                Some(transaction) => transaction.fee = Some(fee),
+                None => transactions.push(Transaction::fee(timestamp, fee)),
+            }
+        }
+
+        for transaction in &mut transactions {
+            transaction.tx_hash = self.transaction.signatures.first().cloned();
+            transaction.blockchain = Some("SOL".to_owned());
+        }
+
+        Ok(transactions)
+    }
+}
+
+async fn address_signatures(rpc: &SolanaRpc, address: &str) -> Result<Vec<SignatureInfo>> {
+    let mut signatures = Vec::new();
+    let mut before: Option<String> = None;
+
+    loop {
+        let mut options = json!({ "limit": SIGNATURES_LIMIT });
+        if let Some(before) = &before {
+            options["before"] = json!(before);
+        }
+
+        let page: Vec<SignatureInfo> = rpc.call("getSignaturesForAddress", json!([address, options])).await?;
+        match page.last() {
+            Some(last) => before = Some(last.signature.clone()),
+            None => break,
+        }
+        signatures.extend(page);
+    }
+
+    // signatures are returned newest first
+    signatures.reverse();
+    Ok(signatures)
+}
+
+/// Returns the token accounts owned by the address. A token transfer to one
+/// of these accounts does not necessarily mention the address itself, so
+/// their signatures are loaded as well.
+async fn token_accounts(rpc: &SolanaRpc, address: &str) -> Result<Vec<String>> {
+    let mut accounts = Vec::new();
+    for program in TOKEN_PROGRAMS {
+        let response: RpcList<KeyedAccount> = rpc.call("getTokenAccountsByOwner", json!([address, { "programId": program }, {
+            "encoding": "base64",
+            "dataSlice": { "offset": 0, "length": 0 },
+        }])).await?;
+        accounts.extend(response.value.into_iter().map(|account| account.pubkey));
+    }
+    Ok(accounts)
+}
+
+/// Loads the inflation rewards paid to the given stake accounts, from the
+/// epoch in which the first of them was created.
+async fn stake_rewards(rpc: &SolanaRpc, stake_accounts: &HashMap<String, u64>) -> Result<Vec<Transaction>> {
+    let Some(first_slot) = stake_accounts.values().min() else {
+        return Ok(Vec::new());
+    };
+
+    let epoch_info: EpochInfo = rpc.call("getEpochInfo", json!([])).await?;
+    let accounts: Vec<&String> = stake_accounts.keys().collect();
+    let mut transactions = Vec::new();
+
+    println!("Loading staking rewards for {} stake accounts...", accounts.len());
+
+    // rewards for an epoch are paid at the start of the next one
+    let epochs: Vec<u64> = (first_slot / epoch_info.slots_in_epoch..epoch_info.epoch).collect();
+    for chunk in epochs.chunks(REWARD_EPOCHS_PER_REQUEST) {
+        let params = chunk.iter().map(|epoch| json!([accounts, { "epoch": epoch }])).collect();
+        let results: Vec<Vec<Option<InflationReward>>> = rpc.call_batch("getInflationReward", params).await?;
+
+        // the rewards are listed in the order of the accounts
+        let mut paid_epochs = Vec::new();
+        for (&epoch, rewards) in chunk.iter().zip(results) {
+            let rewards: Vec<(&String, InflationReward)> = accounts.iter().copied().zip(rewards)
+                .filter_map(|(account, reward)| Some((account, reward?)))
+                .filter(|(_, reward)| reward.amount > 0)
+                .collect();
+            if let Some((_, reward)) = rewards.first() {
+                paid_epochs.push((epoch, reward.effective_slot, rewards));
+            }
+        }
+
+        let params = paid_epochs.iter().map(|(_, effective_slot, _)| json!([effective_slot])).collect();
+        let timestamps: Vec<Option<i64>> = rpc.call_batch("getBlockTime", params).await?;
+
+        for ((epoch, effective_slot, rewards), timestamp) in paid_epochs.into_iter().zip(timestamps) {
+            let timestamp = block_time(timestamp.context("missing block time")?)?;
+            for (account, reward) in rewards {
+                let mut transaction = Transaction::new(timestamp, Operation::Staking(lamports_to_amount(reward.amount as i128)?));
+                // rewards are not paid by a transaction, so refer to the stake
+                // account and the block in which they were paid
+                transaction.description = Some(format!("Staking reward for epoch {} to {} in slot {}", epoch, account, effective_slot));
+                transaction.blockchain = Some("SOL".to_owned());
+                transactions.push(transaction);
+            }
+        }
+    }
+
+    Ok(transactions)
+}
+
+pub(crate) async fn address_transactions(settings: &BackendSettings, address: &str) -> Result<Vec<Transaction>> {
+    let rpc = SolanaRpc::new(settings)?;
+
+    println!("Loading signatures for {}...", address);
+    let mut signatures = address_signatures(&rpc, address).await?;
+    for account in token_accounts(&rpc, address).await? {
+        println!("Loading signatures for token account {}...", account);
+        signatures.extend(address_signatures(&rpc, &account).await?);
+    }
+
+    // transactions involving several of our accounts are loaded only once
+    let mut seen = HashSet::new();
+    signatures.retain(|info| seen.insert(info.signature.clone()));
+    signatures.sort_by_key(|info| info.slot);
+
+    println!("Loading {} transactions...", signatures.len());
+    let mut responses = Vec::new();
+    for SignatureInfo { signature, .. } in &signatures {
+        let response: Option<TransactionResponse> = rpc.call("getTransaction", json!([signature, {
+            "encoding": "jsonParsed",
+            "maxSupportedTransactionVersion": 0,
+        }])).await?;
+        match response {
+            Some(response) => responses.push(response),
+            None => println!("transaction {} not found", signature),
+        }
+    }
+
+    // Balances of our stake accounts are considered our own, with the slot
+    // at which each of them first appeared
+    let mut stake_accounts: HashMap<String, u64> = HashMap::new();
+    for response in &responses {
+        for stake_account in response.stake_accounts(address) {
+            stake_accounts.entry(stake_account).or_insert(response.slot);
+        }
+    }
+    let mut owned: HashSet<String> = stake_accounts.keys().cloned().collect();
+    owned.insert(address.to_owned());
+
+    let symbols = token_symbols(&rpc, responses.iter().flat_map(TransactionResponse::mints).collect()).await;
+
+    let mut transactions = Vec::new();
+    for response in &responses {
+        match response.to_transactions(address, &owned, &symbols) {
+            Ok(txs) => transactions.extend(txs),
+            Err(err) => println!("{:?}: {:?}", err, response.transaction.signatures),
+        }
+    }
+
+    transactions.extend(stake_rewards(&rpc, &stake_accounts).await?);
+
+    Ok(transactions)
+}
+
+pub(crate) fn load_solana_address_async(source_path: String, options: SyncOptions) -> LoadFuture {
+    Box::pin(async move { address_transactions(&options.backends.solana, source_path.trim()).await })
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static SOLANA_ADDRESS: TransactionSource = TransactionSource {
+    id: "SolanaAddress",
+    label: "Solana Address",
+    load_async: Some(load_solana_address_async),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    const OWN: &str = "7xKXtg2CW87d97TXJSDpbD5jBkheTqA83TZRuJosgAsU";
+    const OTHER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+    const OWN_USDC: &str = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+    const OTHER_USDC: &str = "3emsAVdmGKERbHjmGfQ6oZ1e35dkf5iYcS6U4CPKFVaa";
+    const POOL: &str = "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2";
+    const POOL_USDC: &str = "HLmqeL62xR1QoZ1HKKbXRrdN1p3phKpxRMb2VVopvBBz";
+    const STAKE: &str = "3ZT31jkAGhUaw8jsy4bTknwBMP8i4Eueh52By4zXcsVw";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+    const SLOTS_IN_EPOCH: u64 = 432000;
+
+    fn transaction(signature: &str, slot: u64, accounts: &[&str], balances: (Vec<u64>, Vec<u64>), token_balances: (Value, Value), instructions: Value) -> Value {
+        json!({
+            "slot": slot,
+            "blockTime": 1700000000 + slot - 600 * SLOTS_IN_EPOCH,
+            "meta": {
+                "err": null,
+                "fee": 5000,
+                "preBalances": balances.0,
+                "postBalances": balances.1,
+                "preTokenBalances": token_balances.0,
+                "postTokenBalances": token_balances.1,
+            },
+            "transaction": {
+                "message": {
+                    "accountKeys": accounts.iter().map(|key| json!({ "pubkey": key, "signer": false, "writable": true })).collect::<Vec<_>>(),
+                    "instructions": instructions,
+                },
+                "signatures": [signature],
+            },
+        })
+    }
+
+    fn token_balance(account_index: usize, owner: &str, amount: &str) -> Value {
+        json!({
+            "accountIndex": account_index,
+            "mint": USDC,
+            "owner": owner,
+            "uiTokenAmount": { "amount": amount, "decimals": 6 },
+        })
+    }
+
+    fn rpc_result(result: Value) -> String {
+        json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string()
+    }
+
+    fn sol(lamports: i64) -> Amount {
+        Amount::new(Decimal::new(lamports, 9), "SOL".to_owned())
+    }
+
+    #[tokio::test]
+    async fn sync_address_transactions() {
+        let mut server = mockito::Server::new_async().await;
+        let slot = 600 * SLOTS_IN_EPOCH;
+
+        let receive = transaction("S1", slot + 1, &[OTHER, OWN, SYSTEM_PROGRAM],
+            (vec![5_000_000_000, 1_000_000_000, 1], vec![3_999_995_000, 2_000_000_000, 1]),
+            (json!([]), json!([])), json!([]));
+
+        let swap = transaction("S2", slot + 2, &[OWN, POOL, OWN_USDC, POOL_USDC, "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4"],
+            (vec![2_000_000_000, 10_000_000_000, 2_039_280, 2_039_280, 1], vec![1_499_995_000, 10_500_000_000, 2_039_280, 2_039_280, 1]),
+            (json!([token_balance(2, OWN, "0"), token_balance(3, POOL, "1000000000")]),
+             json!([token_balance(2, OWN, "75000000"), token_balance(3, POOL, "925000000")])),
+            json!([]));
+
+        let stake = transaction("S3", slot + 3, &[OWN, STAKE, SYSTEM_PROGRAM, STAKE_PROGRAM],
+            (vec![1_499_995_000, 0, 1, 1], vec![499_990_000, 1_000_000_000, 1, 1]),
+            (json!([]), json!([])),
+            json!([
+                { "programId": SYSTEM_PROGRAM, "parsed": { "type": "createAccount", "info": { "source": OWN, "newAccount": STAKE, "lamports": 1_000_000_000 } } },
+                { "programId": STAKE_PROGRAM, "parsed": { "type": "initialize", "info": { "stakeAccount": STAKE, "authorized": { "staker": OWN, "withdrawer": OWN } } } },
+            ]));
+
+        // a token transfer to our associated token account, which doesn't
+        // mention our address
+        let token_receive = transaction("S4", slot + 4, &[OTHER, OTHER_USDC, OWN_USDC, TOKEN_PROGRAMS[0]],
+            (vec![3_999_995_000, 2_039_280, 2_039_280, 1], vec![3_999_990_000, 2_039_280, 2_039_280, 1]),
+            (json!([token_balance(1, OTHER, "100000000"), token_balance(2, OWN, "75000000")]),
+             json!([token_balance(1, OTHER, "75000000"), token_balance(2, OWN, "100000000")])),
+            json!([]));
+
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getSignaturesForAddress", "params": [OWN] })))
+            .with_body(rpc_result(json!([
+                { "signature": "S3", "slot": slot + 3 },
+                { "signature": "S2", "slot": slot + 2 },
+                { "signature": "S1", "slot": slot + 1 },
+            ])))
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getSignaturesForAddress", "params": [OWN, { "before": "S1" }] })))
+            .with_body(rpc_result(json!([])))
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getTokenAccountsByOwner", "params": [OWN, { "programId": TOKEN_PROGRAMS[0] }] })))
+            .with_body(rpc_result(json!({ "context": { "slot": slot + 10 }, "value": [{ "pubkey": OWN_USDC, "account": {} }] })))
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getTokenAccountsByOwner", "params": [OWN, { "programId": TOKEN_PROGRAMS[1] }] })))
+            .with_body(rpc_result(json!({ "context": { "slot": slot + 10 }, "value": [] })))
+            .create_async()
+            .await;
+        // the swap also involves the token account, but is loaded only once
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getSignaturesForAddress", "params": [OWN_USDC] })))
+            .with_body(rpc_result(json!([
+                { "signature": "S4", "slot": slot + 4 },
+                { "signature": "S2", "slot": slot + 2 },
+            ])))
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getSignaturesForAddress", "params": [OWN_USDC, { "before": "S2" }] })))
+            .with_body(rpc_result(json!([])))
+            .create_async()
+            .await;
+        let mut loads = Vec::new();
+        for (signature, response) in [("S1", receive), ("S2", swap), ("S3", stake), ("S4", token_receive)] {
+            loads.push(server.mock("POST", "/")
+                .match_body(Matcher::PartialJson(json!({ "method": "getTransaction", "params": [signature] })))
+                .with_body(rpc_result(response))
+                .expect(1)
+                .create_async()
+                .await);
+        }
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getEpochInfo" })))
+            .with_body(rpc_result(json!({ "epoch": 602, "slotsInEpoch": SLOTS_IN_EPOCH, "absoluteSlot": 602 * SLOTS_IN_EPOCH + 10 })))
+            .create_async()
+            .await;
+        // the rewards of all epochs are requested at once, and the responses
+        // may arrive in any order
+        let rewards = server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!([
+                { "id": 0, "method": "getInflationReward", "params": [[STAKE], { "epoch": 600 }] },
+                { "id": 1, "method": "getInflationReward", "params": [[STAKE], { "epoch": 601 }] },
+            ])))
+            .with_body(json!([
+                { "jsonrpc": "2.0", "id": 1, "result": [{ "epoch": 601, "effectiveSlot": 602 * SLOTS_IN_EPOCH + 1, "amount": 2_000_000, "postBalance": 1_002_000_000, "commission": 5 }] },
+                { "jsonrpc": "2.0", "id": 0, "result": [null] },
+            ]).to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!([{ "method": "getBlockTime", "params": [602 * SLOTS_IN_EPOCH + 1] }])))
+            .with_body(json!([{ "jsonrpc": "2.0", "id": 0, "result": 1700400000 }]).to_string())
+            .create_async()
+            .await;
+
+        let settings = BackendSettings {
+            url: Some(server.url()),
+            min_interval_ms: Some(0),
+            ..Default::default()
+        };
+        let transactions = address_transactions(&settings, OWN).await.unwrap();
+        let operations: Vec<(&Operation, Option<&Amount>, Option<&str>)> = transactions.iter()
+            .map(|tx| (&tx.operation, tx.fee.as_ref(), tx.counterparty.as_deref()))
+            .collect();
+
+        rewards.assert_async().await;
+        for load in loads {
+            load.assert_async().await;
+        }
+
+        let usdc = Amount::new_asset(Decimal::from(75), "USDC".to_owned(), AssetId::new("solana", USDC));
+        assert_eq!(usdc.effective_currency(), "USDC");
+        assert_eq!(operations, vec![
+            (&Operation::Receive(sol(1_000_000_000)), None, Some(OTHER)),
+            (&Operation::Trade { incoming: usdc.clone(), outgoing: sol(500_000_000) }, Some(&sol(5000)), None),
+            (&Operation::Fee(sol(5000)), None, None),
+            (&Operation::Receive(Amount::new_asset(Decimal::from(25), "USDC".to_owned(), AssetId::new("solana", USDC))), None, Some(OTHER)),
+            (&Operation::Staking(sol(2_000_000)), None, None),
+        ]);
+        assert_eq!(transactions[1].description.as_deref(), Some("Jupiter swap"));
+        assert_eq!(transactions[1].tx_hash.as_deref(), Some("S2"));
+        assert_eq!(transactions[3].tx_hash.as_deref(), Some("S4"));
+        assert_eq!(transactions[4].description.as_deref(), Some(format!("Staking reward for epoch 601 to {} in slot {}", STAKE, 602 * SLOTS_IN_EPOCH + 1).as_str()));
+    }
+
+    #[tokio::test]
+    async fn token_symbols_from_metadata() {
+        const TOKEN_2022_MINT: &str = "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo";
+        const METAPLEX_MINT: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+        const UNNAMED_MINT: &str = "JUPyiwrYJFskUPiHa7hkeR8VUtAeFoSYbKedZNsDvCN";
+
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getAccountInfo", "params": [TOKEN_2022_MINT] })))
+            .with_body(rpc_result(json!({
+                "context": { "slot": 300000000 },
+                "value": {
+                    "data": {
+                        "parsed": {
+                            "info": {
+                                "decimals": 6,
+                                "extensions": [
+                                    { "extension": "metadataPointer", "state": { "metadataAddress": TOKEN_2022_MINT } },
+                                    { "extension": "tokenMetadata", "state": { "mint": TOKEN_2022_MINT, "name": "PayPal USD", "symbol": "PYUSD" } },
+                                ],
+                            },
+                            "type": "mint",
+                        },
+                        "program": "spl-token-2022",
+                    },
+                },
+            })))
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getAccountInfo", "params": [UNNAMED_MINT] })))
+            .with_body(rpc_result(json!({
+                "context": { "slot": 300000000 },
+                "value": { "data": { "parsed": { "info": { "decimals": 5 }, "type": "mint" }, "program": "spl-token" } },
+            })))
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getAccountInfo", "params": [METAPLEX_MINT] })))
+            .with_body(rpc_result(json!({
+                "context": { "slot": 300000000 },
+                "value": { "data": { "parsed": { "info": { "decimals": 5 }, "type": "mint" }, "program": "spl-token" } },
+            })))
+            .create_async()
+            .await;
+
+        // the metadata account of this mint has bump seed 250, while the
+        // address for bump 255 happens to be on the curve and used by a wallet
+        let candidates = metadata_address_candidates(METAPLEX_MINT).unwrap();
+        assert_eq!(candidates[5], "FDZZbyY9XGpL3CNKUZxLk3wFTTQYL3TkDiDzqxrizcPN");
+        let mut metadata = Vec::new();
+        for (string, padded_length) in [("Bonk", 32), ("Bonk", 10)] {
+            metadata.extend((padded_length as u32).to_le_bytes());
+            metadata.extend(string.as_bytes());
+            metadata.resize(metadata.len() + padded_length - string.len(), 0);
+        }
+        let mut accounts = vec![Value::Null; candidates.len()];
+        accounts[0] = json!({ "data": ["", "base58"], "owner": SYSTEM_PROGRAM, "lamports": 1000000, "executable": false });
+        accounts[5] = json!({ "data": [base58::encode(&metadata), "base58"], "owner": METADATA_PROGRAM, "lamports": 5616720, "executable": false });
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getMultipleAccounts", "params": [candidates] })))
+            .with_body(rpc_result(json!({ "context": { "slot": 300000000 }, "value": accounts })))
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "getMultipleAccounts", "params": [metadata_address_candidates(UNNAMED_MINT).unwrap()] })))
+            .with_body(rpc_result(json!({ "context": { "slot": 300000000 }, "value": vec![Value::Null; candidates.len()] })))
+            .create_async()
+            .await;
+
+        let settings = BackendSettings {
+            url: Some(server.url()),
+            min_interval_ms: Some(0),
+            ..Default::default()
+        };
+        let rpc = SolanaRpc::new(&settings).unwrap();
+        let symbols = token_symbols(&rpc, HashSet::from([TOKEN_2022_MINT, METAPLEX_MINT, UNNAMED_MINT, USDC])).await;
+        assert_eq!(symbols.get(TOKEN_2022_MINT).map(String::as_str), Some("PYUSD"));
+        assert_eq!(symbols.get(METAPLEX_MINT).map(String::as_str), Some("Bonk"));
+        assert_eq!(symbols.get(USDC).map(String::as_str), Some("USDC"));
+        assert_eq!(symbols.get(UNNAMED_MINT), None);
+
+        // tokens without a symbol are named after their mint
+        let amount = token_amount(100_000, 5, UNNAMED_MINT, &symbols).unwrap();
+        assert_eq!(amount.currency, "JUPy...DvCN");
+        assert_eq!(amount.quantity, Decimal::ONE);
+    }
+}
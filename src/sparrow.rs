@@ -0,0 +1,140 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::{
+    base::{Amount, Transaction},
+    CsvSpec, LoadOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+// struct for storing the following CSV columns:
+// Date (UTC),Label,Value,Balance,Fee,Txid
+#[derive(Debug, Deserialize)]
+struct SparrowTransaction {
+    /// Empty or "Unconfirmed" for transactions that are not yet confirmed.
+    #[serde(rename = "Date (UTC)")]
+    date: String,
+    #[serde(rename = "Label")]
+    label: String,
+    /// The change of the wallet balance, including the fee.
+    #[serde(rename = "Value")]
+    value: String,
+    #[serde(rename = "Fee")]
+    fee: String,
+    #[serde(rename = "Txid")]
+    txid: String,
+}
+
+/// Parses a value in the unit the wallet is configured to display. Values in
+/// BTC are always written with decimals, while values in sats never are.
+fn parse_btc(raw: &str) -> Result<Decimal> {
+    let raw = raw.trim().replace(',', "");
+    if raw.is_empty() {
+        return Ok(Decimal::ZERO);
+    }
+    let value: Decimal = raw.parse().with_context(|| format!("invalid value '{}'", raw))?;
+    Ok(if raw.contains('.') { value } else { value / Decimal::from(100_000_000) })
+}
+
+impl SparrowTransaction {
+    fn into_transaction(self) -> Result<Option<Transaction>> {
+        let date = self.date.trim();
+        if date.is_empty() || date == "Unconfirmed" {
+            return Ok(None);
+        }
+        let timestamp = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S")
+            .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M"))
+            .with_context(|| format!("invalid date '{}'", date))?;
+
+        let value = parse_btc(&self.value)?;
+        let fee = parse_btc(&self.fee)?.abs();
+
+        let mut tx = if value.is_sign_negative() {
+            let sent = -value - fee;
+            if sent.is_zero() {
+                // consolidation or other self-transfer, which only costs the fee
+                Transaction::fee(timestamp, Amount::new(fee, "BTC".to_owned()))
+            } else {
+                let mut tx = Transaction::send(timestamp, Amount::new(sent, "BTC".to_owned()));
+                if !fee.is_zero() {
+                    tx.fee = Some(Amount::new(fee, "BTC".to_owned()));
+                }
+                tx
+            }
+        } else {
+            Transaction::receive(timestamp, Amount::new(value, "BTC".to_owned()))
+        };
+
+        tx.tx_hash = Some(self.txid);
+        tx.blockchain = Some("BTC".to_owned());
+        if !self.label.is_empty() {
+            tx.description = Some(self.label);
+        }
+        Ok(Some(tx))
+    }
+}
+
+// loads a Sparrow Wallet transactions CSV file into a list of unified transactions
+fn load_sparrow_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(input_path)?;
+
+    let mut transactions = Vec::new();
+    for result in rdr.deserialize() {
+        let record: SparrowTransaction = result?;
+        transactions.extend(record.into_transaction()?);
+    }
+
+    transactions.sort_by_key(|tx| tx.timestamp);
+    Ok(transactions)
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static SPARROW_CSV: TransactionSource = TransactionSource {
+    id: "SparrowCsv",
+    label: "Sparrow Wallet (CSV)",
+    csv: &[CsvSpec::new(&[
+        "Date (UTC)",
+        "Label",
+        "Value",
+        "Balance",
+        "Fee",
+        "Txid",
+    ])],
+    load_sync: Some(load_sparrow_csv),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base::Operation;
+    use crate::time::parse_date_time;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn load_transactions() {
+        let path = Path::new("tests/data/sparrow.csv");
+        assert!(SPARROW_CSV.detect_from_file(path).unwrap());
+
+        let transactions = load_sparrow_csv(path, &LoadOptions { time_zone: chrono_tz::UTC, account: None, custom_csv: None }).unwrap();
+        assert_eq!(transactions.len(), 3);
+
+        let receive = &transactions[0];
+        assert_eq!(receive.timestamp, parse_date_time("2023-11-02 14:05:00").unwrap());
+        assert_eq!(receive.operation, Operation::Receive(Amount::new(dec!(0.015), "BTC".to_owned())));
+        assert_eq!(receive.description.as_deref(), Some("Salary November"));
+
+        let send = &transactions[1];
+        assert_eq!(send.operation, Operation::Send(Amount::new(dec!(0.005), "BTC".to_owned())));
+        assert_eq!(send.fee, Some(Amount::new(dec!(0.0000141), "BTC".to_owned())));
+        assert_eq!(send.tx_hash.as_deref(), Some("f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16"));
+
+        assert_eq!(transactions[2].operation, Operation::Fee(Amount::new(dec!(0.00000564), "BTC".to_owned())));
+    }
+}
@@ -1,4 +1,5 @@
-use chrono::NaiveDateTime;
+use chrono::{Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
 use serde::{Deserializer, Serializer, Deserialize};
 
 const FORMAT: &str = "%Y-%m-%d %H:%M:%S";
@@ -15,3 +16,46 @@ pub(crate) fn deserialize_date_time<'de, D: Deserializer<'de>>(d: D) -> std::res
 pub(crate) fn serialize_date_time<S: Serializer>(date: &NaiveDateTime, s: S) -> std::result::Result<S::Ok, S::Error> {
     s.serialize_str(&date.format(FORMAT).to_string())
 }
+
+/// Converts a local time in the given time zone to UTC.
+///
+/// Times that occur twice when the clocks are turned back resolve to the
+/// earlier occurrence. Times that are skipped when the clocks are turned
+/// forward use the offset from before the change. Both print a warning.
+pub(crate) fn local_to_utc(local: NaiveDateTime, time_zone: Tz) -> NaiveDateTime {
+    match time_zone.from_local_datetime(&local) {
+        LocalResult::Single(date_time) => date_time.naive_utc(),
+        LocalResult::Ambiguous(earliest, _) => {
+            println!("warning: ambiguous local time {} in {}, assuming {}", local, time_zone, earliest);
+            earliest.naive_utc()
+        }
+        LocalResult::None => {
+            // clock changes are months apart, so a day earlier uses the old offset
+            let offset = time_zone.offset_from_utc_datetime(&(local - Duration::days(1))).fix();
+            let utc = local - Duration::seconds(offset.local_minus_utc().into());
+            println!("warning: non-existent local time {} in {}, assuming {} UTC", local, time_zone, utc);
+            utc
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    #[test]
+    fn local_to_utc_around_dst() {
+        let utc = |local: &str, time_zone: Tz| local_to_utc(parse_date_time(local).unwrap(), time_zone);
+
+        assert_eq!(utc("2023-01-10 12:00:00", Berlin), parse_date_time("2023-01-10 11:00:00").unwrap());
+        assert_eq!(utc("2023-07-10 12:00:00", Berlin), parse_date_time("2023-07-10 10:00:00").unwrap());
+        assert_eq!(utc("2023-07-10 12:00:00", Tz::America__New_York), parse_date_time("2023-07-10 16:00:00").unwrap());
+
+        // clocks turned back from 03:00 to 02:00
+        assert_eq!(utc("2023-10-29 02:30:00", Berlin), parse_date_time("2023-10-29 00:30:00").unwrap());
+
+        // clocks turned forward from 02:00 to 03:00
+        assert_eq!(utc("2023-03-26 02:30:00", Berlin), parse_date_time("2023-03-26 01:30:00").unwrap());
+    }
+}
@@ -5,7 +5,7 @@ use chrono::DateTime;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Deserializer};
 
-use crate::{base::{Amount, Operation, Transaction}, CsvSpec, TransactionSource};
+use crate::{base::{Amount, Operation, Transaction}, CsvSpec, LoadOptions, TransactionSource};
 use linkme::distributed_slice;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -120,7 +120,7 @@ impl<'a> TryFrom<TrezorTransactionCsv<'a>> for Transaction {
 }
 
 // loads a TREZOR Suite CSV file into a list of unified transactions
-fn load_trezor_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_trezor_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     use std::fs::File;
     use std::io::{BufRead, BufReader};
 
@@ -337,7 +337,7 @@ impl TrezorTransaction {
 }
 
 // loads a TREZOR Suite JSON file into a list of unified transactions
-pub(crate) fn load_trezor_json(input_path: &Path) -> Result<Vec<Transaction>> {
+pub(crate) fn load_trezor_json(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut transactions = Vec::new();
 
     let json: TrezorWallet = serde_json::from_str(&std::fs::read_to_string(input_path)?)?;
@@ -372,17 +372,14 @@ static TREZOR_CSV: TransactionSource = TransactionSource {
         skip_lines: 0,
         trim: csv::Trim::None,
     }],
-    detect: None,
     load_sync: Some(load_trezor_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[distributed_slice(crate::TRANSACTION_SOURCES)]
 static TREZOR_JSON: TransactionSource = TransactionSource {
     id: "TrezorJson",
     label: "Trezor (JSON)",
-    csv: &[],
-    detect: None,
     load_sync: Some(load_trezor_json),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
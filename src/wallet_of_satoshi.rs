@@ -7,7 +7,7 @@ use serde::Deserialize;
 
 use crate::{
     base::{Amount, Transaction},
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -135,7 +135,7 @@ impl TryFrom<WalletOfSatoshiRecord> for Transaction {
     }
 }
 
-fn load_wallet_of_satoshi_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_wallet_of_satoshi_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut reader = csv::ReaderBuilder::new().from_path(input_path)?;
     let mut transactions = Vec::new();
 
@@ -159,9 +159,8 @@ static WALLET_OF_SATOSHI_CSV: TransactionSource = TransactionSource {
         CsvSpec::new(&WALLET_OF_SATOSHI_HEADERS),
         CsvSpec::new(&WALLET_OF_SATOSHI_NON_CUSTODIAL_HEADERS),
     ],
-    detect: None,
     load_sync: Some(load_wallet_of_satoshi_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 fn non_empty(value: &str) -> Option<&str> {
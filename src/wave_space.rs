@@ -10,7 +10,7 @@ use serde::Deserialize;
 use crate::{
     base::{Amount, Operation, Transaction},
     time::deserialize_date_time,
-    CsvSpec, TransactionSource,
+    CsvSpec, LoadOptions, TransactionSource,
 };
 use linkme::distributed_slice;
 
@@ -185,7 +185,7 @@ fn records_to_transactions(records: Vec<WaveSpaceRecord>) -> Result<Vec<Transact
     Ok(transactions)
 }
 
-fn load_wave_space_csv(input_path: &Path) -> Result<Vec<Transaction>> {
+fn load_wave_space_csv(input_path: &Path, _options: &LoadOptions) -> Result<Vec<Transaction>> {
     let mut reader = csv::ReaderBuilder::new()
         .trim(csv::Trim::Fields)
         .from_path(input_path)?;
@@ -203,9 +203,8 @@ static WAVE_SPACE_CSV: TransactionSource = TransactionSource {
     id: "WaveSpaceCsv",
     label: "wave.space (CSV)",
     csv: &[CsvSpec::new(&WAVE_SPACE_HEADERS)],
-    detect: None,
     load_sync: Some(load_wave_space_csv),
-    load_async: None,
+    ..TransactionSource::DEFAULT
 };
 
 #[cfg(test)]
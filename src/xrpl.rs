@@ -0,0 +1,472 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::DateTime;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    backend::BackendSettings,
+    base::{Amount, AssetId, Operation, Transaction},
+    LoadFuture, SyncOptions, TransactionSource,
+};
+use linkme::distributed_slice;
+
+pub(crate) const DEFAULT_RIPPLED_URL: &str = "https://s1.ripple.com:51234/";
+
+const PAGE_LIMIT: u32 = 200;
+const XRP_SCALE: u32 = 6;
+
+// Seconds between the Unix epoch and the Ripple epoch (2000-01-01)
+const RIPPLE_EPOCH: i64 = 946684800;
+
+// Allows the delivered amount to be less than the given amount
+const TF_PARTIAL_PAYMENT: u32 = 0x00020000;
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountTxResult {
+    transactions: Vec<AccountTransaction>,
+    marker: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountTransaction {
+    tx: TxJson,
+    meta: TxMeta,
+    validated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TxJson {
+    account: String,
+    transaction_type: String,
+    fee: String,
+    destination: Option<String>,
+    amount: Option<XrplAmount>,
+    #[serde(default)]
+    flags: u32,
+    #[serde(rename = "date")]
+    date: i64,
+    #[serde(rename = "hash")]
+    hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TxMeta {
+    transaction_result: String,
+    affected_nodes: Vec<AffectedNode>,
+    #[serde(rename = "delivered_amount")]
+    delivered_amount: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+enum AffectedNode {
+    #[serde(rename = "CreatedNode")]
+    Created(LedgerNode),
+    #[serde(rename = "ModifiedNode")]
+    Modified(LedgerNode),
+    #[serde(rename = "DeletedNode")]
+    Deleted(LedgerNode),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LedgerNode {
+    ledger_entry_type: String,
+    final_fields: Option<LedgerFields>,
+    new_fields: Option<LedgerFields>,
+    previous_fields: Option<LedgerFields>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LedgerFields {
+    account: Option<String>,
+    balance: Option<XrplAmount>,
+    high_limit: Option<IssuedAmount>,
+    low_limit: Option<IssuedAmount>,
+}
+
+/// Amounts of XRP are given in drops, other amounts are issued currencies.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum XrplAmount {
+    Drops(String),
+    Issued(IssuedAmount),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct IssuedAmount {
+    currency: String,
+    issuer: String,
+    value: String,
+}
+
+fn drops_to_amount(drops: &str) -> Result<Amount> {
+    let drops: i64 = drops.parse().with_context(|| format!("invalid amount of drops: {}", drops))?;
+    Ok(Amount::new(Decimal::new(drops, XRP_SCALE), "XRP".to_owned()))
+}
+
+fn parse_value(value: &str) -> Result<Decimal> {
+    Decimal::from_str(value).or_else(|_| Decimal::from_scientific(value))
+        .with_context(|| format!("invalid value: {}", value))
+}
+
+// Non-standard currency codes are 40 hexadecimal characters, usually
+// encoding an ASCII name padded with zeros.
+fn currency_code(currency: &str) -> String {
+    if currency.len() == 40 && currency.chars().all(|c| c.is_ascii_hexdigit()) {
+        let bytes: Vec<u8> = (0..40).step_by(2)
+            .filter_map(|i| u8::from_str_radix(&currency[i..i + 2], 16).ok())
+            .filter(|&byte| byte != 0)
+            .collect();
+        if let Ok(name) = String::from_utf8(bytes) {
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_graphic()) {
+                return name;
+            }
+        }
+    }
+    currency.to_owned()
+}
+
+fn issued_amount(quantity: Decimal, currency: &str, issuer: &str) -> Amount {
+    Amount::new_asset(quantity, currency_code(currency), AssetId::new("xrpl", issuer))
+}
+
+impl XrplAmount {
+    fn to_amount(&self) -> Result<Amount> {
+        match self {
+            XrplAmount::Drops(drops) => drops_to_amount(drops),
+            XrplAmount::Issued(amount) => Ok(issued_amount(parse_value(&amount.value)?, &amount.currency, &amount.issuer)),
+        }
+    }
+}
+
+fn add_change(changes: &mut Vec<Amount>, change: Amount) {
+    if change.is_zero() {
+        return;
+    }
+    match changes.iter_mut().find(|amount| amount.effective_currency() == change.effective_currency()) {
+        Some(amount) => amount.quantity += change.quantity,
+        None => changes.push(change),
+    }
+}
+
+/// Returns the changes to the balances of the address, from the ledger
+/// entries affected by a transaction.
+fn balance_changes(meta: &TxMeta, address: &str) -> Result<Vec<Amount>> {
+    let mut changes = Vec::new();
+
+    for node in &meta.affected_nodes {
+        let (node, created) = match node {
+            AffectedNode::Created(node) => (node, true),
+            AffectedNode::Modified(node) | AffectedNode::Deleted(node) => (node, false),
+        };
+        let Some(fields) = node.final_fields.as_ref().or(node.new_fields.as_ref()) else { continue };
+        let previous_balance = match &node.previous_fields {
+            Some(LedgerFields { balance: Some(balance), .. }) => Some(balance),
+            _ if created => None,
+            // the balance was not changed
+            _ => continue,
+        };
+
+        match node.ledger_entry_type.as_str() {
+            "AccountRoot" if fields.account.as_deref() == Some(address) => {
+                let balance = fields.balance.as_ref().context("missing balance of account")?.to_amount()?;
+                let previous = match previous_balance {
+                    Some(previous) => previous.to_amount()?.quantity,
+                    None => Decimal::ZERO,
+                };
+                add_change(&mut changes, Amount { quantity: balance.quantity - previous, ..balance });
+            }
+            "RippleState" => {
+                let (Some(XrplAmount::Issued(balance)), Some(high), Some(low)) = (&fields.balance, &fields.high_limit, &fields.low_limit) else {
+                    return Err(anyhow!("invalid trust line"));
+                };
+                let previous = match previous_balance {
+                    Some(XrplAmount::Issued(previous)) => parse_value(&previous.value)?,
+                    Some(_) => return Err(anyhow!("invalid trust line balance")),
+                    None => Decimal::ZERO,
+                };
+                let change = parse_value(&balance.value)? - previous;
+
+                // the balance is positive when the low account holds the currency
+                if low.issuer == address {
+                    add_change(&mut changes, issued_amount(change, &balance.currency, &high.issuer));
+                } else if high.issuer == address {
+                    add_change(&mut changes, issued_amount(-change, &balance.currency, &low.issuer));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(changes)
+}
+
+impl AccountTransaction {
+    /// The amount delivered by a payment, which for partial payments can
+    /// only be taken from the metadata.
+    fn delivered_amount(&self) -> Result<Option<Amount>> {
+        match &self.meta.delivered_amount {
+            Some(Value::String(unavailable)) if unavailable == "unavailable" => {}
+            Some(delivered_amount) => {
+                let amount: XrplAmount = serde_json::from_value(delivered_amount.clone())?;
+                return Ok(Some(amount.to_amount()?));
+            }
+            None => {}
+        }
+        if self.tx.flags & TF_PARTIAL_PAYMENT != 0 {
+            return Ok(None);
+        }
+        self.tx.amount.as_ref().map(XrplAmount::to_amount).transpose()
+    }
+
+    fn to_transactions(&self, address: &str) -> Result<Vec<Transaction>> {
+        let tx = &self.tx;
+        let timestamp = DateTime::from_timestamp(tx.date + RIPPLE_EPOCH, 0).context("invalid date")?.naive_utc();
+        let sent_by_us = tx.account == address;
+        let fee = drops_to_amount(&tx.fee)?;
+
+        let mut operations: Vec<(Operation, Option<&str>)> = Vec::new();
+
+        // Failed transactions only charge the fee
+        if self.meta.transaction_result == "tesSUCCESS" {
+            let mut changes = balance_changes(&self.meta, address)?;
+            if sent_by_us {
+                // the fee is recorded separately
+                add_change(&mut changes, fee.clone());
+                changes.retain(|change| !change.is_zero());
+            }
+            let (mut incoming, mut outgoing): (Vec<Amount>, Vec<Amount>) = changes.into_iter()
+                .partition(|change| change.quantity > Decimal::ZERO);
+            for amount in &mut outgoing {
+                amount.quantity = -amount.quantity;
+            }
+
+            let destination = tx.destination.as_deref();
+            match tx.transaction_type.as_str() {
+                "Payment" if !sent_by_us && destination == Some(address) => {
+                    let amount = match self.delivered_amount()? {
+                        Some(amount) => amount,
+                        None => incoming.pop().context("missing delivered amount")?,
+                    };
+                    operations.push((Operation::Receive(amount), Some(&tx.account)));
+                }
+                "Payment" if sent_by_us && destination != Some(address) => {
+                    let amount = self.delivered_amount()?.context("missing delivered amount")?;
+                    // a payment delivering another currency converts the
+                    // sent currency first
+                    if let Some(spent) = outgoing.into_iter().find(|spent| spent.effective_currency() != amount.effective_currency()) {
+                        operations.push((Operation::Trade { incoming: amount.clone(), outgoing: spent }, None));
+                    }
+                    operations.push((Operation::Send(amount), destination));
+                }
+                _ => {
+                    // offers, including those of ours consumed by others, and
+                    // payments to ourselves are trades
+                    if incoming.len() == 1 && outgoing.len() == 1 {
+                        operations.push((Operation::Trade { incoming: incoming.remove(0), outgoing: outgoing.remove(0) }, None));
+                    } else {
+                        let counterparty = (!sent_by_us).then_some(tx.account.as_str());
+                        operations.extend(incoming.into_iter().map(|amount| (Operation::Receive(amount), counterparty)));
+                        operations.extend(outgoing.into_iter().map(|amount| (Operation::Send(amount), None)));
+                    }
+                }
+            }
+        }
+
+        let mut transactions: Vec<Transaction> = operations.into_iter().map(|(operation, counterparty)| {
+            let mut transaction = Transaction::new(timestamp, operation);
+            transaction.counterparty = counterparty.map(str::to_owned);
+            transaction
+        }).collect();
+
+        // The fee is attached to the first operation, or recorded on its own
+        if sent_by_us && !fee.is_zero() {
+            match transactions.first_mut() {
+                Some(transaction) => transaction.fee = Some(fee),
+                None => transactions.push(Transaction::fee(timestamp, fee)),
+            }
+        }
+
+        for transaction in &mut transactions {
+            transaction.tx_hash = Some(tx.hash.clone());
+            transaction.blockchain = Some("XRP".to_owned());
+            if tx.transaction_type != "Payment" {
+                transaction.description = Some(tx.transaction_type.clone());
+            }
+        }
+
+        Ok(transactions)
+    }
+}
+
+async fn account_tx(client: &reqwest::Client, settings: &BackendSettings, address: &str, marker: Option<Value>) -> Result<AccountTxResult> {
+    let mut params = json!({
+        "account": address,
+        "ledger_index_min": -1,
+        "ledger_index_max": -1,
+        "limit": PAGE_LIMIT,
+        "forward": true,
+    });
+    if let Some(marker) = marker {
+        params["marker"] = marker;
+    }
+
+    let mut request = client.post(settings.url_or(DEFAULT_RIPPLED_URL))
+        .json(&json!({ "method": "account_tx", "params": [params] }));
+    if let Some(api_key) = &settings.api_key {
+        request = request.bearer_auth(api_key);
+    }
+
+    let response: RpcResponse = request.send().await?.error_for_status()?.json().await?;
+    if response.result["status"] == "error" {
+        return Err(anyhow!("account_tx failed: {}", response.result["error_message"].as_str()
+            .or(response.result["error"].as_str())
+            .unwrap_or("unknown error")));
+    }
+    Ok(serde_json::from_value(response.result)?)
+}
+
+pub(crate) async fn address_transactions(settings: &BackendSettings, address: &str) -> Result<Vec<Transaction>> {
+    let client = settings.http_client()?;
+    let rate_limiter = settings.rate_limiter(100);
+    let mut marker = None;
+    let mut transactions = Vec::new();
+
+    println!("Loading transactions for {}...", address);
+
+    loop {
+        rate_limiter.wait().await;
+        let result = account_tx(&client, settings, address, marker).await?;
+
+        println!("Processing {} transactions", result.transactions.len());
+
+        for account_transaction in result.transactions {
+            if !account_transaction.validated {
+                continue;
+            }
+            match account_transaction.to_transactions(address) {
+                Ok(txs) => transactions.extend(txs),
+                Err(err) => println!("{:?}: {:?}", err, account_transaction.tx),
+            }
+        }
+
+        match result.marker {
+            Some(next) => marker = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(transactions)
+}
+
+pub(crate) fn load_xrp_account_async(source_path: String, options: SyncOptions) -> LoadFuture {
+    Box::pin(async move { address_transactions(&options.backends.xrpl, source_path.trim()).await })
+}
+
+#[distributed_slice(crate::TRANSACTION_SOURCES)]
+static XRP_ACCOUNT: TransactionSource = TransactionSource {
+    id: "XrpAccount",
+    label: "XRP Account",
+    load_async: Some(load_xrp_account_async),
+    ..TransactionSource::DEFAULT
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    const OWN: &str = "rHb9CJAWyB4rj91VRWn96DkukG4bwdtyTh";
+    const OTHER: &str = "rEb8TK3gBgk5auZkwc6sHnwrGVJH8DuaLh";
+    const ISSUER: &str = "rvYAfWj5gh67oV6fW32ZzP3Aw4Eubs59B";
+
+    fn usd(quantity: Decimal) -> Amount {
+        issued_amount(quantity, "USD", ISSUER)
+    }
+
+    fn xrp(quantity: Decimal) -> Amount {
+        Amount::new(quantity, "XRP".to_owned())
+    }
+
+    #[tokio::test]
+    async fn sync_account_transactions() {
+        // two pages of account_tx responses, with a partial payment, a
+        // crossed offer, a failed payment, a token payment and an offer of
+        // ours which is consumed by another account
+        let pages: Vec<Value> = serde_json::from_str(&std::fs::read_to_string("tests/data/xrpl_account_tx.json").unwrap()).unwrap();
+        let marker = pages[0]["result"]["marker"].clone();
+
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "method": "account_tx", "params": [{ "account": OWN, "forward": true }] })))
+            .with_body(pages[0].to_string())
+            .create_async()
+            .await;
+        server.mock("POST", "/")
+            .match_body(Matcher::PartialJson(json!({ "params": [{ "marker": marker }] })))
+            .with_body(pages[1].to_string())
+            .create_async()
+            .await;
+
+        let settings = BackendSettings {
+            url: Some(server.url()),
+            min_interval_ms: Some(0),
+            ..Default::default()
+        };
+        let transactions = address_transactions(&settings, OWN).await.unwrap();
+        let operations: Vec<(&Operation, Option<&Amount>, Option<&str>)> = transactions.iter()
+            .map(|tx| (&tx.operation, tx.fee.as_ref(), tx.counterparty.as_deref()))
+            .collect();
+
+        let offer_fee = xrp(Decimal::new(12, 6));
+        let payment_fee = xrp(Decimal::new(10, 6));
+        assert_eq!(operations, vec![
+            // only the delivered amount of the partial payment is received
+            (&Operation::Receive(xrp(Decimal::from(10))), None, Some(OTHER)),
+            (&Operation::Trade { incoming: usd(Decimal::from(20)), outgoing: xrp(Decimal::from(50)) }, Some(&offer_fee), None),
+            (&Operation::Fee(payment_fee.clone()), None, None),
+            (&Operation::Send(usd(Decimal::from(5))), Some(&payment_fee), Some(OTHER)),
+            (&Operation::Fee(offer_fee.clone()), None, None),
+            (&Operation::Trade { incoming: xrp(Decimal::from(40)), outgoing: usd(Decimal::from(15)) }, None, None),
+        ]);
+
+        let hashes: Vec<&str> = pages.iter()
+            .flat_map(|page| page["result"]["transactions"].as_array().unwrap())
+            .map(|transaction| transaction["tx"]["hash"].as_str().unwrap())
+            .collect();
+        let tx_hashes: Vec<&str> = transactions.iter().map(|tx| tx.tx_hash.as_deref().unwrap()).collect();
+        assert_eq!(tx_hashes, hashes);
+        assert_eq!(transactions[1].description.as_deref(), Some("OfferCreate"));
+        assert_eq!(transactions[5].description.as_deref(), Some("OfferCreate"));
+        assert_eq!(transactions[3].blockchain.as_deref(), Some("XRP"));
+    }
+
+    /// Syncs the account in `RACCOIN_XRPL_ACCOUNT` from a public rippled
+    /// server, to check that live responses are understood.
+    #[tokio::test]
+    #[ignore = "requires network access and RACCOIN_XRPL_ACCOUNT"]
+    async fn sync_account_from_rippled() {
+        let address = std::env::var("RACCOIN_XRPL_ACCOUNT").unwrap();
+        let settings = BackendSettings::default();
+        let transactions = address_transactions(&settings, &address).await.unwrap();
+        assert!(!transactions.is_empty());
+        assert!(transactions.iter().all(|tx| tx.tx_hash.is_some()));
+    }
+
+    #[test]
+    fn decode_currency_codes() {
+        assert_eq!(currency_code("USD"), "USD");
+        assert_eq!(currency_code("534F4C4F00000000000000000000000000000000"), "SOLO");
+    }
+}